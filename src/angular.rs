@@ -2,10 +2,10 @@
 //!
 //! Provides a collection of utilities to work with Angular configuration.
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use log::info;
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::BufReader;
 use std::path::PathBuf;
@@ -42,12 +42,364 @@ pub struct Project {
     pub root: Option<String>,
     /// The root of the source files, assets and index.html file structure.
     pub source_root: Option<String>,
+    /// Project i18n options
+    pub i18n: Option<I18nConfig>,
+    /// Tool configuration of the project targets, i.e. `build` or `serve`.
+    pub architect: Option<BTreeMap<String, Target>>,
     // todo: schematics
-    // todo: i18n
-    // todo: architect
     // todo: targets
 }
 
+/// Browser build output of the project
+#[derive(Debug, PartialEq)]
+pub struct BuildOutput {
+    /// Output directory, relative to the workspace root.
+    pub dir: PathBuf,
+    /// Base url the application is served from.
+    pub base_href: String,
+    /// URL the assets are loaded from.
+    pub deploy_url: Option<String>,
+    pub locale: Option<String>,
+}
+
+/// Joins the url parts with a single slash.
+fn url_join(base: &str, path: &str) -> String {
+    format!(
+        "{}/{}",
+        base.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    )
+}
+
+impl Project {
+    /// Returns the options of the target with the configuration applied,
+    /// the default configuration is used when none is provided.
+    pub fn target_options(
+        &self,
+        target: &str,
+        configuration: Option<&str>,
+    ) -> Option<TargetOptions> {
+        let target = self.architect.as_ref()?.get(target)?;
+        let mut options = target.options.clone().unwrap_or_default();
+
+        let configuration = configuration.or(target.default_configuration.as_deref());
+        for name in configuration.iter().flat_map(|names| names.split(',')) {
+            let overrides = target
+                .configurations
+                .as_ref()
+                .and_then(|configurations| configurations.get(name.trim()));
+            if let Some(overrides) = overrides {
+                options.merge(overrides);
+            }
+        }
+
+        Some(options)
+    }
+
+    /// Returns the locales of the localized build, the source locale first.
+    pub fn build_locales(&self, options: &TargetOptions) -> Vec<String> {
+        let i18n = match &self.i18n {
+            Some(i18n) => i18n,
+            None => return vec![],
+        };
+
+        match &options.localize {
+            Some(Localize::Locales(locales)) => locales.clone(),
+            Some(Localize::All(true)) => {
+                let mut locales = vec![i18n.source_locale_code().to_string()];
+                locales.extend(
+                    i18n.locales
+                        .iter()
+                        .flat_map(|locales| locales.keys().cloned()),
+                );
+                locales
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Resolves the output directories of the build target, one per locale for the localized builds.
+    pub fn build_outputs(&self, configuration: Option<&str>) -> Result<Vec<BuildOutput>> {
+        let options = self
+            .target_options("build", configuration)
+            .ok_or_else(|| anyhow!("Project has no `build` target"))?;
+        let dir = options
+            .output_path
+            .as_ref()
+            .ok_or_else(|| anyhow!("Build target has no `outputPath` option"))?
+            .browser_dir(
+                self.architect
+                    .as_ref()
+                    .and_then(|architect| architect.get("build")),
+            );
+        let base_href = options.base_href.as_deref().unwrap_or("/");
+
+        let locales = self.build_locales(&options);
+        if locales.is_empty() {
+            return Ok(vec![BuildOutput {
+                dir,
+                base_href: base_href.to_string(),
+                deploy_url: options.deploy_url.clone(),
+                locale: None,
+            }]);
+        }
+
+        let i18n = self.i18n.as_ref();
+        Ok(locales
+            .into_iter()
+            .map(|locale| {
+                let locale_href = i18n
+                    .map(|i18n| i18n.locale_base_href(&locale))
+                    .unwrap_or(&locale);
+                BuildOutput {
+                    dir: dir.join(&locale),
+                    base_href: url_join(base_href, &format!("{}/", locale_href.trim_matches('/'))),
+                    deploy_url: options.deploy_url.clone(),
+                    locale: Some(locale.clone()),
+                }
+            })
+            .collect())
+    }
+}
+
+/// Project target
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Target {
+    /// The builder used for this package.
+    pub builder: Option<String>,
+    /// Default options of the target.
+    pub options: Option<TargetOptions>,
+    /// A map of alternative target options.
+    pub configurations: Option<BTreeMap<String, TargetOptions>>,
+    /// A default named configuration to use when a target configuration is not provided.
+    pub default_configuration: Option<String>,
+}
+
+impl Target {
+    /// Checks whether the target uses the application builder, which writes the browser files
+    /// to the `browser` directory of the output path.
+    pub fn is_application_builder(&self) -> bool {
+        self.builder
+            .as_deref()
+            .map_or(false, |builder| builder.ends_with(":application"))
+    }
+}
+
+/// Target options used by the commands, the other options are ignored
+#[derive(Clone, Default, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetOptions {
+    /// The full path for the new output directory, relative to the workspace root.
+    pub output_path: Option<OutputPath>,
+    /// Base url for the application being built.
+    pub base_href: Option<String>,
+    /// URL where files will be deployed.
+    pub deploy_url: Option<String>,
+    /// Translates the application for the locales.
+    pub localize: Option<Localize>,
+    /// Budget thresholds of the output file sizes.
+    pub budgets: Option<Vec<Budget>>,
+}
+
+impl TargetOptions {
+    /// Overrides the options with the ones set in the configuration.
+    pub fn merge(&mut self, other: &TargetOptions) {
+        if other.output_path.is_some() {
+            self.output_path = other.output_path.clone();
+        }
+        if other.base_href.is_some() {
+            self.base_href = other.base_href.clone();
+        }
+        if other.deploy_url.is_some() {
+            self.deploy_url = other.deploy_url.clone();
+        }
+        if other.localize.is_some() {
+            self.localize = other.localize.clone();
+        }
+        if other.budgets.is_some() {
+            self.budgets = other.budgets.clone();
+        }
+    }
+}
+
+/// Output path as a single directory or as the application builder locations
+#[derive(Clone, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum OutputPath {
+    Path(String),
+    Options {
+        /// Base output directory.
+        base: String,
+        /// Name of the directory for the browser build, `browser` by default.
+        browser: Option<String>,
+    },
+}
+
+impl OutputPath {
+    /// Returns the directory with the browser build of the target, relative to the workspace root.
+    pub fn browser_dir(&self, target: Option<&Target>) -> PathBuf {
+        match self {
+            OutputPath::Path(path) if target.map_or(false, Target::is_application_builder) => {
+                PathBuf::from(path).join("browser")
+            }
+            OutputPath::Path(path) => PathBuf::from(path),
+            OutputPath::Options { base, browser } => {
+                PathBuf::from(base).join(browser.as_deref().unwrap_or("browser"))
+            }
+        }
+    }
+}
+
+/// Size budget of the build output, the sizes are like `500kb` or `10%` of the baseline
+#[derive(Clone, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Budget {
+    /// The type of budget.
+    #[serde(rename = "type")]
+    pub budget_type: BudgetType,
+    /// The name of the bundle.
+    pub name: Option<String>,
+    /// The baseline size for comparison.
+    pub baseline: Option<String>,
+    /// The maximum threshold for warning relative to the baseline.
+    pub maximum_warning: Option<String>,
+    /// The maximum threshold for error relative to the baseline.
+    pub maximum_error: Option<String>,
+    /// The minimum threshold for warning relative to the baseline.
+    pub minimum_warning: Option<String>,
+    /// The minimum threshold for error relative to the baseline.
+    pub minimum_error: Option<String>,
+    /// The threshold for warning relative to the baseline (min & max).
+    pub warning: Option<String>,
+    /// The threshold for error relative to the baseline (min & max).
+    pub error: Option<String>,
+}
+
+/// Files the budget applies to
+#[derive(Clone, Copy, PartialEq, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum BudgetType {
+    All,
+    AllScript,
+    Any,
+    AnyScript,
+    AnyComponentStyle,
+    Bundle,
+    Initial,
+}
+
+/// All locales or the list of locales to build
+#[derive(Clone, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum Localize {
+    All(bool),
+    Locales(Vec<String>),
+}
+
+/// Project i18n options
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct I18nConfig {
+    /// Specifies the source locale of the application.
+    pub source_locale: Option<SourceLocale>,
+    /// Localization options for the target locales.
+    pub locales: Option<BTreeMap<String, LocaleConfig>>,
+}
+
+impl I18nConfig {
+    /// Returns the base HREF of the locale, the locale code by default.
+    pub fn locale_base_href<'a>(&'a self, locale: &'a str) -> &'a str {
+        let base_href = if locale == self.source_locale_code() {
+            match &self.source_locale {
+                Some(SourceLocale::Options { base_href, .. }) => base_href.as_deref(),
+                _ => None,
+            }
+        } else {
+            match self
+                .locales
+                .as_ref()
+                .and_then(|locales| locales.get(locale))
+            {
+                Some(LocaleConfig::Options { base_href, .. }) => base_href.as_deref(),
+                _ => None,
+            }
+        };
+
+        base_href.unwrap_or(locale)
+    }
+
+    /// Returns the source locale code, `en-US` by default.
+    pub fn source_locale_code(&self) -> &str {
+        match &self.source_locale {
+            Some(SourceLocale::Code(code)) => code,
+            Some(SourceLocale::Options {
+                code: Some(code), ..
+            }) => code,
+            _ => "en-US",
+        }
+    }
+}
+
+/// Source locale as a code or as localization options
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum SourceLocale {
+    Code(String),
+    #[serde(rename_all = "camelCase")]
+    Options {
+        /// Specifies the locale code of the source locale
+        code: Option<String>,
+        /// HTML base HREF to use for the locale (defaults to the locale code)
+        base_href: Option<String>,
+    },
+}
+
+/// Localization files or options for a single locale
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum LocaleConfig {
+    Translation(TranslationFiles),
+    #[serde(rename_all = "camelCase")]
+    Options {
+        /// Localization file(s) to use for i18n
+        translation: Option<TranslationFiles>,
+        /// HTML base HREF to use for the locale (defaults to the locale code)
+        base_href: Option<String>,
+    },
+}
+
+impl LocaleConfig {
+    /// Returns the paths to the localization files.
+    pub fn translations(&self) -> Vec<&str> {
+        match self {
+            LocaleConfig::Translation(files) => files.paths(),
+            LocaleConfig::Options {
+                translation: Some(files),
+                ..
+            } => files.paths(),
+            _ => vec![],
+        }
+    }
+}
+
+/// Single or multiple localization files
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum TranslationFiles {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl TranslationFiles {
+    pub fn paths(&self) -> Vec<&str> {
+        match self {
+            TranslationFiles::Single(path) => vec![path.as_str()],
+            TranslationFiles::Multiple(paths) => paths.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
 /// Types of the workspace projects
 #[derive(PartialEq, Deserialize, Debug)]
 #[serde(rename_all = "lowercase")]
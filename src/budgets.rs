@@ -0,0 +1,617 @@
+//! # Budget utils
+//!
+//! Measures the build output against the size budgets of the Angular build configuration,
+//! without running the build again.
+
+use anyhow::{anyhow, Result};
+use brotli2::write::BrotliEncoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::angular::{Budget, BudgetType};
+use crate::utils::{format_size, strip_hash};
+
+/// Extensions of the measured build files
+const BUILD_EXTENSIONS: [&str; 3] = ["js", "mjs", "css"];
+
+/// Extensions of the component style sources
+const STYLE_EXTENSIONS: [&str; 4] = ["css", "scss", "sass", "less"];
+
+/// Initial bundles of the builds without the index page
+const INITIAL_BUNDLES: [&str; 6] = [
+    "main",
+    "polyfills",
+    "runtime",
+    "scripts",
+    "styles",
+    "vendor",
+];
+
+/// Saved sizes by the project and the budget label
+pub type Baseline = BTreeMap<String, BTreeMap<String, Sizes>>;
+
+/// Size compared against the budgets
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SizeKind {
+    Raw,
+    Gzip,
+    Brotli,
+}
+
+impl Default for SizeKind {
+    fn default() -> Self {
+        SizeKind::Raw
+    }
+}
+
+impl FromStr for SizeKind {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "raw" => Ok(SizeKind::Raw),
+            "gzip" => Ok(SizeKind::Gzip),
+            "brotli" => Ok(SizeKind::Brotli),
+            _ => Err(anyhow!("Unknown size `{}`", value)),
+        }
+    }
+}
+
+/// Raw and compressed sizes in bytes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Sizes {
+    pub raw: u64,
+    pub gzip: u64,
+    pub brotli: u64,
+}
+
+impl Sizes {
+    /// Measures the content, compressed with the best quality as the precompressed files are.
+    pub fn measure(content: &[u8]) -> io::Result<Sizes> {
+        let mut gzip = GzEncoder::new(vec![], Compression::best());
+        gzip.write_all(content)?;
+        let mut brotli = BrotliEncoder::new(vec![], 11);
+        brotli.write_all(content)?;
+
+        Ok(Sizes {
+            raw: content.len() as u64,
+            gzip: gzip.finish()?.len() as u64,
+            brotli: brotli.finish()?.len() as u64,
+        })
+    }
+
+    pub fn get(&self, kind: SizeKind) -> u64 {
+        match kind {
+            SizeKind::Raw => self.raw,
+            SizeKind::Gzip => self.gzip,
+            SizeKind::Brotli => self.brotli,
+        }
+    }
+
+    fn add(&mut self, other: &Sizes) {
+        self.raw += other.raw;
+        self.gzip += other.gzip;
+        self.brotli += other.brotli;
+    }
+}
+
+/// Measured file, the name is relative to the measured directory
+#[derive(Clone, Debug, PartialEq)]
+pub struct BuildFile {
+    pub name: String,
+    pub sizes: Sizes,
+}
+
+impl BuildFile {
+    fn is_script(&self) -> bool {
+        self.name.ends_with(".js") || self.name.ends_with(".mjs")
+    }
+}
+
+/// Lists the files of the directory recursively, the hidden ones and `node_modules` are skipped.
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if name.starts_with('.') || name == "node_modules" {
+            continue;
+        }
+        if path.is_dir() {
+            walk(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Measures the files of the directory with one of the extensions.
+fn measure_files(dir: &Path, extensions: &[&str], suffix: &str) -> Result<Vec<BuildFile>> {
+    let mut paths = vec![];
+    walk(dir, &mut paths).map_err(|err| anyhow!("Error reading `{}`: {}", dir.display(), err))?;
+    paths.sort();
+
+    let mut files = vec![];
+    for path in paths {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let matches = extensions
+            .iter()
+            .any(|extension| name.ends_with(&format!("{}.{}", suffix, extension)));
+        if !matches {
+            continue;
+        }
+
+        let content = fs::read(&path)?;
+        let name = path
+            .strip_prefix(dir)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        files.push(BuildFile {
+            name,
+            sizes: Sizes::measure(&content)?,
+        });
+    }
+    Ok(files)
+}
+
+/// Measures the scripts and the stylesheets of the build output.
+pub fn read_build_files(dist_dir: &Path) -> Result<Vec<BuildFile>> {
+    measure_files(dist_dir, &BUILD_EXTENSIONS, "")
+}
+
+/// Measures the component style sources, an approximation of the compiled component styles
+/// which are embedded in the scripts.
+pub fn read_component_styles(source_root: &Path) -> Result<Vec<BuildFile>> {
+    measure_files(source_root, &STYLE_EXTENSIONS, ".component")
+}
+
+/// Returns the build files loaded by the index page: the module scripts, the stylesheets
+/// and the preloaded modules.
+pub fn initial_files(index_html: &str, base_href: &str, deploy_url: Option<&str>) -> Vec<String> {
+    let tag = Regex::new(r"(?is)<(script|link)\b[^>]*>").unwrap();
+    let attribute = Regex::new(r#"(?is)\b(src|href|rel)\s*=\s*["']([^"']*)["']"#).unwrap();
+
+    let mut files = vec![];
+    for captures in tag.captures_iter(index_html) {
+        let element = captures.get(0).unwrap().as_str();
+        let attributes: HashMap<String, &str> = attribute
+            .captures_iter(element)
+            .map(|captures| {
+                (
+                    captures[1].to_lowercase(),
+                    captures.get(2).unwrap().as_str(),
+                )
+            })
+            .collect();
+
+        let url = if captures[1].eq_ignore_ascii_case("script") {
+            // the legacy scripts are not loaded by the modern browsers
+            if element.to_lowercase().contains("nomodule") {
+                continue;
+            }
+            attributes.get("src")
+        } else {
+            let rel = attributes.get("rel").map(|rel| rel.to_lowercase());
+            match rel.as_deref() {
+                Some("stylesheet") | Some("modulepreload") => attributes.get("href"),
+                _ => None,
+            }
+        };
+
+        if let Some(name) = url.and_then(|url| resolve_file(url, base_href, deploy_url)) {
+            if !files.contains(&name) {
+                files.push(name);
+            }
+        }
+    }
+    files
+}
+
+/// Resolves the url of the index page to the build file name.
+fn resolve_file(url: &str, base_href: &str, deploy_url: Option<&str>) -> Option<String> {
+    let url = url.split(['?', '#']).next().unwrap_or_default();
+    let url = match deploy_url {
+        Some(deploy_url) if !deploy_url.is_empty() && url.starts_with(deploy_url) => {
+            &url[deploy_url.len()..]
+        }
+        _ => url.strip_prefix(base_href).unwrap_or(url),
+    };
+    if url.is_empty() || url.contains("://") || url.starts_with("//") {
+        return None;
+    }
+    Some(
+        url.trim_start_matches("./")
+            .trim_start_matches('/')
+            .to_string(),
+    )
+}
+
+/// Checks whether the build file is one of the initial bundles, for the builds without the index page.
+fn is_initial_bundle(name: &str) -> bool {
+    let stem = strip_hash(name);
+    let stem = stem.split('.').next().unwrap_or_default();
+    INITIAL_BUNDLES
+        .iter()
+        .any(|bundle| stem == *bundle || stem.starts_with(&format!("{}-es", bundle)))
+}
+
+/// Checks whether the build file is the named bundle, i.e. `main.3f2a1b4c5d6e7f80.js` for `main`.
+fn is_bundle(name: &str, bundle: &str) -> bool {
+    let file_name = name.rsplit('/').next().unwrap_or_default();
+    strip_hash(file_name)
+        .rsplit_once('.')
+        .map_or(false, |(stem, _)| stem == bundle)
+}
+
+/// Budget severity
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// Size limit of the budget
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Threshold {
+    /// Limit in bytes
+    pub limit: f64,
+    /// Maximum or minimum size
+    pub maximum: bool,
+    pub severity: Severity,
+}
+
+/// Parses the budget size, i.e. `500kb`, `1.5mb` or `10%`. With the baseline the size is relative
+/// to it, added for the maximum thresholds and subtracted for the minimum ones.
+pub fn parse_size(input: &str, baseline: Option<&str>, maximum: bool) -> Result<f64> {
+    let invalid = || anyhow!("Invalid budget size `{}`", input);
+    let input = input.trim().to_lowercase();
+    let (number, unit) = match input.find(|c: char| !c.is_ascii_digit() && c != '.') {
+        Some(index) => input.split_at(index),
+        None => (input.as_str(), ""),
+    };
+    let value: f64 = number.parse().map_err(|_| invalid())?;
+
+    let baseline = match baseline {
+        Some(baseline) => parse_size(baseline, None, true)?,
+        None => 0.0,
+    };
+    let value = match unit.trim() {
+        "" | "b" => value,
+        "kb" => value * 1024.0,
+        "mb" => value * 1024.0 * 1024.0,
+        "gb" => value * 1024.0 * 1024.0 * 1024.0,
+        "%" if baseline > 0.0 => baseline * value / 100.0,
+        "%" => return Err(anyhow!("Budget size `{}` requires a baseline", input)),
+        _ => return Err(invalid()),
+    };
+
+    if baseline == 0.0 {
+        Ok(value)
+    } else if maximum {
+        Ok(baseline + value)
+    } else {
+        Ok(baseline - value)
+    }
+}
+
+/// Returns the thresholds of the budget, `warning` and `error` set both the minimum and the maximum.
+pub fn thresholds(budget: &Budget) -> Result<Vec<Threshold>> {
+    let limits = [
+        (&budget.maximum_warning, true, Severity::Warning),
+        (&budget.maximum_error, true, Severity::Error),
+        (&budget.minimum_warning, false, Severity::Warning),
+        (&budget.minimum_error, false, Severity::Error),
+        (&budget.warning, false, Severity::Warning),
+        (&budget.warning, true, Severity::Warning),
+        (&budget.error, false, Severity::Error),
+        (&budget.error, true, Severity::Error),
+    ];
+
+    let mut thresholds = vec![];
+    for (input, maximum, severity) in limits.iter() {
+        if let Some(input) = input {
+            thresholds.push(Threshold {
+                limit: parse_size(input, budget.baseline.as_deref(), *maximum)?,
+                maximum: *maximum,
+                severity: *severity,
+            });
+        }
+    }
+    Ok(thresholds)
+}
+
+/// Result of the budget check
+#[derive(Clone, Debug, PartialEq)]
+pub enum Status {
+    Ok,
+    Warning(String),
+    Error(String),
+}
+
+impl Status {
+    fn name(&self) -> &'static str {
+        match self {
+            Status::Ok => "ok",
+            Status::Warning(_) => "warning",
+            Status::Error(_) => "error",
+        }
+    }
+}
+
+/// Checks the size against the thresholds, the errors take precedence over the warnings.
+pub fn evaluate(thresholds: &[Threshold], size: u64) -> Status {
+    let mut status = Status::Ok;
+    for threshold in thresholds {
+        let difference = if threshold.maximum {
+            size as f64 - threshold.limit
+        } else {
+            threshold.limit - size as f64
+        };
+        if difference <= 0.0 {
+            continue;
+        }
+
+        let message = format!(
+            "{} budget of {} by {}",
+            if threshold.maximum {
+                "exceeded maximum"
+            } else {
+                "failed to meet minimum"
+            },
+            format_size(threshold.limit as u64),
+            format_size(difference.ceil() as u64)
+        );
+        match (threshold.severity, &status) {
+            (Severity::Error, Status::Error(_)) => {}
+            (Severity::Error, _) => status = Status::Error(message),
+            (Severity::Warning, Status::Ok) => status = Status::Warning(message),
+            _ => {}
+        }
+    }
+    status
+}
+
+/// Measured budget
+#[derive(Clone, Debug, PartialEq)]
+pub struct Row {
+    /// Budget type and the bundle or the file name, i.e. `bundle main` or `anyScript main.js`
+    pub label: String,
+    pub sizes: Sizes,
+    pub status: Status,
+}
+
+/// Labels of the files without the content hashes, unless the names collide.
+fn file_labels(files: &[BuildFile]) -> HashMap<&str, String> {
+    let stripped = |name: &str| match name.rsplit_once('/') {
+        Some((dir, file_name)) => format!("{}/{}", dir, strip_hash(file_name)),
+        None => strip_hash(name),
+    };
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for file in files {
+        *counts.entry(stripped(&file.name)).or_default() += 1;
+    }
+    files
+        .iter()
+        .map(|file| {
+            let label = stripped(&file.name);
+            if counts[&label] > 1 {
+                (file.name.as_str(), file.name.clone())
+            } else {
+                (file.name.as_str(), label)
+            }
+        })
+        .collect()
+}
+
+/// Checks the files one by one, the failing files are listed or the largest one otherwise.
+fn check_each(
+    name: &str,
+    files: &[&BuildFile],
+    thresholds: &[Threshold],
+    kind: SizeKind,
+    labels: &HashMap<&str, String>,
+) -> Vec<Row> {
+    let mut rows: Vec<Row> = files
+        .iter()
+        .map(|file| Row {
+            label: format!("{} {}", name, labels[file.name.as_str()]),
+            sizes: file.sizes,
+            status: evaluate(thresholds, file.sizes.get(kind)),
+        })
+        .collect();
+    rows.sort_by(|a, b| {
+        b.sizes
+            .get(kind)
+            .cmp(&a.sizes.get(kind))
+            .then_with(|| a.label.cmp(&b.label))
+    });
+
+    let failing: Vec<Row> = rows
+        .iter()
+        .filter(|row| row.status != Status::Ok)
+        .cloned()
+        .collect();
+    if failing.is_empty() {
+        rows.into_iter().take(1).collect()
+    } else {
+        failing
+    }
+}
+
+fn total<'a>(files: impl Iterator<Item = &'a BuildFile>) -> Sizes {
+    let mut sizes = Sizes::default();
+    for file in files {
+        sizes.add(&file.sizes);
+    }
+    sizes
+}
+
+/// Build output measured by the budgets
+#[derive(Debug, Default)]
+pub struct BuildStats {
+    pub files: Vec<BuildFile>,
+    /// Names of the files loaded by the index page, the initial bundles by name if empty.
+    pub initial: Vec<String>,
+    pub component_styles: Vec<BuildFile>,
+}
+
+/// Checks the budgets, one row per budget except for the `any` budgets.
+pub fn check_budgets(budgets: &[Budget], stats: &BuildStats, kind: SizeKind) -> Result<Vec<Row>> {
+    let labels = file_labels(&stats.files);
+    let style_labels = file_labels(&stats.component_styles);
+
+    let mut rows = vec![];
+    for budget in budgets {
+        let thresholds = thresholds(budget)?;
+        let row = |label: String, sizes: Sizes| Row {
+            label,
+            status: evaluate(&thresholds, sizes.get(kind)),
+            sizes,
+        };
+
+        match budget.budget_type {
+            BudgetType::All => rows.push(row(String::from("all"), total(stats.files.iter()))),
+            BudgetType::AllScript => rows.push(row(
+                String::from("allScript"),
+                total(stats.files.iter().filter(|file| file.is_script())),
+            )),
+            BudgetType::Initial => {
+                let initial = stats.files.iter().filter(|file| {
+                    if stats.initial.is_empty() {
+                        is_initial_bundle(&file.name)
+                    } else {
+                        stats.initial.contains(&file.name)
+                    }
+                });
+                rows.push(row(String::from("initial"), total(initial)));
+            }
+            BudgetType::Bundle => {
+                let name = budget
+                    .name
+                    .as_deref()
+                    .ok_or_else(|| anyhow!("Budget of type `bundle` has no `name`"))?;
+                let files: Vec<_> = stats
+                    .files
+                    .iter()
+                    .filter(|file| is_bundle(&file.name, name))
+                    .collect();
+                let label = format!("bundle {}", name);
+                if files.is_empty() {
+                    rows.push(Row {
+                        label,
+                        sizes: Sizes::default(),
+                        status: Status::Warning(format!("no file matches the bundle `{}`", name)),
+                    });
+                } else {
+                    rows.push(row(label, total(files.into_iter())));
+                }
+            }
+            BudgetType::Any => {
+                let files: Vec<_> = stats.files.iter().collect();
+                rows.extend(check_each("any", &files, &thresholds, kind, &labels));
+            }
+            BudgetType::AnyScript => {
+                let files: Vec<_> = stats.files.iter().filter(|file| file.is_script()).collect();
+                rows.extend(check_each("anyScript", &files, &thresholds, kind, &labels));
+            }
+            BudgetType::AnyComponentStyle => {
+                let files: Vec<_> = stats.component_styles.iter().collect();
+                rows.extend(check_each(
+                    "anyComponentStyle",
+                    &files,
+                    &thresholds,
+                    kind,
+                    &style_labels,
+                ));
+            }
+        }
+    }
+    Ok(rows)
+}
+
+/// Reads the baseline file saved with `--save-baseline`.
+pub fn read_baseline(path: &Path) -> Result<Baseline> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| anyhow!("Error reading `{}`: {}", path.display(), err))?;
+    serde_json::from_str(&content)
+        .map_err(|err| anyhow!("Invalid baseline `{}`: {}", path.display(), err))
+}
+
+pub fn write_baseline(path: &Path, baseline: &Baseline) -> Result<()> {
+    let content = serde_json::to_string_pretty(baseline)?;
+    fs::write(path, content + "\n")
+        .map_err(|err| anyhow!("Error writing `{}`: {}", path.display(), err))
+}
+
+/// Formats the size difference with the sign, i.e. `+1.5 KB`.
+pub fn format_delta(size: u64, baseline: u64) -> String {
+    if size == baseline {
+        format_size(0)
+    } else if size > baseline {
+        format!("+{}", format_size(size - baseline))
+    } else {
+        format!("-{}", format_size(baseline - size))
+    }
+}
+
+/// Renders the table of the rows, the delta of the compared size against the baseline if any.
+pub fn render_table(
+    rows: &[Row],
+    baseline: Option<&BTreeMap<String, Sizes>>,
+    kind: SizeKind,
+) -> String {
+    let mut lines = vec![[
+        String::from("Budget"),
+        String::from("Raw"),
+        String::from("Gzip"),
+        String::from("Brotli"),
+        String::from("Delta"),
+        String::from("Status"),
+    ]];
+    for row in rows {
+        let delta = match baseline {
+            Some(baseline) => match baseline.get(&row.label) {
+                Some(sizes) => format_delta(row.sizes.get(kind), sizes.get(kind)),
+                None => String::from("new"),
+            },
+            None => String::from("-"),
+        };
+        lines.push([
+            row.label.clone(),
+            format_size(row.sizes.raw),
+            format_size(row.sizes.gzip),
+            format_size(row.sizes.brotli),
+            delta,
+            row.status.name().to_string(),
+        ]);
+    }
+
+    let mut widths = [0; 6];
+    for line in &lines {
+        for (width, cell) in widths.iter_mut().zip(line.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut table = String::new();
+    for line in &lines {
+        let mut text = format!("{:<width$}", line[0], width = widths[0]);
+        // the sizes are right aligned
+        for (cell, width) in line[1..5].iter().zip(widths[1..5].iter()) {
+            text.push_str(&format!("  {:>width$}", cell, width = width));
+        }
+        text.push_str(&format!("  {}", line[5]));
+        table.push_str(text.trim_end());
+        table.push('\n');
+    }
+    table
+}
@@ -0,0 +1,319 @@
+//! # Dependency utils
+//!
+//! Verifies that the `package.json` files of the workspace projects
+//! are consistent with the root `package.json` file.
+
+use serde_json::Value;
+use std::fmt;
+
+/// Dependency sections of the `package.json` file
+pub const SECTIONS: [&str; 4] = [
+    "dependencies",
+    "devDependencies",
+    "peerDependencies",
+    "optionalDependencies",
+];
+
+/// Dependency consistency issue
+#[derive(Debug, PartialEq)]
+pub enum Issue {
+    /// Version range does not accept the version used by the root package.
+    Conflict {
+        section: String,
+        name: String,
+        range: String,
+        expected: String,
+    },
+    /// Peer dependency is not installed by the root package.
+    MissingPeer { name: String, range: String },
+    /// Package is declared in multiple sections.
+    Duplicate { name: String, sections: Vec<String> },
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::Conflict {
+                section,
+                name,
+                range,
+                expected,
+            } => write!(
+                f,
+                "{} `{}@{}` conflicts with root `{}`",
+                section, name, range, expected
+            ),
+            Issue::MissingPeer { name, range } => write!(
+                f,
+                "peer dependency `{}@{}` is missing in the root package",
+                name, range
+            ),
+            Issue::Duplicate { name, sections } => write!(
+                f,
+                "`{}` is declared in multiple sections: {}",
+                name,
+                sections.join(", ")
+            ),
+        }
+    }
+}
+
+type Version = (u64, u64, u64);
+
+/// Parses a (partial) version, missing or wildcard parts are returned as `None`
+fn parse_partial(text: &str) -> Option<Vec<Option<u64>>> {
+    let text = text.trim().trim_start_matches(['v', '=']);
+    let text = text.split(['-', '+']).next()?;
+
+    if text.is_empty() {
+        return None;
+    }
+
+    text.split('.')
+        .take(3)
+        .map(|part| match part {
+            "x" | "X" | "*" => Some(None),
+            _ => part.parse().ok().map(Some),
+        })
+        .collect()
+}
+
+fn to_version(parts: &[Option<u64>]) -> Version {
+    let get = |index: usize| parts.get(index).copied().flatten().unwrap_or(0);
+    (get(0), get(1), get(2))
+}
+
+/// Number of leading version parts that are specified
+fn precision(parts: &[Option<u64>]) -> usize {
+    parts.iter().take_while(|part| part.is_some()).count()
+}
+
+/// Returns the first version after the one with the given number of leading parts
+fn next_version((major, minor, patch): Version, level: usize) -> Option<Version> {
+    match level {
+        0 => None,
+        1 => Some((major + 1, 0, 0)),
+        2 => Some((major, minor + 1, 0)),
+        _ => Some((major, minor, patch + 1)),
+    }
+}
+
+/// Returns the `[min, max)` interval of the comparator, `None` for unsupported syntax
+fn comparator(text: &str) -> Option<(Version, Option<Version>)> {
+    let index = text.find(|c: char| c.is_ascii_digit() || c == 'x' || c == 'X' || c == '*')?;
+    let (op, rest) = text.split_at(index);
+
+    let parts = parse_partial(rest)?;
+    let version = to_version(&parts);
+    let (major, minor, _) = version;
+    let specified = precision(&parts);
+
+    match op.trim() {
+        "" | "=" | "v" => Some((version, next_version(version, specified))),
+        "^" => {
+            let level = if major > 0 || specified < 2 {
+                1
+            } else if minor > 0 || specified < 3 {
+                2
+            } else {
+                3
+            };
+            Some((version, next_version(version, level)))
+        }
+        "~" => Some((version, next_version(version, specified.clamp(1, 2)))),
+        ">=" => Some((version, None)),
+        ">" => Some((next_version(version, specified.max(1))?, None)),
+        "<" => Some(((0, 0, 0), Some(version))),
+        "<=" => Some(((0, 0, 0), next_version(version, specified.max(1)))),
+        _ => None,
+    }
+}
+
+fn satisfies_set(version: Version, set: &str) -> Option<bool> {
+    // hyphen ranges, i.e. `1.2.3 - 2.3`
+    if let Some((low, high)) = set.split_once(" - ") {
+        let (min, _) = comparator(low.trim())?;
+        let high = parse_partial(high)?;
+        let max = next_version(to_version(&high), precision(&high));
+        return Some(version >= min && max.map_or(true, |max| version < max));
+    }
+
+    let mut result = true;
+    let mut tokens = set.split_whitespace().peekable();
+
+    while let Some(token) = tokens.next() {
+        // operators may be separated from the version, i.e. `>= 1.2.3`
+        let token = match tokens.peek() {
+            Some(next) if token.chars().all(|c| "<>=^~".contains(c)) => {
+                let joined = format!("{}{}", token, next);
+                tokens.next();
+                joined
+            }
+            _ => token.to_string(),
+        };
+
+        let (min, max) = comparator(&token)?;
+        result &= version >= min && max.map_or(true, |max| version < max);
+    }
+
+    Some(result)
+}
+
+/// Checks whether the version satisfies the npm range.
+/// Returns `None` when the range is not a semver range (tags, urls, etc).
+pub fn satisfies(version: &str, range: &str) -> Option<bool> {
+    let version = to_version(&parse_partial(version)?);
+    let range = range.trim();
+
+    if range.is_empty() || range == "*" || range == "latest" {
+        return Some(true);
+    }
+
+    let mut result = false;
+    for set in range.split("||") {
+        result |= satisfies_set(version, set.trim())?;
+    }
+
+    Some(result)
+}
+
+/// Returns the lowest version accepted by the npm range.
+pub fn min_version(range: &str) -> Option<String> {
+    let mut versions: Vec<Version> = vec![];
+
+    for set in range.split("||") {
+        let first = set.split_whitespace().next()?;
+        let (min, _) = comparator(first)?;
+        versions.push(min);
+    }
+
+    versions
+        .into_iter()
+        .min()
+        .map(|(major, minor, patch)| format!("{}.{}.{}", major, minor, patch))
+}
+
+fn section_entries<'a>(package: &'a Value, section: &str) -> Vec<(&'a String, &'a str)> {
+    package
+        .get(section)
+        .and_then(Value::as_object)
+        .map(|map| {
+            map.iter()
+                .filter_map(|(name, range)| range.as_str().map(|range| (name, range)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the version range the root package uses for the dependency.
+pub fn root_range<'a>(root: &'a Value, name: &str) -> Option<&'a str> {
+    ["dependencies", "devDependencies", "optionalDependencies"]
+        .iter()
+        .find_map(|section| root.get(*section)?.get(name)?.as_str())
+}
+
+fn is_conflict(range: &str, expected: &str) -> bool {
+    if range == expected {
+        return false;
+    }
+
+    match min_version(expected) {
+        Some(version) => satisfies(&version, range) == Some(false),
+        None => false,
+    }
+}
+
+/// Finds the packages declared in multiple sections.
+/// Declaring a peer dependency as a dev dependency is a common practice and is allowed.
+pub fn find_duplicates(package: &Value) -> Vec<Issue> {
+    let mut names: Vec<&String> = vec![];
+    for section in &SECTIONS {
+        for (name, _) in section_entries(package, section) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+
+    let mut issues = vec![];
+
+    for name in names {
+        let sections: Vec<String> = SECTIONS
+            .iter()
+            .filter(|section| package.get(**section).and_then(|s| s.get(name)).is_some())
+            .map(|section| section.to_string())
+            .collect();
+
+        let peer_with_dev = sections.len() == 2
+            && sections.contains(&String::from("peerDependencies"))
+            && sections.contains(&String::from("devDependencies"));
+
+        if sections.len() > 1 && !peer_with_dev {
+            issues.push(Issue::Duplicate {
+                name: name.to_string(),
+                sections,
+            });
+        }
+    }
+
+    issues
+}
+
+/// Compares the project package with the root package.
+pub fn check_package(root: &Value, package: &Value) -> Vec<Issue> {
+    let mut issues = vec![];
+
+    for section in &SECTIONS {
+        for (name, range) in section_entries(package, section) {
+            match root_range(root, name) {
+                Some(expected) if is_conflict(range, expected) => issues.push(Issue::Conflict {
+                    section: section.to_string(),
+                    name: name.to_string(),
+                    range: range.to_string(),
+                    expected: expected.to_string(),
+                }),
+                Some(_) => {}
+                None if *section == "peerDependencies" => issues.push(Issue::MissingPeer {
+                    name: name.to_string(),
+                    range: range.to_string(),
+                }),
+                None => {}
+            }
+        }
+    }
+
+    issues.extend(find_duplicates(package));
+    issues
+}
+
+/// Aligns the conflicting version ranges to the root package,
+/// returns the JSON pointers and the new ranges of the changes.
+pub fn fix_package(root: &Value, package: &mut Value) -> Vec<(String, String)> {
+    let mut changes = vec![];
+
+    for section in &SECTIONS {
+        let map = match package.get_mut(*section).and_then(Value::as_object_mut) {
+            Some(map) => map,
+            None => continue,
+        };
+
+        for (name, range) in map.iter_mut() {
+            let expected = match (range.as_str(), root_range(root, name)) {
+                (Some(current), Some(expected)) if is_conflict(current, expected) => expected,
+                _ => continue,
+            };
+
+            *range = Value::String(expected.to_string());
+            changes.push((
+                format!(
+                    "/{}/{}",
+                    section,
+                    name.replace('~', "~0").replace('/', "~1")
+                ),
+                expected.to_string(),
+            ));
+        }
+    }
+
+    changes
+}
@@ -0,0 +1,146 @@
+//! # Diff utils
+//!
+//! Provides structural comparison of JSON documents.
+
+use serde_json::{json, Value};
+use std::fmt;
+
+/// A single difference between two JSON documents
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    Added {
+        pointer: String,
+        value: Value,
+    },
+    Removed {
+        pointer: String,
+        value: Value,
+    },
+    Changed {
+        pointer: String,
+        old: Value,
+        new: Value,
+    },
+}
+
+impl Change {
+    pub fn pointer(&self) -> &str {
+        match self {
+            Change::Added { pointer, .. } => pointer,
+            Change::Removed { pointer, .. } => pointer,
+            Change::Changed { pointer, .. } => pointer,
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pointer = match self.pointer() {
+            "" => "(root)",
+            value => value,
+        };
+
+        match self {
+            Change::Added { value, .. } => write!(f, "+ {}: {}", pointer, value),
+            Change::Removed { value, .. } => write!(f, "- {}: {}", pointer, value),
+            Change::Changed { old, new, .. } => write!(f, "~ {}: {} -> {}", pointer, old, new),
+        }
+    }
+}
+
+/// Escapes the key to be used as a JSON pointer token (RFC 6901)
+pub fn escape_token(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Compares the numbers, the integers exactly as the large ids do not fit into `f64`.
+fn numbers_equal(a: &Value, b: &Value) -> bool {
+    let (x, y) = match (a, b) {
+        (Value::Number(x), Value::Number(y)) => (x, y),
+        _ => return a == b,
+    };
+
+    if (x.is_i64() || x.is_u64()) && (y.is_i64() || y.is_u64()) {
+        x.as_i64() == y.as_i64() && x.as_u64() == y.as_u64()
+    } else {
+        x.as_f64() == y.as_f64()
+    }
+}
+
+fn compare(pointer: &str, a: &Value, b: &Value, changes: &mut Vec<Change>) {
+    match (a, b) {
+        (Value::Object(left), Value::Object(right)) => {
+            for (key, value) in left {
+                let path = format!("{}/{}", pointer, escape_token(key));
+                match right.get(key) {
+                    Some(other) => compare(&path, value, other, changes),
+                    None => changes.push(Change::Removed {
+                        pointer: path,
+                        value: value.clone(),
+                    }),
+                }
+            }
+
+            for (key, value) in right {
+                if !left.contains_key(key) {
+                    changes.push(Change::Added {
+                        pointer: format!("{}/{}", pointer, escape_token(key)),
+                        value: value.clone(),
+                    });
+                }
+            }
+        }
+        (Value::Array(left), Value::Array(right)) => {
+            for (index, (x, y)) in left.iter().zip(right.iter()).enumerate() {
+                compare(&format!("{}/{}", pointer, index), x, y, changes);
+            }
+
+            for (index, value) in right.iter().enumerate().skip(left.len()) {
+                changes.push(Change::Added {
+                    pointer: format!("{}/{}", pointer, index),
+                    value: value.clone(),
+                });
+            }
+
+            // removing from the end keeps the remaining indices valid when applied as a patch
+            for (index, value) in left.iter().enumerate().skip(right.len()).rev() {
+                changes.push(Change::Removed {
+                    pointer: format!("{}/{}", pointer, index),
+                    value: value.clone(),
+                });
+            }
+        }
+        (Value::Number(_), Value::Number(_)) if numbers_equal(a, b) => {}
+        _ if a == b => {}
+        _ => changes.push(Change::Changed {
+            pointer: pointer.to_string(),
+            old: a.clone(),
+            new: b.clone(),
+        }),
+    }
+}
+
+/// Compares two JSON documents ignoring key order and formatting.
+pub fn diff(a: &Value, b: &Value) -> Vec<Change> {
+    let mut changes = vec![];
+    compare("", a, b, &mut changes);
+    changes
+}
+
+/// Converts the changes to a JSON Patch document (RFC 6902)
+pub fn to_patch(changes: &[Change]) -> Value {
+    let operations: Vec<Value> = changes
+        .iter()
+        .map(|change| match change {
+            Change::Added { pointer, value } => {
+                json!({ "op": "add", "path": pointer, "value": value })
+            }
+            Change::Removed { pointer, .. } => json!({ "op": "remove", "path": pointer }),
+            Change::Changed { pointer, new, .. } => {
+                json!({ "op": "replace", "path": pointer, "value": new })
+            }
+        })
+        .collect();
+
+    Value::Array(operations)
+}
@@ -11,24 +11,24 @@ use std::path::Path;
 
 /// Verifies that files have headers matching one of the templates.
 pub fn check_headers(files: &[&Path], templates: &[&Path]) -> bool {
-    if !verify_files(&files) {
+    if !verify_files(files) {
         return false;
     }
 
-    if !verify_files(&templates) {
+    if !verify_files(templates) {
         return false;
     }
 
     let result: bool = files
         .iter()
-        .all(|file| compare_file_headers(file, &templates));
+        .all(|file| compare_file_headers(file, templates));
 
     result
 }
 
 fn compare_file_headers(file: &Path, templates: &[&Path]) -> bool {
     for template in templates {
-        let template_lines = get_lines(&template);
+        let template_lines = get_lines(template);
         let file_lines = get_top_lines(file, template_lines.len());
 
         if Ordering::Equal == utils::compare(&template_lines, &file_lines) {
@@ -69,7 +69,7 @@ pub fn get_top_lines(path: &Path, size: usize) -> Vec<String> {
 
 /// Returns the content of the file as a collection of lines
 pub fn get_lines(path: &Path) -> Vec<String> {
-    match read_to_string(&path) {
+    match read_to_string(path) {
         Ok(content) => content.lines().map(|line| line.to_string()).collect(),
         Err(err) => {
             error!("Error loading `{}`. {}", path.display(), err);
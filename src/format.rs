@@ -0,0 +1,454 @@
+//! # Formatting utils
+//!
+//! Provides canonical formatting for JSON files.
+
+use anyhow::{anyhow, Result};
+use log::{error, info};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Indentation style
+#[derive(Clone, Debug, PartialEq)]
+pub enum Indent {
+    Spaces(usize),
+    Tab,
+}
+
+impl FromStr for Indent {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value == "tab" {
+            return Ok(Indent::Tab);
+        }
+
+        value
+            .parse()
+            .map(Indent::Spaces)
+            .map_err(|_| format!("invalid indent `{}`, expected a number or `tab`", value))
+    }
+}
+
+/// JSON formatting options
+#[derive(Clone, Debug)]
+pub struct FormatOptions {
+    pub indent: Indent,
+    /// Sort the keys of all objects.
+    pub sort_keys: bool,
+    /// JSON pointers to the objects which keys should be sorted.
+    pub sort_pointers: Vec<String>,
+    /// Terminate the output with a newline.
+    pub final_newline: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            indent: Indent::Spaces(2),
+            sort_keys: false,
+            sort_pointers: vec![],
+            final_newline: true,
+        }
+    }
+}
+
+/// JSON value keeping the order of the keys and the number literals as written
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    /// `null`, `true`, `false` or a number
+    Literal(String),
+    String(String),
+    Array(Vec<Node>),
+    Object(Vec<(String, Node)>),
+}
+
+impl From<&Value> for Node {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::String(text) => Node::String(text.clone()),
+            Value::Array(items) => Node::Array(items.iter().map(Node::from).collect()),
+            Value::Object(map) => Node::Object(
+                map.iter()
+                    .map(|(key, value)| (key.clone(), Node::from(value)))
+                    .collect(),
+            ),
+            literal => Node::Literal(literal.to_string()),
+        }
+    }
+}
+
+/// Checks the number literal against the JSON grammar.
+fn is_number(literal: &str) -> bool {
+    let digits = |text: &str| text.bytes().take_while(u8::is_ascii_digit).count();
+
+    let mut rest = literal.strip_prefix('-').unwrap_or(literal);
+    let count = digits(rest);
+    if count == 0 || (count > 1 && rest.starts_with('0')) {
+        return false;
+    }
+    rest = &rest[count..];
+
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let count = digits(fraction);
+        if count == 0 {
+            return false;
+        }
+        rest = &fraction[count..];
+    }
+
+    if let Some(exponent) = rest.strip_prefix(['e', 'E']) {
+        let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        let count = digits(exponent);
+        if count == 0 {
+            return false;
+        }
+        rest = &exponent[count..];
+    }
+
+    rest.is_empty()
+}
+
+/// Nesting limit of the arrays and objects, the same as serde_json
+const MAX_DEPTH: usize = 128;
+
+/// Parser of the JSON text into the nodes
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> anyhow::Error {
+        let before = &self.text[..self.pos];
+        let line = before.matches('\n').count() + 1;
+        let column = before.len() - before.rfind('\n').map_or(0, |index| index + 1) + 1;
+        anyhow!("{} at line {} column {}", message, line, column)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn parse_value(&mut self) -> Result<Node> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(byte @ b'{') | Some(byte @ b'[') => {
+                if self.depth == MAX_DEPTH {
+                    return Err(self.error("Recursion limit exceeded"));
+                }
+                self.depth += 1;
+                let node = if byte == b'{' {
+                    self.parse_object()
+                } else {
+                    self.parse_array()
+                };
+                self.depth -= 1;
+                node
+            }
+            Some(b'"') => self.parse_string().map(Node::String),
+            Some(_) => self.parse_literal(),
+            None => Err(self.error("EOF while parsing a value")),
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String> {
+        let bytes = self.text.as_bytes();
+        let mut end = self.pos + 1;
+        loop {
+            match bytes.get(end) {
+                Some(b'\\') => end += 2,
+                Some(b'"') => break,
+                Some(_) => end += 1,
+                None => return Err(self.error("EOF while parsing a string")),
+            }
+        }
+
+        // the escapes are decoded as serde does
+        let text = serde_json::from_str(&self.text[self.pos..=end])
+            .map_err(|err| self.error(&format!("Invalid string, {}", err)))?;
+        self.pos = end + 1;
+        Ok(text)
+    }
+
+    fn parse_literal(&mut self) -> Result<Node> {
+        let rest = &self.text[self.pos..];
+        let length = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')))
+            .unwrap_or(rest.len());
+        let literal = &rest[..length];
+
+        if matches!(literal, "null" | "true" | "false") || is_number(literal) {
+            self.pos += length;
+            Ok(Node::Literal(literal.to_string()))
+        } else {
+            Err(self.error("Expected a value"))
+        }
+    }
+
+    /// Parses the items of the array or the object until the closing bracket.
+    fn parse_items(
+        &mut self,
+        close: u8,
+        mut item: impl FnMut(&mut Self) -> Result<()>,
+    ) -> Result<()> {
+        self.pos += 1;
+        self.skip_whitespace();
+        if self.peek() == Some(close) {
+            self.pos += 1;
+            return Ok(());
+        }
+
+        loop {
+            item(self)?;
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(byte) if byte == close => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                _ => return Err(self.error("Expected `,` or the closing bracket")),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Node> {
+        let mut items = vec![];
+        self.parse_items(b']', |parser| {
+            items.push(parser.parse_value()?);
+            Ok(())
+        })?;
+        Ok(Node::Array(items))
+    }
+
+    fn parse_object(&mut self) -> Result<Node> {
+        let mut entries = vec![];
+        self.parse_items(b'}', |parser| {
+            parser.skip_whitespace();
+            if parser.peek() != Some(b'"') {
+                return Err(parser.error("Expected a key"));
+            }
+            let key = parser.parse_string()?;
+            parser.skip_whitespace();
+            if parser.peek() != Some(b':') {
+                return Err(parser.error("Expected `:`"));
+            }
+            parser.pos += 1;
+            entries.push((key, parser.parse_value()?));
+            Ok(())
+        })?;
+        Ok(Node::Object(entries))
+    }
+}
+
+fn parse(text: &str) -> Result<Node> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut parser = Parser {
+        text,
+        pos: 0,
+        depth: 0,
+    };
+    let node = parser.parse_value()?;
+    parser.skip_whitespace();
+    if parser.pos < text.len() {
+        return Err(parser.error("Trailing characters"));
+    }
+    Ok(node)
+}
+
+fn sort_all(node: &mut Node) {
+    match node {
+        Node::Object(entries) => {
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            entries.iter_mut().for_each(|(_, value)| sort_all(value));
+        }
+        Node::Array(items) => items.iter_mut().for_each(sort_all),
+        _ => {}
+    }
+}
+
+/// Resolves the JSON pointer, i.e. `/compilerOptions/paths`.
+fn pointer_mut<'a>(node: &'a mut Node, pointer: &str) -> Option<&'a mut Node> {
+    if pointer.is_empty() {
+        return Some(node);
+    }
+
+    let mut target = node;
+    for token in pointer.strip_prefix('/')?.split('/') {
+        let token = token.replace("~1", "/").replace("~0", "~");
+        target = match target {
+            Node::Object(entries) => entries
+                .iter_mut()
+                .find(|(key, _)| *key == token)
+                .map(|(_, value)| value)?,
+            Node::Array(items) => items.get_mut(token.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(target)
+}
+
+fn write_node(node: &Node, indent: &str, depth: usize, text: &mut String) {
+    let new_line = |text: &mut String, depth: usize| {
+        text.push('\n');
+        text.push_str(&indent.repeat(depth));
+    };
+
+    match node {
+        Node::Literal(literal) => text.push_str(literal),
+        Node::String(value) => text.push_str(&serde_json::to_string(value).unwrap()),
+        Node::Array(items) if items.is_empty() => text.push_str("[]"),
+        Node::Object(entries) if entries.is_empty() => text.push_str("{}"),
+        Node::Array(items) => {
+            text.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    text.push(',');
+                }
+                new_line(text, depth + 1);
+                write_node(item, indent, depth + 1, text);
+            }
+            new_line(text, depth);
+            text.push(']');
+        }
+        Node::Object(entries) => {
+            text.push('{');
+            for (index, (key, value)) in entries.iter().enumerate() {
+                if index > 0 {
+                    text.push(',');
+                }
+                new_line(text, depth + 1);
+                text.push_str(&serde_json::to_string(key).unwrap());
+                text.push_str(": ");
+                write_node(value, indent, depth + 1, text);
+            }
+            new_line(text, depth);
+            text.push('}');
+        }
+    }
+}
+
+fn format_node(mut node: Node, options: &FormatOptions) -> String {
+    if options.sort_keys {
+        sort_all(&mut node);
+    } else {
+        for pointer in &options.sort_pointers {
+            if let Some(Node::Object(entries)) = pointer_mut(&mut node, pointer) {
+                entries.sort_by(|a, b| a.0.cmp(&b.0));
+            }
+        }
+    }
+
+    let indent = match options.indent {
+        Indent::Spaces(size) => " ".repeat(size),
+        Indent::Tab => String::from("\t"),
+    };
+
+    let mut text = String::new();
+    write_node(&node, &indent, 0, &mut text);
+    if options.final_newline {
+        text.push('\n');
+    }
+
+    text
+}
+
+/// Formats the JSON value according to the options.
+pub fn format_value(value: &Value, options: &FormatOptions) -> String {
+    format_node(Node::from(value), options)
+}
+
+/// Formats the JSON text according to the options, the number literals are kept as written.
+pub fn format_text(text: &str, options: &FormatOptions) -> Result<String> {
+    Ok(format_node(parse(text)?, options))
+}
+
+/// Formats the JSON text with the strings at the JSON pointers replaced,
+/// the rest of the text is kept as `format_text` does.
+pub fn format_text_with(
+    text: &str,
+    strings: &[(String, String)],
+    options: &FormatOptions,
+) -> Result<String> {
+    let mut node = parse(text)?;
+    for (pointer, value) in strings {
+        match pointer_mut(&mut node, pointer) {
+            Some(target) => *target = Node::String(value.clone()),
+            None => return Err(anyhow!("`{}` not found", pointer)),
+        }
+    }
+
+    Ok(format_node(node, options))
+}
+
+/// Formats the JSON file, returns the original and the formatted text,
+/// or `None` if the file cannot be read or parsed.
+pub fn format_file(path: &Path, options: &FormatOptions) -> Option<(String, String)> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(_) => {
+            error!("Error opening file: {}", path.display());
+            return None;
+        }
+    };
+
+    match format_text(&text, options) {
+        Ok(formatted) => Some((text, formatted)),
+        Err(err) => {
+            error!("Error reading from file: {}. {}", path.display(), err);
+            None
+        }
+    }
+}
+
+/// Verifies that all files are formatted, lists the files that are not.
+pub fn check_files(paths: &[&Path], options: &FormatOptions) -> bool {
+    let mut result = true;
+
+    for path in paths {
+        match format_file(path, options) {
+            Some((original, formatted)) if original == formatted => {}
+            Some(_) => {
+                error!("Not formatted: {}", path.display());
+                result = false;
+            }
+            None => result = false,
+        }
+    }
+
+    result
+}
+
+/// Rewrites all files that are not formatted.
+pub fn format_files(paths: &[&Path], options: &FormatOptions) -> bool {
+    let mut result = true;
+
+    for path in paths {
+        let formatted = match format_file(path, options) {
+            Some((original, formatted)) if original == formatted => continue,
+            Some((_, formatted)) => formatted,
+            None => {
+                result = false;
+                continue;
+            }
+        };
+
+        match fs::write(path, formatted) {
+            Ok(_) => info!("Formatted: {}", path.display()),
+            Err(err) => {
+                error!("Error writing `{}`. {}", path.display(), err);
+                result = false;
+            }
+        }
+    }
+
+    result
+}
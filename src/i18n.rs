@@ -0,0 +1,320 @@
+//! # i18n utils
+//!
+//! Provides loading and consistency checks for the translation files
+//! in XLIFF 1.2, XLIFF 2.0, XMB/XTB and JSON formats.
+
+use anyhow::{anyhow, Result};
+use roxmltree::{Document, Node};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+/// Message text with the names of the placeholders and ICU expressions it uses
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Message {
+    pub text: String,
+    pub placeholders: BTreeSet<String>,
+}
+
+impl Message {
+    fn push_text(&mut self, text: &str) {
+        self.text.push_str(text);
+    }
+
+    fn push_placeholder(&mut self, name: &str) {
+        self.placeholders.insert(name.to_string());
+    }
+
+    fn finish(mut self) -> Self {
+        for name in icu_expressions(&self.text) {
+            self.placeholders.insert(name);
+        }
+        self
+    }
+
+    /// Message has neither text nor placeholders
+    pub fn is_empty(&self) -> bool {
+        self.text.trim().is_empty() && self.placeholders.is_empty()
+    }
+}
+
+/// Translation unit
+#[derive(Debug, Default, Clone)]
+pub struct Unit {
+    pub source: Option<Message>,
+    pub target: Option<Message>,
+}
+
+/// Contents of one or more translation files
+#[derive(Debug, Default)]
+pub struct TranslationFile {
+    pub messages: BTreeMap<String, Unit>,
+}
+
+impl TranslationFile {
+    /// Adds all messages from the other file.
+    pub fn merge(&mut self, other: TranslationFile) {
+        self.messages.extend(other.messages);
+    }
+}
+
+/// Translation consistency issue
+#[derive(Debug, PartialEq)]
+pub enum Issue {
+    /// Message of the source locale is not translated.
+    Missing(String),
+    /// Message does not exist in the source locale.
+    Extra(String),
+    /// Message has an empty target.
+    EmptyTarget(String),
+    /// Target uses different placeholders or ICU expressions than the source.
+    PlaceholderMismatch {
+        id: String,
+        expected: BTreeSet<String>,
+        actual: BTreeSet<String>,
+    },
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::Missing(id) => write!(f, "missing translation `{}`", id),
+            Issue::Extra(id) => write!(f, "extra translation `{}`", id),
+            Issue::EmptyTarget(id) => write!(f, "empty target `{}`", id),
+            Issue::PlaceholderMismatch {
+                id,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "placeholder mismatch `{}`: expected {:?}, found {:?}",
+                id, expected, actual
+            ),
+        }
+    }
+}
+
+/// Returns the names of the ICU expressions, i.e. `{VAR_PLURAL, plural, ...}`
+fn icu_expressions(text: &str) -> Vec<String> {
+    let mut result = vec![];
+
+    for (index, _) in text.match_indices('{') {
+        let rest = &text[index + 1..];
+        let mut parts = rest.splitn(3, ',');
+
+        let name = parts.next().unwrap_or_default().trim();
+        let kind = parts.next().unwrap_or_default().trim();
+
+        let is_name = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '.');
+
+        if is_name
+            && parts.next().is_some()
+            && ["plural", "select", "selectordinal"].contains(&kind)
+        {
+            result.push(format!("ICU({}, {})", name, kind));
+        }
+    }
+
+    result
+}
+
+fn collect_xml(node: Node, message: &mut Message) {
+    for child in node.children() {
+        if child.is_text() {
+            message.push_text(child.text().unwrap_or_default());
+            continue;
+        }
+
+        match child.tag_name().name() {
+            // XLIFF 1.2
+            "x" => message.push_placeholder(child.attribute("id").unwrap_or_default()),
+            // XLIFF 2.0 and XMB/XTB
+            "ph" => match child.attribute("equiv").or_else(|| child.attribute("name")) {
+                Some(name) => message.push_placeholder(name),
+                None => message.push_placeholder(child.attribute("id").unwrap_or_default()),
+            },
+            "pc" => {
+                if let Some(name) = child.attribute("equivStart") {
+                    message.push_placeholder(name);
+                }
+                if let Some(name) = child.attribute("equivEnd") {
+                    message.push_placeholder(name);
+                }
+                collect_xml(child, message);
+            }
+            // XMB placeholder examples are not part of the message
+            "ex" => {}
+            _ => collect_xml(child, message),
+        }
+    }
+}
+
+fn xml_message(node: Node) -> Message {
+    let mut message = Message::default();
+    collect_xml(node, &mut message);
+    message.finish()
+}
+
+fn child_element<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.descendants()
+        .find(|child| child.is_element() && child.tag_name().name() == name)
+}
+
+fn parse_xml(text: &str) -> Result<TranslationFile> {
+    let document = Document::parse(text)?;
+    let root = document.root_element();
+    let mut file = TranslationFile::default();
+
+    let (unit_tag, is_bundle) = match root.tag_name().name() {
+        "xliff" if root.attribute("version") == Some("2.0") => ("unit", false),
+        "xliff" => ("trans-unit", false),
+        "messagebundle" => ("msg", true),
+        "translationbundle" => ("translation", true),
+        other => return Err(anyhow!("unsupported translation format `{}`", other)),
+    };
+
+    for node in root.descendants() {
+        if !node.is_element() || node.tag_name().name() != unit_tag {
+            continue;
+        }
+
+        let id = match node.attribute("id") {
+            Some(id) => id.to_string(),
+            None => continue,
+        };
+
+        let unit = if is_bundle {
+            let message = Some(xml_message(node));
+            if unit_tag == "msg" {
+                Unit {
+                    source: message,
+                    target: None,
+                }
+            } else {
+                Unit {
+                    source: None,
+                    target: message,
+                }
+            }
+        } else {
+            Unit {
+                source: child_element(node, "source").map(xml_message),
+                target: child_element(node, "target").map(xml_message),
+            }
+        };
+
+        file.messages.insert(id, unit);
+    }
+
+    Ok(file)
+}
+
+fn json_message(text: &str) -> Message {
+    let mut message = Message::default();
+    message.push_text(text);
+
+    for (index, _) in text.match_indices("{$") {
+        if let Some(end) = text[index..].find('}') {
+            message.push_placeholder(&text[index + 2..index + end]);
+        }
+    }
+
+    message.finish()
+}
+
+fn parse_json(text: &str) -> Result<TranslationFile> {
+    let value: Value = serde_json::from_str(text)?;
+    let translations = value
+        .get("translations")
+        .and_then(Value::as_object)
+        .ok_or_else(|| anyhow!("missing `translations` object"))?;
+
+    let mut file = TranslationFile::default();
+
+    for (id, text) in translations {
+        let message = text.as_str().map(json_message);
+        file.messages.insert(
+            id.to_string(),
+            Unit {
+                source: message.clone(),
+                target: message,
+            },
+        );
+    }
+
+    Ok(file)
+}
+
+/// Loads the translation file, the format is detected by the file extension.
+pub fn read_translation_file(path: &Path) -> Result<TranslationFile> {
+    let text = read_to_string(path)?;
+
+    let result = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => parse_json(&text),
+        _ => parse_xml(&text),
+    };
+
+    result.map_err(|err| anyhow!("Error loading `{}`. {}", path.display(), err))
+}
+
+/// Returns the default source locale file for the translation file,
+/// i.e. `src/locale/messages.xlf` for `src/locale/messages.de.xlf`.
+pub fn default_source_path(translation: &Path) -> PathBuf {
+    let extension = match translation.extension().and_then(|ext| ext.to_str()) {
+        Some("xtb") => "xmb",
+        Some(ext) => ext,
+        None => "xlf",
+    };
+
+    translation.with_file_name(format!("messages.{}", extension))
+}
+
+/// Compares the translations with the messages of the source locale.
+pub fn check_translations(source: &TranslationFile, translation: &TranslationFile) -> Vec<Issue> {
+    let mut issues = vec![];
+
+    for (id, unit) in &source.messages {
+        let target = match translation.messages.get(id) {
+            Some(Unit {
+                target: Some(target),
+                ..
+            }) => target,
+            Some(_) => {
+                issues.push(Issue::EmptyTarget(id.to_string()));
+                continue;
+            }
+            None => {
+                issues.push(Issue::Missing(id.to_string()));
+                continue;
+            }
+        };
+
+        if target.is_empty() {
+            issues.push(Issue::EmptyTarget(id.to_string()));
+            continue;
+        }
+
+        if let Some(expected) = &unit.source {
+            if expected.placeholders != target.placeholders {
+                issues.push(Issue::PlaceholderMismatch {
+                    id: id.to_string(),
+                    expected: expected.placeholders.clone(),
+                    actual: target.placeholders.clone(),
+                });
+            }
+        }
+    }
+
+    for id in translation.messages.keys() {
+        if !source.messages.contains_key(id) {
+            issues.push(Issue::Extra(id.to_string()));
+        }
+    }
+
+    issues
+}
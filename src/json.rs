@@ -42,12 +42,12 @@ pub fn validate_with_schema(json_path: &Path, schema_path: &Path) -> Result<bool
         schema_path.display()
     );
 
-    let instance = match read_json(&json_path) {
+    let instance = match read_json(json_path) {
         Some(value) => value,
         None => return Ok(false),
     };
 
-    let schema = match read_json(&schema_path) {
+    let schema = match read_json(schema_path) {
         Some(value) => value,
         None => return Ok(false),
     };
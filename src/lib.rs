@@ -4,15 +4,24 @@
 //! with Angular CLI projects more convenient.
 
 pub mod angular;
+pub mod budgets;
+pub mod deps;
+pub mod diff;
 pub mod files;
+pub mod format;
+pub mod i18n;
 pub mod json;
 pub mod logger;
+pub mod pwa;
+pub mod schema;
 pub mod serve;
+pub mod template;
 pub mod utils;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::ArgMatches;
-use log::{error, info};
+use log::{error, info, warn};
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::process;
 
@@ -39,27 +48,22 @@ pub fn validate_json(args: &ArgMatches) {
     }
 }
 
+/// Resolve workspace configuration file path using CLI args
+pub fn get_workspace_config_path(args: &ArgMatches) -> Result<PathBuf> {
+    match args.value_of("config") {
+        Some(value) => Ok(PathBuf::from(value)),
+        None => Ok(std::env::current_dir()?.join("angular.json")),
+    }
+}
+
 /// Load workspace configuration file using CLI args
 pub fn get_workspace_config(args: &ArgMatches) -> Result<angular::WorkspaceConfig> {
-    let config_path = match args.value_of("config") {
-        Some(value) => PathBuf::from(value),
-        None => std::env::current_dir()?.join("angular.json"),
-    };
-
-    angular::read_config(config_path)
+    angular::read_config(get_workspace_config_path(args)?)
 }
 
 pub fn check_files_headers(args: &ArgMatches) {
-    let files: Vec<_> = args
-        .values_of("file")
-        .unwrap()
-        .map(|path| Path::new(path))
-        .collect();
-    let templates: Vec<_> = args
-        .values_of("template")
-        .unwrap()
-        .map(|path| Path::new(path))
-        .collect();
+    let files: Vec<_> = args.values_of("file").unwrap().map(Path::new).collect();
+    let templates: Vec<_> = args.values_of("template").unwrap().map(Path::new).collect();
 
     let result = files::check_headers(&files, &templates);
     if result {
@@ -72,12 +76,822 @@ pub fn check_files_headers(args: &ArgMatches) {
 }
 
 pub fn serve(args: &ArgMatches) -> std::io::Result<()> {
+    let proxy = match args.value_of("proxy-config") {
+        Some(path) => match serve::proxy::read_proxy_config(Path::new(path)) {
+            Ok(configs) => configs,
+            Err(err) => {
+                error!("Error loading `{}`. {}", path, err);
+                process::exit(1);
+            }
+        },
+        None => vec![],
+    };
+
+    let fallback = if args.is_present("spa") {
+        serve::Fallback::Spa
+    } else if let Some(page) = args.value_of("not-found") {
+        serve::Fallback::NotFound(PathBuf::from(page))
+    } else if args.is_present("strict") {
+        serve::Fallback::Strict
+    } else {
+        serve::Fallback::Index
+    };
+
+    let (mounts, locales) = match serve_mounts(args, &fallback) {
+        Ok(result) => result,
+        Err(err) => {
+            error!("{}", err);
+            process::exit(1);
+        }
+    };
+
+    let headers = match headers_config(args) {
+        Ok(config) => config,
+        Err(err) => {
+            error!("{}", err);
+            process::exit(1);
+        }
+    };
+
+    let network = match network_config(args) {
+        Ok(config) => config,
+        Err(err) => {
+            error!("{}", err);
+            process::exit(1);
+        }
+    };
+
+    let templates = match args.values_of("config-template") {
+        Some(patterns) => {
+            let env_files: Vec<&Path> = args
+                .values_of("env-file")
+                .map(|files| files.map(Path::new).collect())
+                .unwrap_or_default();
+            match template::load_vars(&env_files) {
+                Ok(vars) => Some(serve::templates::TemplateOptions {
+                    patterns: patterns.map(String::from).collect(),
+                    vars,
+                }),
+                Err(err) => {
+                    error!("{}", err);
+                    process::exit(1);
+                }
+            }
+        }
+        None => None,
+    };
+
+    let access = serve::access::AccessOptions {
+        users: args
+            .values_of("auth")
+            .map(|users| users.map(String::from).collect())
+            .unwrap_or_default(),
+        htpasswd: args.value_of("htpasswd").map(PathBuf::from),
+        allow: args
+            .values_of("allow")
+            .map(|networks| networks.map(String::from).collect())
+            .unwrap_or_default(),
+    };
+    if let Err(err) = serve::access::Access::new(&access) {
+        error!("{}", err);
+        process::exit(1);
+    }
+
+    let access_log = match access_log_options(args) {
+        Ok(options) => options,
+        Err(err) => {
+            error!("{}", err);
+            process::exit(1);
+        }
+    };
+
     let options = serve::ServerOptions {
         host: args.value_of("host").unwrap().to_string(),
-        port: args.value_of("port").unwrap().to_string(),
-        root_dir: args.value_of("dir").unwrap().to_string(),
+        port: args.value_of_t("port").unwrap_or_else(|err| err.exit()),
+        mounts,
+        locales,
         open: args.is_present("open"),
+        access,
+        ssl: if args.is_present("ssl") {
+            Some(serve::ssl::SslOptions {
+                cert: args.value_of("ssl-cert").map(PathBuf::from),
+                key: args.value_of("ssl-key").map(PathBuf::from),
+            })
+        } else {
+            None
+        },
+        proxy,
+        record: args.value_of("record").map(PathBuf::from),
+        replay: args
+            .value_of("replay")
+            .map(|dir| serve::recording::ReplayOptions {
+                dir: PathBuf::from(dir),
+                matching: match args.value_of("match") {
+                    Some(_) => args.value_of_t("match").unwrap_or_else(|err| err.exit()),
+                    None => Default::default(),
+                },
+            }),
+        headers,
+        watch: args.is_present("watch"),
+        list: args.is_present("list"),
+        metrics: args.is_present("metrics"),
+        access_log,
+        request_id: args.is_present("request-id"),
+        mocks: args.value_of("mocks").map(PathBuf::from),
+        mocks_prefix: args.value_of("mocks-prefix").unwrap_or("/api").to_string(),
+        templates,
+        network,
+        ready_file: args.value_of("ready-file").map(PathBuf::from),
     };
 
     serve::run_server(options)
 }
+
+/// Collects the directories to serve from the target directory, the project and the mounts,
+/// with the locales of the project in the i18n mode
+fn serve_mounts(
+    args: &ArgMatches,
+    fallback: &serve::Fallback,
+) -> Result<(Vec<serve::Mount>, Option<serve::i18n::LocaleOptions>)> {
+    let (mut mounts, routes) = match (args.value_of("dir"), args.is_present("project")) {
+        (Some(dir), _) => (vec![serve::Mount::new("/", dir)], vec![]),
+        (None, true) => project_mounts(args)?,
+        (None, false) => (vec![], vec![]),
+    };
+
+    let locales = if args.is_present("i18n") {
+        if routes.is_empty() {
+            return Err(anyhow!(
+                "Project `{}` has no localized build",
+                args.value_of("project").unwrap_or_default()
+            ));
+        }
+        Some(serve::i18n::LocaleOptions {
+            routes,
+            cookie: args
+                .value_of("locale-cookie")
+                .unwrap_or(serve::i18n::DEFAULT_COOKIE)
+                .to_string(),
+        })
+    } else {
+        None
+    };
+
+    for mount in mounts.iter_mut() {
+        let is_locale = locales.as_ref().map_or(false, |locales| {
+            locales
+                .routes
+                .iter()
+                .any(|route| route.prefix == mount.prefix)
+        });
+        // every locale has its own history API fallback
+        mount.fallback = if is_locale {
+            serve::Fallback::Spa
+        } else {
+            fallback.clone()
+        };
+    }
+
+    for value in args.values_of("mount").into_iter().flatten() {
+        let mount = serve::Mount::parse(value, fallback)?;
+        if mounts.iter().any(|other| other.prefix == mount.prefix) {
+            return Err(anyhow!("Path `{}` is mounted more than once", mount.prefix));
+        }
+        mounts.push(mount);
+    }
+
+    Ok((mounts, locales))
+}
+
+/// Resolves the directories to serve from the build target of the workspace project
+fn project_mounts(args: &ArgMatches) -> Result<(Vec<serve::Mount>, Vec<serve::i18n::LocaleRoute>)> {
+    let config_path = get_workspace_config_path(args)?;
+    let config = angular::read_config(config_path.clone())?;
+    let workspace_root = config_path.parent().unwrap_or_else(|| Path::new(""));
+
+    let name = args.value_of("project").unwrap();
+    let project = config
+        .projects
+        .as_ref()
+        .and_then(|projects| projects.get(name))
+        .ok_or_else(|| anyhow!("Project `{}` not found", name))?;
+    let outputs = project
+        .build_outputs(args.value_of("configuration"))
+        .map_err(|err| anyhow!("Project `{}`. {}", name, err))?;
+
+    let mut mounts = vec![];
+    let mut asset_mounts = vec![];
+    let mut routes = vec![];
+    for output in outputs {
+        let mount = serve::Mount::new(&output.base_href, workspace_root.join(&output.dir));
+        if let Some(locale) = &output.locale {
+            routes.push(serve::i18n::LocaleRoute {
+                locale: locale.to_string(),
+                prefix: mount.prefix.clone(),
+            });
+        }
+
+        if let Some(deploy_url) = &output.deploy_url {
+            if deploy_url.contains("//") {
+                info!("Assets are loaded from {}", deploy_url);
+            } else {
+                // relative deploy urls are resolved against the base href
+                let prefix = if deploy_url.starts_with('/') {
+                    deploy_url.to_string()
+                } else {
+                    format!("{}/{}", mount.prefix.trim_end_matches('/'), deploy_url)
+                };
+                let assets = serve::Mount::new(&prefix, &mount.root_dir);
+                if assets.prefix != mount.prefix {
+                    asset_mounts.push(assets);
+                }
+            }
+        }
+
+        mounts.push(mount);
+    }
+
+    // the pages go first, the first one is opened in the browser
+    mounts.extend(asset_mounts);
+    Ok((mounts, routes))
+}
+
+/// Merges the headers configuration file with the command line options
+fn headers_config(args: &ArgMatches) -> Result<serve::headers::HeadersConfig> {
+    let mut config = match args.value_of("headers-config") {
+        Some(path) => serve::headers::read_headers_config(Path::new(path))
+            .map_err(|err| anyhow!("Error loading `{}`. {}", path, err))?,
+        None => serve::headers::HeadersConfig::default(),
+    };
+
+    if let Some(values) = args.values_of("header") {
+        let mut headers = std::collections::BTreeMap::new();
+        for value in values {
+            let (name, value) = serve::headers::parse_header(value)?;
+            headers.insert(name, value);
+        }
+        // the later rules override the earlier ones, the options override the file
+        config.rules.push(serve::headers::HeaderRule {
+            source: String::from("**"),
+            headers,
+        });
+    }
+
+    if let Some(policy) = args.value_of("csp") {
+        config.content_security_policy = Some(policy.to_string());
+    }
+
+    if let Some(origins) = args.values_of("cors") {
+        config
+            .cors
+            .get_or_insert_with(Default::default)
+            .origins
+            .extend(origins.map(String::from));
+    }
+
+    config.csp_report |= args.is_present("csp-report");
+    config.security_headers |= args.is_present("security-headers");
+
+    Ok(config)
+}
+
+/// Reads the access log options, `None` without `--access-log`
+fn access_log_options(args: &ArgMatches) -> Result<Option<serve::access_log::AccessLogOptions>> {
+    let path = match args.value_of("access-log") {
+        Some(path) => PathBuf::from(path),
+        None => return Ok(None),
+    };
+
+    let rotation = match args.values_of("access-log-rotate") {
+        Some(values) => serve::access_log::parse_rotation(&values.collect::<Vec<_>>())?,
+        None => Default::default(),
+    };
+    let keep = match args.value_of("access-log-keep") {
+        Some(value) => value
+            .parse()
+            .map_err(|_| anyhow!("Invalid number of the access logs `{}`", value))?,
+        None => serve::access_log::DEFAULT_KEEP,
+    };
+
+    Ok(Some(serve::access_log::AccessLogOptions {
+        path,
+        format: match args.value_of("access-log-format") {
+            Some(format) => format.parse().map_err(|err: String| anyhow!(err))?,
+            None => Default::default(),
+        },
+        rotation,
+        keep,
+        timing: args.is_present("access-log-timing"),
+    }))
+}
+
+/// Reads the network configuration file, the options apply to all routes
+fn network_config(args: &ArgMatches) -> Result<serve::network::NetworkConfig> {
+    let mut config = match args.value_of("network-config") {
+        Some(path) => serve::network::read_network_config(Path::new(path))
+            .map_err(|err| anyhow!("Error loading `{}`. {}", path, err))?,
+        None => serve::network::NetworkConfig::default(),
+    };
+
+    let rule = serve::network::NetworkRule {
+        source: String::from("**"),
+        latency: match args.value_of("latency") {
+            Some(value) => Some(
+                value
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid latency `{}`", value))?,
+            ),
+            None => None,
+        },
+        throttle: args.value_of("throttle").map(String::from),
+        fail_rate: match args.value_of("fail-rate") {
+            Some(value) => Some(
+                value
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid fail rate `{}`", value))?,
+            ),
+            None => None,
+        },
+        fail_status: match args.values_of("fail-status") {
+            Some(values) => values
+                .map(|value| {
+                    value
+                        .parse()
+                        .map_err(|_| anyhow!("Invalid status code `{}`", value))
+                })
+                .collect::<Result<_>>()?,
+            None => vec![],
+        },
+    };
+
+    if rule.latency.is_some() || rule.throttle.is_some() || rule.fail_rate.is_some() {
+        // the later rules override the earlier ones, the options override the file
+        config.rules.push(rule);
+        serve::network::Network::new(&config)?;
+    }
+
+    Ok(config)
+}
+
+/// Renders the template file from the environment variables
+pub fn render_config(args: &ArgMatches) {
+    let path = Path::new(args.value_of("template").unwrap());
+    let env_files: Vec<&Path> = args
+        .values_of("env-file")
+        .map(|files| files.map(Path::new).collect())
+        .unwrap_or_default();
+
+    let rendered =
+        match template::load_vars(&env_files).and_then(|vars| template::render_file(path, &vars)) {
+            Ok(rendered) => rendered,
+            Err(err) => {
+                error!("Error rendering `{}`. {}", path.display(), err);
+                process::exit(1);
+            }
+        };
+
+    if !rendered.missing.is_empty() {
+        let missing = rendered.missing.join(", ");
+        if args.is_present("allow-missing") {
+            warn!("Rendered without the variables {}", missing);
+        } else {
+            error!("Missing variables {}", missing);
+            process::exit(1);
+        }
+    }
+
+    match args.value_of("output") {
+        Some(output) => {
+            if let Err(err) = fs::write(output, rendered.text) {
+                error!("Error writing `{}`. {}", output, err);
+                process::exit(1);
+            }
+        }
+        None => print!("{}", rendered.text),
+    }
+}
+
+/// Infers JSON Schema from the sample files
+pub fn infer_schema(args: &ArgMatches) {
+    let mut samples = vec![];
+
+    for file in args.values_of("file").unwrap() {
+        match json::read_json(Path::new(file)) {
+            Some(value) => samples.push(value),
+            None => process::exit(1),
+        }
+    }
+
+    let options = schema::InferOptions {
+        enum_threshold: args
+            .value_of_t("enum-threshold")
+            .unwrap_or_else(|err| err.exit()),
+    };
+
+    let inferred = schema::infer_schema(&samples, &options);
+    let text = serde_json::to_string_pretty(&inferred).unwrap();
+
+    match args.value_of("output") {
+        Some(output) => {
+            if let Err(err) = fs::write(output, text + "\n") {
+                error!("Error writing `{}`. {}", output, err);
+                process::exit(1);
+            }
+            info!("Schema written to `{}`", output);
+        }
+        None => println!("{}", text),
+    }
+}
+
+/// Formats JSON files or checks that they are formatted
+pub fn format_json(args: &ArgMatches) {
+    let files: Vec<_> = args.values_of("file").unwrap().map(Path::new).collect();
+    let options = format::FormatOptions {
+        indent: args.value_of_t("indent").unwrap_or_else(|err| err.exit()),
+        sort_keys: args.is_present("sort-keys"),
+        sort_pointers: args
+            .values_of("sort-keys-at")
+            .map(|values| values.map(String::from).collect())
+            .unwrap_or_default(),
+        final_newline: !args.is_present("no-final-newline"),
+    };
+
+    if args.is_present("check") {
+        if format::check_files(&files, &options) {
+            info!("All files are formatted");
+            process::exit(0);
+        } else {
+            error!("Formatting check failed");
+            process::exit(1);
+        }
+    }
+
+    if !format::format_files(&files, &options) {
+        process::exit(1);
+    }
+}
+
+/// Compares two JSON files, exits with `1` when they differ
+pub fn diff_json(args: &ArgMatches) {
+    let left = json::read_json(Path::new(args.value_of("left").unwrap()));
+    let right = json::read_json(Path::new(args.value_of("right").unwrap()));
+
+    let (left, right) = match (left, right) {
+        (Some(left), Some(right)) => (left, right),
+        _ => process::exit(2),
+    };
+
+    let changes = diff::diff(&left, &right);
+
+    match args.value_of("format") {
+        Some("patch") => {
+            let patch = diff::to_patch(&changes);
+            println!("{}", serde_json::to_string_pretty(&patch).unwrap());
+        }
+        _ => {
+            for change in &changes {
+                println!("{}", change);
+            }
+        }
+    }
+
+    process::exit(if changes.is_empty() { 0 } else { 1 });
+}
+
+/// Verifies the translation files of the workspace projects
+pub fn check_i18n(args: &ArgMatches) {
+    let valid = match check_project_translations(args) {
+        Ok(valid) => valid,
+        Err(err) => {
+            error!("{}", err);
+            process::exit(1);
+        }
+    };
+
+    if valid {
+        info!("Validation succeeded");
+        process::exit(0);
+    } else {
+        error!("Validation failed");
+        process::exit(1);
+    }
+}
+
+fn check_project_translations(args: &ArgMatches) -> Result<bool> {
+    let config_path = get_workspace_config_path(args)?;
+    let config = angular::read_config(config_path.clone())?;
+    let workspace_root = config_path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut names: Vec<_> = match args.value_of("project") {
+        Some(name) => vec![name.to_string()],
+        None => config
+            .projects
+            .iter()
+            .flat_map(|projects| projects.keys().cloned())
+            .collect(),
+    };
+    names.sort();
+
+    let mut valid = true;
+
+    for name in names {
+        let project = config
+            .projects
+            .as_ref()
+            .and_then(|projects| projects.get(&name))
+            .ok_or_else(|| anyhow!("Project `{}` not found", name))?;
+
+        let locales = match project.i18n.as_ref().and_then(|i18n| i18n.locales.as_ref()) {
+            Some(locales) => locales,
+            None => continue,
+        };
+
+        let source_path = match args.value_of("source") {
+            Some(value) => PathBuf::from(value),
+            None => match locales
+                .values()
+                .flat_map(|locale| locale.translations())
+                .next()
+            {
+                Some(path) => i18n::default_source_path(&workspace_root.join(path)),
+                None => continue,
+            },
+        };
+
+        info!(
+            "Checking `{}` translations with `{}`",
+            name,
+            source_path.display()
+        );
+        let source = i18n::read_translation_file(&source_path)?;
+
+        for (locale, locale_config) in locales {
+            let mut translation = i18n::TranslationFile::default();
+            for path in locale_config.translations() {
+                translation.merge(i18n::read_translation_file(&workspace_root.join(path))?);
+            }
+
+            for issue in i18n::check_translations(&source, &translation) {
+                error!("{} ({}): {}", name, locale, issue);
+                valid = false;
+            }
+        }
+    }
+
+    Ok(valid)
+}
+
+/// Verifies that the project packages are consistent with the root package
+pub fn check_deps(args: &ArgMatches) {
+    let valid = match check_project_packages(args) {
+        Ok(valid) => valid,
+        Err(err) => {
+            error!("{}", err);
+            process::exit(1);
+        }
+    };
+
+    if valid {
+        info!("Validation succeeded");
+        process::exit(0);
+    } else {
+        error!("Validation failed");
+        process::exit(1);
+    }
+}
+
+fn check_project_packages(args: &ArgMatches) -> Result<bool> {
+    let config_path = get_workspace_config_path(args)?;
+    let config = angular::read_config(config_path.clone())?;
+    let workspace_root = config_path.parent().unwrap_or_else(|| Path::new(""));
+    let fix = args.is_present("fix");
+
+    let root_path = workspace_root.join("package.json");
+    let root = json::read_json(&root_path)
+        .ok_or_else(|| anyhow!("Error reading `{}`", root_path.display()))?;
+
+    let mut valid = true;
+
+    for issue in deps::find_duplicates(&root) {
+        error!("package.json: {}", issue);
+        valid = false;
+    }
+
+    let mut projects: Vec<_> = config.projects.iter().flatten().collect();
+    projects.sort_by(|a, b| a.0.cmp(b.0));
+
+    for (name, project) in projects {
+        let package_path = match project.root.as_deref() {
+            Some(root) if !root.is_empty() => workspace_root.join(root).join("package.json"),
+            _ => continue,
+        };
+
+        if !package_path.exists() {
+            continue;
+        }
+
+        let text = fs::read_to_string(&package_path)
+            .map_err(|err| anyhow!("Error reading `{}`. {}", package_path.display(), err))?;
+        let mut package = serde_json::from_str(&text)
+            .map_err(|err| anyhow!("Error reading `{}`. {}", package_path.display(), err))?;
+
+        if fix {
+            let changes = deps::fix_package(&root, &mut package);
+            if !changes.is_empty() {
+                let options = format::FormatOptions::default();
+                fs::write(
+                    &package_path,
+                    format::format_text_with(&text, &changes, &options)?,
+                )?;
+                info!("{}: aligned {} version range(s)", name, changes.len());
+            }
+        }
+
+        for issue in deps::check_package(&root, &package) {
+            error!("{}: {}", name, issue);
+            valid = false;
+        }
+    }
+
+    Ok(valid)
+}
+
+/// Verifies the service worker manifest and the web app manifest of the build output
+pub fn check_pwa(args: &ArgMatches) {
+    let dist_dir = Path::new(args.value_of("dir").unwrap());
+    info!("Checking `{}`", dist_dir.display());
+
+    let issues = match pwa::check_pwa(dist_dir) {
+        Ok(issues) => issues,
+        Err(err) => {
+            error!("{}", err);
+            process::exit(1);
+        }
+    };
+    for issue in &issues {
+        error!("{}", issue);
+    }
+
+    if issues.is_empty() {
+        info!("Validation succeeded");
+        process::exit(0);
+    } else {
+        error!("Validation failed");
+        process::exit(1);
+    }
+}
+
+/// Measures the build output against the budgets of the build configuration
+pub fn check_budgets(args: &ArgMatches) {
+    let valid = match check_project_budgets(args) {
+        Ok(valid) => valid,
+        Err(err) => {
+            error!("{}", err);
+            process::exit(1);
+        }
+    };
+
+    if valid {
+        info!("Validation succeeded");
+        process::exit(0);
+    } else {
+        error!("Validation failed");
+        process::exit(1);
+    }
+}
+
+fn check_project_budgets(args: &ArgMatches) -> Result<bool> {
+    let config_path = get_workspace_config_path(args)?;
+    let config = angular::read_config(config_path.clone())?;
+    let workspace_root = config_path.parent().unwrap_or_else(|| Path::new(""));
+    let configuration = args.value_of("configuration");
+    let kind: budgets::SizeKind = match args.value_of("size") {
+        Some(value) => value.parse()?,
+        None => budgets::SizeKind::default(),
+    };
+
+    let baseline = match args.value_of("baseline") {
+        Some(path) => Some(budgets::read_baseline(Path::new(path))?),
+        None => None,
+    };
+    let mut saved = budgets::Baseline::new();
+
+    let mut names: Vec<_> = match args.value_of("project") {
+        Some(name) => vec![name.to_string()],
+        None => config
+            .projects
+            .iter()
+            .flat_map(|projects| projects.iter())
+            .filter(|(_, project)| project.project_type == angular::ProjectType::Application)
+            .map(|(name, _)| name.clone())
+            .collect(),
+    };
+    names.sort();
+
+    let mut valid = true;
+
+    for name in names {
+        let project = config
+            .projects
+            .as_ref()
+            .and_then(|projects| projects.get(&name))
+            .ok_or_else(|| anyhow!("Project `{}` not found", name))?;
+
+        let project_budgets = match project
+            .target_options("build", configuration)
+            .and_then(|options| options.budgets)
+        {
+            Some(project_budgets) if !project_budgets.is_empty() => project_budgets,
+            _ => {
+                info!("{}: no budgets", name);
+                continue;
+            }
+        };
+
+        let component_styles = if project_budgets
+            .iter()
+            .any(|budget| budget.budget_type == angular::BudgetType::AnyComponentStyle)
+        {
+            let source_root = match (&project.source_root, &project.root) {
+                (Some(source_root), _) => workspace_root.join(source_root),
+                (None, Some(root)) => workspace_root.join(root).join("src"),
+                (None, None) => workspace_root.join("src"),
+            };
+            budgets::read_component_styles(&source_root)?
+        } else {
+            vec![]
+        };
+
+        let outputs = project
+            .build_outputs(configuration)
+            .map_err(|err| anyhow!("Project `{}`. {}", name, err))?;
+        for output in outputs {
+            let key = match &output.locale {
+                Some(locale) => format!("{}/{}", name, locale),
+                None => name.clone(),
+            };
+            let dist_dir = match args.value_of("dist") {
+                Some(dir) => match &output.locale {
+                    Some(locale) => Path::new(dir).join(locale),
+                    None => PathBuf::from(dir),
+                },
+                None => workspace_root.join(&output.dir),
+            };
+            if !dist_dir.is_dir() {
+                return Err(anyhow!(
+                    "Build output `{}` of `{}` not found",
+                    dist_dir.display(),
+                    key
+                ));
+            }
+
+            info!("Checking `{}` budgets in `{}`", key, dist_dir.display());
+            let initial = match fs::read_to_string(dist_dir.join("index.html")) {
+                Ok(index) => {
+                    budgets::initial_files(&index, &output.base_href, output.deploy_url.as_deref())
+                }
+                Err(_) => vec![],
+            };
+            let stats = budgets::BuildStats {
+                files: budgets::read_build_files(&dist_dir)?,
+                initial,
+                component_styles: component_styles.clone(),
+            };
+
+            let rows = budgets::check_budgets(&project_budgets, &stats, kind)?;
+            for row in &rows {
+                match &row.status {
+                    budgets::Status::Ok => {}
+                    budgets::Status::Warning(message) => {
+                        warn!("{}: {} {}", key, row.label, message)
+                    }
+                    budgets::Status::Error(message) => {
+                        error!("{}: {} {}", key, row.label, message);
+                        valid = false;
+                    }
+                }
+            }
+
+            let project_baseline = baseline.as_ref().and_then(|baseline| baseline.get(&key));
+            if baseline.is_some() && project_baseline.is_none() {
+                warn!("{}: not found in the baseline", key);
+            }
+            println!("{}", key);
+            print!("{}", budgets::render_table(&rows, project_baseline, kind));
+
+            saved.insert(
+                key,
+                rows.iter()
+                    .map(|row| (row.label.clone(), row.sizes))
+                    .collect(),
+            );
+        }
+    }
+
+    if let Some(path) = args.value_of("save-baseline") {
+        budgets::write_baseline(Path::new(path), &saved)?;
+        info!("Baseline saved to `{}`", path);
+    }
+
+    Ok(valid)
+}
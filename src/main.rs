@@ -1,7 +1,9 @@
 use anyhow::Result;
 use clap::{crate_authors, crate_description, crate_name, crate_version, App, AppSettings, Arg};
+use log::error;
 use rung::{angular, logger};
 use std::path::PathBuf;
+use std::process;
 
 fn angular_config_arg<'a>() -> Arg<'a> {
     Arg::new("config")
@@ -12,6 +14,14 @@ fn angular_config_arg<'a>() -> Arg<'a> {
         .default_value("angular.json")
 }
 
+fn project_arg<'a>() -> Arg<'a> {
+    Arg::new("project")
+        .about("The name of the project.")
+        .long("project")
+        .value_name("NAME")
+        .takes_value(true)
+}
+
 fn template_arg<'a>() -> Arg<'a> {
     Arg::new("template")
         .about("template file")
@@ -42,6 +52,15 @@ fn directory_arg<'a>() -> Arg<'a> {
         .takes_value(true)
 }
 
+fn env_file_arg<'a>() -> Arg<'a> {
+    Arg::new("env-file")
+        .about("Reads the variables from the `.env` file, the environment takes precedence.")
+        .long("env-file")
+        .value_name("FILE")
+        .takes_value(true)
+        .multiple_occurrences(true)
+}
+
 fn main() -> Result<()> {
     logger::init_logger();
 
@@ -67,6 +86,203 @@ fn main() -> Result<()> {
                         .about("Validates a JSON file matches the JSON Schema file")
                         .arg(input_file_arg())
                         .arg(template_arg()),
+                )
+                .subcommand(
+                    App::new("budgets")
+                        .version(crate_version!())
+                        .about("Validates the build output against the budgets of the build configuration")
+                        .arg(angular_config_arg())
+                        .arg(project_arg())
+                        .arg(
+                            Arg::new("configuration")
+                                .about("Build configuration of the project, the default one if not set.")
+                                .long("configuration")
+                                .value_name("NAME")
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::new("dist")
+                                .about("Build output directory, the `outputPath` of the project by default.")
+                                .long("dist")
+                                .value_name("DIR")
+                                .takes_value(true)
+                                .requires("project"),
+                        )
+                        .arg(
+                            Arg::new("size")
+                                .about("Size compared against the budgets, `raw` by default.")
+                                .long("size")
+                                .value_name("SIZE")
+                                .takes_value(true)
+                                .possible_values(&["raw", "gzip", "brotli"]),
+                        )
+                        .arg(
+                            Arg::new("baseline")
+                                .about("Sizes of a previous build to compare with.")
+                                .long("baseline")
+                                .value_name("FILE")
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::new("save-baseline")
+                                .about("Saves the measured sizes as the baseline.")
+                                .long("save-baseline")
+                                .value_name("FILE")
+                                .takes_value(true),
+                        ),
+                )
+                .subcommand(
+                    App::new("deps")
+                        .version(crate_version!())
+                        .about("Validates that the project dependencies match the root package.json")
+                        .arg(angular_config_arg())
+                        .arg(
+                            Arg::new("fix")
+                                .about("Aligns conflicting version ranges to the root package.json.")
+                                .long("fix"),
+                        ),
+                )
+                .subcommand(
+                    App::new("i18n")
+                        .version(crate_version!())
+                        .about("Validates that the translation files match the source locale")
+                        .arg(angular_config_arg())
+                        .arg(project_arg())
+                        .arg(
+                            Arg::new("source")
+                                .about("Source locale file, defaults to `messages.<ext>` next to the translations.")
+                                .long("source")
+                                .short('s')
+                                .value_name("FILE")
+                                .takes_value(true),
+                        ),
+                )
+                .subcommand(
+                    App::new("pwa")
+                        .version(crate_version!())
+                        .about("Validates the service worker and web app manifests of the build output")
+                        .arg(
+                            Arg::new("dir")
+                                .about("Build output directory")
+                                .index(1)
+                                .required(true),
+                        ),
+                ),
+        )
+        .subcommand(
+            App::new("diff")
+                .about("compares things")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    App::new("json")
+                        .version(crate_version!())
+                        .about("Compares two JSON files ignoring key order and formatting")
+                        .arg(
+                            Arg::new("left")
+                                .about("Original JSON file")
+                                .value_name("LEFT")
+                                .required(true)
+                                .index(1),
+                        )
+                        .arg(
+                            Arg::new("right")
+                                .about("Modified JSON file")
+                                .value_name("RIGHT")
+                                .required(true)
+                                .index(2),
+                        )
+                        .arg(
+                            Arg::new("format")
+                                .about("Output format.")
+                                .long("format")
+                                .value_name("FORMAT")
+                                .takes_value(true)
+                                .possible_values(&["text", "patch"])
+                                .default_value("text"),
+                        ),
+                ),
+        )
+        .subcommand(
+            App::new("fmt")
+                .about("formats things")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    App::new("json")
+                        .version(crate_version!())
+                        .about("Formats JSON files")
+                        .arg(
+                            Arg::new("file")
+                                .about("JSON file(s) to format")
+                                .value_name("FILE")
+                                .required(true)
+                                .multiple(true)
+                                .index(1),
+                        )
+                        .arg(
+                            Arg::new("check")
+                                .about("Lists unformatted files and exits with an error instead of formatting.")
+                                .long("check"),
+                        )
+                        .arg(
+                            Arg::new("indent")
+                                .about("Number of spaces or `tab`.")
+                                .long("indent")
+                                .value_name("INDENT")
+                                .takes_value(true)
+                                .default_value("2"),
+                        )
+                        .arg(
+                            Arg::new("sort-keys")
+                                .about("Sorts the keys of all objects.")
+                                .long("sort-keys"),
+                        )
+                        .arg(
+                            Arg::new("sort-keys-at")
+                                .about("Sorts the keys of the object at the JSON pointer.")
+                                .long("sort-keys-at")
+                                .value_name("POINTER")
+                                .takes_value(true)
+                                .multiple_occurrences(true),
+                        )
+                        .arg(
+                            Arg::new("no-final-newline")
+                                .about("Does not terminate the output with a newline.")
+                                .long("no-final-newline"),
+                        ),
+                ),
+        )
+        .subcommand(
+            App::new("schema")
+                .about("JSON Schema tools")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    App::new("infer")
+                        .version(crate_version!())
+                        .about("Infers a JSON Schema from one or more sample JSON files")
+                        .arg(
+                            Arg::new("file")
+                                .about("Sample JSON file(s)")
+                                .value_name("FILE")
+                                .required(true)
+                                .multiple(true)
+                                .index(1),
+                        )
+                        .arg(
+                            Arg::new("output")
+                                .about("Writes the schema to the file instead of stdout.")
+                                .long("output")
+                                .short('o')
+                                .value_name("FILE")
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::new("enum-threshold")
+                                .about("Maximum number of distinct string values turned into an enum, 0 to disable.")
+                                .long("enum-threshold")
+                                .value_name("COUNT")
+                                .takes_value(true)
+                                .default_value("5"),
+                        ),
                 ),
         )
         .subcommand(
@@ -76,9 +292,45 @@ fn main() -> Result<()> {
                 .arg(
                     Arg::new("dir")
                         .about("Target directory")
-                        .required(true)
+                        .required_unless_present_any(["project", "mount"])
                         .index(1),
                 )
+                .arg(
+                    Arg::new("mount")
+                        .about("Serves the directory under the path prefix, i.e. `/admin=dist/admin,spa`.")
+                        .long("mount")
+                        .value_name("PREFIX=DIR")
+                        .takes_value(true)
+                        .multiple_occurrences(true),
+                )
+                .arg(angular_config_arg())
+                .arg(
+                    project_arg()
+                        .about("Serves the build output of the workspace project.")
+                        .conflicts_with("dir"),
+                )
+                .arg(
+                    Arg::new("configuration")
+                        .about("Build configuration of the project, the default one if not set.")
+                        .long("configuration")
+                        .value_name("NAME")
+                        .takes_value(true)
+                        .requires("project"),
+                )
+                .arg(
+                    Arg::new("i18n")
+                        .about("Redirects to the locale of the localized project build by the cookie or `Accept-Language`.")
+                        .long("i18n")
+                        .requires("project"),
+                )
+                .arg(
+                    Arg::new("locale-cookie")
+                        .about("Cookie with the preferred locale, `locale` by default.")
+                        .long("locale-cookie")
+                        .value_name("NAME")
+                        .takes_value(true)
+                        .requires("i18n"),
+                )
                 .arg(
                     Arg::new("host")
                         .about("Host to listen on.")
@@ -90,7 +342,7 @@ fn main() -> Result<()> {
                 )
                 .arg(
                     Arg::new("port")
-                        .about("Port to listen on.")
+                        .about("Port to listen on, `auto` for any free port.")
                         .long("port")
                         .short('p')
                         .value_name("PORT")
@@ -102,6 +354,292 @@ fn main() -> Result<()> {
                         .about("Opens the url in default browser.")
                         .long("open")
                         .short('o'),
+                )
+                .arg(
+                    Arg::new("spa")
+                        .about("Serves index.html with 200 status for unknown HTML navigation requests.")
+                        .long("spa")
+                        .conflicts_with_all(&["not-found", "strict"]),
+                )
+                .arg(
+                    Arg::new("not-found")
+                        .about("Serves the page with 404 status for unknown requests.")
+                        .long("not-found")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .conflicts_with("strict"),
+                )
+                .arg(
+                    Arg::new("strict")
+                        .about("Responds with plain 404 status for unknown requests.")
+                        .long("strict"),
+                )
+                .arg(
+                    Arg::new("list")
+                        .about("Lists the directories without the index page, as JSON for `Accept: application/json`.")
+                        .long("list"),
+                )
+                .arg(
+                    Arg::new("ssl")
+                        .about("Serves using HTTPS, with a self-signed certificate unless provided.")
+                        .long("ssl"),
+                )
+                .arg(
+                    Arg::new("ssl-cert")
+                        .about("SSL certificate file in PEM format.")
+                        .long("ssl-cert")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .requires_all(&["ssl", "ssl-key"]),
+                )
+                .arg(
+                    Arg::new("ssl-key")
+                        .about("SSL private key file in PEM format.")
+                        .long("ssl-key")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .requires_all(&["ssl", "ssl-cert"]),
+                )
+                .arg(
+                    Arg::new("proxy-config")
+                        .about("Proxy configuration file in the Angular CLI format.")
+                        .long("proxy-config")
+                        .value_name("FILE")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("auth")
+                        .about("Requires the HTTP basic auth with the `user:password` credentials.")
+                        .long("auth")
+                        .value_name("USER:PASS")
+                        .takes_value(true)
+                        .multiple_occurrences(true),
+                )
+                .arg(
+                    Arg::new("htpasswd")
+                        .about("Requires the HTTP basic auth with the users of the htpasswd file, bcrypt only.")
+                        .long("htpasswd")
+                        .value_name("FILE")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("allow")
+                        .about("Allows the requests from the address or network only, i.e. `192.168.1.0/24`.")
+                        .long("allow")
+                        .value_name("CIDR")
+                        .takes_value(true)
+                        .multiple_occurrences(true),
+                )
+                .arg(
+                    Arg::new("record")
+                        .about("Saves the proxied requests and responses to the directory.")
+                        .long("record")
+                        .value_name("DIR")
+                        .takes_value(true)
+                        .requires("proxy-config")
+                        .conflicts_with("replay"),
+                )
+                .arg(
+                    Arg::new("replay")
+                        .about("Answers the proxied requests from the recordings in the directory.")
+                        .long("replay")
+                        .value_name("DIR")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("match")
+                        .about("Request fields the recordings have to match, of `method`, `path`, `query` and `body`, `method,path,query` by default.")
+                        .long("match")
+                        .value_name("FIELDS")
+                        .takes_value(true)
+                        .requires("replay"),
+                )
+                .arg(
+                    Arg::new("header")
+                        .about("Adds the `Name: value` header to all responses.")
+                        .long("header")
+                        .value_name("HEADER")
+                        .takes_value(true)
+                        .multiple_occurrences(true),
+                )
+                .arg(
+                    Arg::new("headers-config")
+                        .about("Response headers configuration file.")
+                        .long("headers-config")
+                        .value_name("FILE")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("csp")
+                        .about("Content-Security-Policy header value.")
+                        .long("csp")
+                        .value_name("POLICY")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("csp-report")
+                        .about("Reports the CSP violations to the server log instead of enforcing the policy.")
+                        .long("csp-report"),
+                )
+                .arg(
+                    Arg::new("cors")
+                        .about("Allows cross-origin requests from the origin, `*` for any origin.")
+                        .long("cors")
+                        .value_name("ORIGIN")
+                        .takes_value(true)
+                        .multiple_occurrences(true),
+                )
+                .arg(
+                    Arg::new("security-headers")
+                        .about("Adds HSTS, X-Frame-Options, X-Content-Type-Options and Referrer-Policy headers.")
+                        .long("security-headers"),
+                )
+                .arg(
+                    Arg::new("mocks")
+                        .about("Answers the API requests from the JSON fixtures in the directory.")
+                        .long("mocks")
+                        .value_name("DIR")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("mocks-prefix")
+                        .about("Path prefix of the mocked requests, `/api` by default.")
+                        .long("mocks-prefix")
+                        .value_name("PATH")
+                        .takes_value(true)
+                        .requires("mocks"),
+                )
+                .arg(
+                    Arg::new("config-template")
+                        .about("Renders the matching files from the environment variables, i.e. `assets/config.json`.")
+                        .long("config-template")
+                        .value_name("GLOB")
+                        .takes_value(true)
+                        .multiple_occurrences(true),
+                )
+                .arg(env_file_arg().requires("config-template"))
+                .arg(
+                    Arg::new("network-config")
+                        .about("Simulates the network conditions per route, from the JSON file.")
+                        .long("network-config")
+                        .value_name("FILE")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("latency")
+                        .about("Delays all responses by the milliseconds.")
+                        .long("latency")
+                        .value_name("MS")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("throttle")
+                        .about("Limits the throughput, `slow-3g`, `fast-3g` or kilobytes per second.")
+                        .long("throttle")
+                        .value_name("PRESET|KBPS")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("fail-rate")
+                        .about("Fails the percentage of the requests.")
+                        .long("fail-rate")
+                        .value_name("PERCENT")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("fail-status")
+                        .about("Status code of the failed requests, picked at random, `503` by default.")
+                        .long("fail-status")
+                        .value_name("CODE")
+                        .takes_value(true)
+                        .multiple_occurrences(true)
+                        .requires("fail-rate"),
+                )
+                .arg(
+                    Arg::new("access-log")
+                        .about("Writes the access log to the file.")
+                        .long("access-log")
+                        .value_name("FILE")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("access-log-format")
+                        .about("Access log format, `json` or `combined`, `json` by default.")
+                        .long("access-log-format")
+                        .value_name("FORMAT")
+                        .takes_value(true)
+                        .possible_values(&["json", "combined"])
+                        .requires("access-log"),
+                )
+                .arg(
+                    Arg::new("access-log-rotate")
+                        .about("Rotates the access log by size or time, i.e. `10MB` or `daily`.")
+                        .long("access-log-rotate")
+                        .value_name("SIZE|PERIOD")
+                        .takes_value(true)
+                        .multiple_occurrences(true)
+                        .requires("access-log"),
+                )
+                .arg(
+                    Arg::new("access-log-keep")
+                        .about("Number of the rotated access logs to keep, `7` by default.")
+                        .long("access-log-keep")
+                        .value_name("COUNT")
+                        .takes_value(true)
+                        .requires("access-log-rotate"),
+                )
+                .arg(
+                    Arg::new("access-log-timing")
+                        .about("Logs the duration of the requests.")
+                        .long("access-log-timing")
+                        .requires("access-log"),
+                )
+                .arg(
+                    Arg::new("request-id")
+                        .about("Adds the `X-Request-Id` header to the requests and responses, and logs it.")
+                        .long("request-id"),
+                )
+                .arg(
+                    Arg::new("metrics")
+                        .about("Serves the `/__rung/health` check and the `/__rung/metrics` Prometheus metrics.")
+                        .long("metrics"),
+                )
+                .arg(
+                    Arg::new("ready-file")
+                        .about("Writes the server url to the file once it is listening.")
+                        .long("ready-file")
+                        .value_name("FILE")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("watch")
+                        .about("Reloads the pages when the files change.")
+                        .long("watch")
+                        .short('w'),
+                ),
+        )
+        .subcommand(
+            App::new("render-config")
+                .about("Renders the `${NAME}` placeholders of the template from the environment variables")
+                .arg(
+                    Arg::new("template")
+                        .about("Template file")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("output")
+                        .about("Writes the result to the file instead of the standard output.")
+                        .long("output")
+                        .short('o')
+                        .value_name("FILE")
+                        .takes_value(true),
+                )
+                .arg(env_file_arg())
+                .arg(
+                    Arg::new("allow-missing")
+                        .about("Renders the variables without a value and a default empty instead of failing.")
+                        .long("allow-missing"),
                 ),
         )
         .subcommand(
@@ -138,9 +676,31 @@ fn main() -> Result<()> {
         Some(("check", check_matches)) => match check_matches.subcommand() {
             Some(("header", header_matches)) => rung::check_files_headers(header_matches),
             Some(("json", json_matches)) => rung::validate_json(json_matches),
+            Some(("i18n", i18n_matches)) => rung::check_i18n(i18n_matches),
+            Some(("deps", deps_matches)) => rung::check_deps(deps_matches),
+            Some(("pwa", pwa_matches)) => rung::check_pwa(pwa_matches),
+            Some(("budgets", budgets_matches)) => rung::check_budgets(budgets_matches),
+            _ => unreachable!(),
+        },
+        Some(("diff", diff_matches)) => match diff_matches.subcommand() {
+            Some(("json", json_matches)) => rung::diff_json(json_matches),
+            _ => unreachable!(),
+        },
+        Some(("fmt", fmt_matches)) => match fmt_matches.subcommand() {
+            Some(("json", json_matches)) => rung::format_json(json_matches),
             _ => unreachable!(),
         },
-        Some(("serve", serve_matches)) => rung::serve(serve_matches).unwrap(),
+        Some(("schema", schema_matches)) => match schema_matches.subcommand() {
+            Some(("infer", infer_matches)) => rung::infer_schema(infer_matches),
+            _ => unreachable!(),
+        },
+        Some(("serve", serve_matches)) => {
+            if let Err(err) = rung::serve(serve_matches) {
+                error!("{}", err);
+                process::exit(1);
+            }
+        }
+        Some(("render-config", render_matches)) => rung::render_config(render_matches),
         Some(("ls", ls_matches)) => match ls_matches.subcommand() {
             Some(("apps", apps_matches)) => {
                 let config = rung::get_workspace_config(apps_matches)?;
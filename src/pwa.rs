@@ -0,0 +1,402 @@
+//! # Service worker utils
+//!
+//! Verifies the Angular service worker manifest (`ngsw.json`) and the web app manifest
+//! of the application build output.
+
+use anyhow::{anyhow, Result};
+use percent_encoding::percent_decode_str;
+use regex::Regex;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Icon sizes required to install the application
+pub const REQUIRED_ICON_SIZES: [&str; 2] = ["192x192", "512x512"];
+
+/// Web app manifest file names, the Angular CLI one first
+const MANIFEST_FILES: [&str; 2] = ["manifest.webmanifest", "manifest.json"];
+
+/// Service worker manifest generated by the Angular CLI
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NgswManifest {
+    pub config_version: u32,
+    /// Url of the index page, under the base href
+    pub index: String,
+    #[serde(default)]
+    pub asset_groups: Vec<AssetGroup>,
+    /// SHA-1 hashes of the cached urls
+    #[serde(default)]
+    pub hash_table: BTreeMap<String, String>,
+    #[serde(default)]
+    pub navigation_urls: Vec<NavigationUrl>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetGroup {
+    pub name: String,
+    #[serde(default)]
+    pub urls: Vec<String>,
+}
+
+/// Navigation url rule, the urls matching the positive rules and none of the negative ones
+/// are answered with the index page
+#[derive(Deserialize, Debug)]
+pub struct NavigationUrl {
+    pub positive: bool,
+    /// JavaScript regular expression
+    pub regex: String,
+}
+
+/// Web app manifest
+#[derive(Deserialize, Debug)]
+pub struct WebManifest {
+    pub name: Option<String>,
+    pub short_name: Option<String>,
+    pub start_url: Option<String>,
+    pub display: Option<String>,
+    #[serde(default)]
+    pub icons: Vec<Icon>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Icon {
+    pub src: String,
+    /// Space separated sizes, i.e. `192x192 256x256` or `any`
+    pub sizes: Option<String>,
+}
+
+/// Service worker or web app manifest issue
+#[derive(Debug, PartialEq)]
+pub enum Issue {
+    /// Url is listed, but the file does not exist.
+    Stale { url: String },
+    /// File content does not match the hash, the service worker keeps the old version.
+    HashMismatch {
+        url: String,
+        expected: String,
+        actual: String,
+    },
+    /// Asset group url has no hash.
+    Unhashed { url: String },
+    /// Hash table entry is not listed in any asset group.
+    Unreferenced { url: String },
+    /// Navigation url regular expression does not compile.
+    InvalidNavigationUrl { regex: String },
+    /// Navigation to the url is not answered with the index page.
+    NavigationNotHandled { url: String },
+    /// Web app manifest is missing.
+    MissingManifest,
+    /// Web app manifest field is missing or invalid.
+    Manifest(String),
+    /// Icon file does not exist.
+    MissingIcon { src: String },
+    /// Icon image size is not one of the declared sizes.
+    IconSize {
+        src: String,
+        declared: String,
+        actual: String,
+    },
+    /// No icon of the size required to install the application.
+    MissingIconSize(String),
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::Stale { url } => write!(f, "stale entry `{}`, the file does not exist", url),
+            Issue::HashMismatch {
+                url,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "hash of `{}` does not match, expected `{}` but the file has `{}`",
+                url, expected, actual
+            ),
+            Issue::Unhashed { url } => write!(f, "`{}` is missing in the hashTable", url),
+            Issue::Unreferenced { url } => write!(
+                f,
+                "hashTable entry `{}` is not listed in any asset group",
+                url
+            ),
+            Issue::InvalidNavigationUrl { regex } => {
+                write!(f, "invalid navigation url regex `{}`", regex)
+            }
+            Issue::NavigationNotHandled { url } => write!(
+                f,
+                "navigation to `{}` is not handled by the service worker",
+                url
+            ),
+            Issue::MissingManifest => write!(f, "web app manifest not found"),
+            Issue::Manifest(message) => write!(f, "manifest: {}", message),
+            Issue::MissingIcon { src } => write!(f, "icon `{}` does not exist", src),
+            Issue::IconSize {
+                src,
+                declared,
+                actual,
+            } => write!(
+                f,
+                "icon `{}` is {}, but declared as `{}`",
+                src, actual, declared
+            ),
+            Issue::MissingIconSize(size) => write!(f, "no {} icon", size),
+        }
+    }
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| anyhow!("Error reading `{}`. {}", path.display(), err))?;
+    serde_json::from_str(&text)
+        .map_err(|err| anyhow!("Error parsing `{}`. {}", path.display(), err))
+}
+
+/// Returns the base href of the build, the directory of the index url.
+pub fn base_href(ngsw: &NgswManifest) -> &str {
+    match ngsw.index.rfind('/') {
+        Some(index) => &ngsw.index[..=index],
+        None => "/",
+    }
+}
+
+/// Resolves the url to a file of the build output, `None` for the urls outside of the base href.
+pub fn resolve_url(dist_dir: &Path, base: &str, url: &str) -> Option<PathBuf> {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let rest = path.strip_prefix(base)?;
+    let rest = percent_decode_str(rest).decode_utf8().ok()?;
+    if rest.split('/').any(|segment| segment == "..") {
+        return None;
+    }
+    Some(dist_dir.join(rest.as_ref()))
+}
+
+/// Returns the hex SHA-1 hash of the file, as in the `hashTable`.
+pub fn file_hash(path: &Path) -> Result<String> {
+    Ok(format!("{:x}", Sha1::digest(&fs::read(path)?)))
+}
+
+/// Compiles the JavaScript regular expression of the navigation url.
+fn compile_regex(regex: &str) -> Option<Regex> {
+    // `\/` is a valid escape in JavaScript only
+    Regex::new(&regex.replace("\\/", "/")).ok()
+}
+
+/// Checks the navigation url rules the service worker applies to the url.
+pub fn is_navigation_handled(rules: &[(bool, Regex)], url: &str) -> bool {
+    let matches = |positive: bool| {
+        rules
+            .iter()
+            .filter(|(rule_positive, _)| *rule_positive == positive)
+            .any(|(_, regex)| regex.is_match(url))
+    };
+    let has_positive = rules.iter().any(|(positive, _)| *positive);
+    (!has_positive || matches(true)) && !matches(false)
+}
+
+/// Verifies the hashes and the urls of the service worker manifest.
+pub fn check_ngsw(dist_dir: &Path, ngsw: &NgswManifest) -> Vec<Issue> {
+    let base = base_href(ngsw);
+    let mut issues = vec![];
+
+    for (url, expected) in &ngsw.hash_table {
+        let file = match resolve_url(dist_dir, base, url) {
+            Some(file) if file.is_file() => file,
+            _ => {
+                issues.push(Issue::Stale { url: url.clone() });
+                continue;
+            }
+        };
+        match file_hash(&file) {
+            Ok(actual) if actual.eq_ignore_ascii_case(expected) => {}
+            Ok(actual) => issues.push(Issue::HashMismatch {
+                url: url.clone(),
+                expected: expected.clone(),
+                actual,
+            }),
+            Err(_) => issues.push(Issue::Stale { url: url.clone() }),
+        }
+    }
+
+    let mut listed = BTreeSet::new();
+    for url in ngsw.asset_groups.iter().flat_map(|group| &group.urls) {
+        listed.insert(url.as_str());
+        if ngsw.hash_table.contains_key(url) {
+            continue;
+        }
+        match resolve_url(dist_dir, base, url) {
+            Some(file) if file.is_file() => issues.push(Issue::Unhashed { url: url.clone() }),
+            _ => issues.push(Issue::Stale { url: url.clone() }),
+        }
+    }
+
+    for url in ngsw.hash_table.keys() {
+        if !listed.contains(url.as_str()) {
+            issues.push(Issue::Unreferenced { url: url.clone() });
+        }
+    }
+
+    if !ngsw.hash_table.contains_key(&ngsw.index) && !listed.contains(ngsw.index.as_str()) {
+        issues.push(Issue::Unhashed {
+            url: ngsw.index.clone(),
+        });
+    }
+
+    let mut rules = vec![];
+    for rule in &ngsw.navigation_urls {
+        match compile_regex(&rule.regex) {
+            Some(regex) => rules.push((rule.positive, regex)),
+            None => issues.push(Issue::InvalidNavigationUrl {
+                regex: rule.regex.clone(),
+            }),
+        }
+    }
+    // the base href and a deep link of the router
+    let deep_link = format!("{}some/route", base);
+    for url in [base, deep_link.as_str()] {
+        if !is_navigation_handled(&rules, url) {
+            issues.push(Issue::NavigationNotHandled {
+                url: url.to_string(),
+            });
+        }
+    }
+
+    issues
+}
+
+/// Reads the width and height of the PNG image.
+pub fn png_size(path: &Path) -> Option<(u32, u32)> {
+    let bytes = fs::read(path).ok()?;
+    // signature, then the `IHDR` chunk starting with the width and the height
+    if bytes.len() < 24 || &bytes[..8] != b"\x89PNG\r\n\x1a\n" || &bytes[12..16] != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes([bytes[16], bytes[17], bytes[18], bytes[19]]);
+    let height = u32::from_be_bytes([bytes[20], bytes[21], bytes[22], bytes[23]]);
+    Some((width, height))
+}
+
+/// Resolves the url relative to the manifest at the base href.
+fn manifest_url(base: &str, url: &str) -> String {
+    if url.starts_with('/') {
+        url.to_string()
+    } else {
+        format!("{}{}", base, url.trim_start_matches("./"))
+    }
+}
+
+/// Verifies the fields and the icons of the web app manifest.
+pub fn check_manifest(dist_dir: &Path, ngsw: &NgswManifest, manifest: &WebManifest) -> Vec<Issue> {
+    let base = base_href(ngsw);
+    let mut issues = vec![];
+
+    if manifest.name.is_none() && manifest.short_name.is_none() {
+        issues.push(Issue::Manifest(String::from(
+            "`name` or `short_name` is required",
+        )));
+    }
+    match manifest.display.as_deref() {
+        Some("fullscreen") | Some("standalone") | Some("minimal-ui") => {}
+        Some(display) => issues.push(Issue::Manifest(format!(
+            "`display` is `{}`, the application can not be installed",
+            display
+        ))),
+        None => issues.push(Issue::Manifest(String::from("`display` is required"))),
+    }
+
+    match manifest.start_url.as_deref() {
+        Some(start_url) if !start_url.contains("://") => {
+            let url = manifest_url(base, start_url);
+            let url = url.split(['?', '#']).next().unwrap_or_default();
+            let rules: Vec<_> = ngsw
+                .navigation_urls
+                .iter()
+                .filter_map(|rule| Some((rule.positive, compile_regex(&rule.regex)?)))
+                .collect();
+            if url != base && !is_navigation_handled(&rules, url) {
+                issues.push(Issue::NavigationNotHandled {
+                    url: url.to_string(),
+                });
+            }
+        }
+        Some(_) => {}
+        None => issues.push(Issue::Manifest(String::from("`start_url` is required"))),
+    }
+
+    let mut sizes = BTreeSet::new();
+    for icon in &manifest.icons {
+        let declared = icon.sizes.as_deref().unwrap_or_default();
+        let file = match resolve_url(dist_dir, base, &manifest_url(base, &icon.src)) {
+            Some(file) if file.is_file() => file,
+            _ => {
+                issues.push(Issue::MissingIcon {
+                    src: icon.src.clone(),
+                });
+                continue;
+            }
+        };
+
+        // only the PNG images are measured
+        match png_size(&file) {
+            Some((width, height)) => {
+                let actual = format!("{}x{}", width, height);
+                if !declared.split_whitespace().any(|size| size == actual) {
+                    issues.push(Issue::IconSize {
+                        src: icon.src.clone(),
+                        declared: declared.to_string(),
+                        actual: actual.clone(),
+                    });
+                }
+                sizes.insert(actual);
+            }
+            None => sizes.extend(declared.split_whitespace().map(String::from)),
+        }
+    }
+
+    for size in REQUIRED_ICON_SIZES.iter() {
+        if !sizes.contains(*size) && !sizes.contains("any") {
+            issues.push(Issue::MissingIconSize(size.to_string()));
+        }
+    }
+
+    issues
+}
+
+/// Verifies the service worker and the web app manifests of the build output.
+pub fn check_pwa(dist_dir: &Path) -> Result<Vec<Issue>> {
+    let ngsw_path = dist_dir.join("ngsw.json");
+    if !ngsw_path.is_file() {
+        return Err(anyhow!(
+            "`{}` not found, is the service worker enabled?",
+            ngsw_path.display()
+        ));
+    }
+    let ngsw: NgswManifest = read_json(&ngsw_path)?;
+    if ngsw.config_version != 1 {
+        return Err(anyhow!(
+            "Unsupported `ngsw.json` config version {}",
+            ngsw.config_version
+        ));
+    }
+
+    let mut issues = check_ngsw(dist_dir, &ngsw);
+
+    match MANIFEST_FILES
+        .iter()
+        .map(|name| dist_dir.join(name))
+        .find(|path| path.is_file())
+    {
+        Some(path) => {
+            let manifest: WebManifest = read_json(&path)?;
+            issues.extend(check_manifest(dist_dir, &ngsw, &manifest));
+        }
+        None => issues.push(Issue::MissingManifest),
+    }
+
+    Ok(issues)
+}
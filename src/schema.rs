@@ -0,0 +1,170 @@
+//! # JSON Schema utils
+//!
+//! Infers a draft-07 JSON Schema from one or more sample documents.
+
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// JSON Schema dialect used for the generated schemas
+pub const SCHEMA_DRAFT_07: &str = "http://json-schema.org/draft-07/schema#";
+
+/// Schema inference options
+pub struct InferOptions {
+    /// Maximum number of distinct string values to turn into an `enum`.
+    /// Use `0` to disable enum detection.
+    pub enum_threshold: usize,
+}
+
+impl Default for InferOptions {
+    fn default() -> Self {
+        InferOptions { enum_threshold: 5 }
+    }
+}
+
+/// Accumulated information about all the values seen at a single location
+#[derive(Default)]
+struct Shape {
+    null: bool,
+    boolean: bool,
+    integer: bool,
+    number: bool,
+    string_count: usize,
+    strings: BTreeSet<String>,
+    object_count: usize,
+    properties: BTreeMap<String, (usize, Shape)>,
+    array: bool,
+    items: Option<Box<Shape>>,
+}
+
+impl Shape {
+    fn add(&mut self, value: &Value) {
+        match value {
+            Value::Null => self.null = true,
+            Value::Bool(_) => self.boolean = true,
+            Value::Number(number) => {
+                if number.is_i64() || number.is_u64() {
+                    self.integer = true;
+                } else {
+                    self.number = true;
+                }
+            }
+            Value::String(text) => {
+                self.string_count += 1;
+                self.strings.insert(text.to_string());
+            }
+            Value::Array(items) => {
+                self.array = true;
+                for item in items {
+                    self.items.get_or_insert_with(Box::default).add(item);
+                }
+            }
+            Value::Object(map) => {
+                self.object_count += 1;
+                for (key, value) in map {
+                    let entry = self.properties.entry(key.to_string()).or_default();
+                    entry.0 += 1;
+                    entry.1.add(value);
+                }
+            }
+        }
+    }
+
+    fn type_names(&self) -> Vec<&'static str> {
+        let mut types = vec![];
+
+        if self.null {
+            types.push("null");
+        }
+        if self.boolean {
+            types.push("boolean");
+        }
+        if self.number {
+            types.push("number");
+        } else if self.integer {
+            types.push("integer");
+        }
+        if self.string_count > 0 {
+            types.push("string");
+        }
+        if self.array {
+            types.push("array");
+        }
+        if self.object_count > 0 {
+            types.push("object");
+        }
+
+        types
+    }
+
+    fn to_schema(&self, options: &InferOptions) -> Map<String, Value> {
+        let mut schema = Map::new();
+        let types = self.type_names();
+
+        match types.len() {
+            0 => {}
+            1 => {
+                schema.insert("type".into(), json!(types[0]));
+            }
+            _ => {
+                schema.insert("type".into(), json!(types));
+            }
+        }
+
+        if self.is_enum(options) {
+            let mut values: Vec<Value> = self.strings.iter().map(|s| json!(s)).collect();
+            if self.null {
+                values.push(Value::Null);
+            }
+            schema.insert("enum".into(), Value::Array(values));
+        }
+
+        if self.object_count > 0 {
+            let mut properties = Map::new();
+            let mut required = vec![];
+
+            for (key, (count, shape)) in &self.properties {
+                properties.insert(key.to_string(), Value::Object(shape.to_schema(options)));
+                if *count == self.object_count {
+                    required.push(json!(key));
+                }
+            }
+
+            schema.insert("properties".into(), Value::Object(properties));
+            if !required.is_empty() {
+                schema.insert("required".into(), Value::Array(required));
+            }
+        }
+
+        if let Some(items) = &self.items {
+            schema.insert("items".into(), Value::Object(items.to_schema(options)));
+        }
+
+        schema
+    }
+
+    /// Only plain strings that repeat across the samples are considered enum candidates.
+    fn is_enum(&self, options: &InferOptions) -> bool {
+        let only_strings =
+            !self.boolean && !self.integer && !self.number && !self.array && self.object_count == 0;
+
+        only_strings
+            && self.string_count > self.strings.len()
+            && !self.strings.is_empty()
+            && self.strings.len() <= options.enum_threshold
+    }
+}
+
+/// Infers a draft-07 JSON Schema that every sample validates against.
+pub fn infer_schema(samples: &[Value], options: &InferOptions) -> Value {
+    let mut shape = Shape::default();
+
+    for sample in samples {
+        shape.add(sample);
+    }
+
+    let mut schema = Map::new();
+    schema.insert("$schema".into(), json!(SCHEMA_DRAFT_07));
+    schema.extend(shape.to_schema(options));
+
+    Value::Object(schema)
+}
@@ -1,75 +1,708 @@
 use actix_files::{Files, NamedFile};
-use actix_web::http::StatusCode;
-use actix_web::{guard, middleware, rt, web, App, HttpResponse, HttpServer, Result};
-use log::info;
-use std::path::Path;
+use actix_service::ServiceFactory;
+use actix_web::dev::{Body, Service, ServiceRequest, ServiceResponse};
+use actix_web::http::{header, HeaderMap, Method, StatusCode};
+use actix_web::{
+    guard, middleware, rt, web, App, Error, HttpRequest, HttpResponse, HttpServer, Result,
+};
+use anyhow::anyhow;
+use futures::future::{ok, FutureExt, LocalBoxFuture};
+use log::{info, warn};
+use std::fs;
+use std::io;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::str::FromStr;
+
+pub mod access;
+pub mod access_log;
+pub mod assets;
+pub mod headers;
+pub mod i18n;
+pub mod listing;
+pub mod metrics;
+pub mod mock;
+pub mod network;
+pub mod proxy;
+pub mod recording;
+pub mod reload;
+pub mod ssl;
+pub mod templates;
 
 struct AppState {
-    root_dir: String,
+    mounts: Vec<Mount>,
+    templates: Option<templates::Templates>,
+    list: bool,
+}
+
+/// Directory served under the path prefix
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mount {
+    /// Path prefix without the trailing slash, `/` for the root
+    pub prefix: String,
+    pub root_dir: PathBuf,
+    /// Page served for the directories and used by the fallback, `index.html` by default
+    pub index_file: String,
+    pub fallback: Fallback,
+}
+
+impl Mount {
+    pub fn new(prefix: &str, root_dir: impl Into<PathBuf>) -> Self {
+        let prefix = format!("/{}", prefix.trim_matches('/'));
+        Mount {
+            prefix,
+            root_dir: root_dir.into(),
+            index_file: String::from("index.html"),
+            fallback: Fallback::default(),
+        }
+    }
+
+    /// Parses the `/prefix=dir[,option...]` mount, the options are `spa`, `strict`,
+    /// `not-found=FILE` and `index=FILE`. Uses the fallback unless the options override it.
+    pub fn parse(text: &str, fallback: &Fallback) -> anyhow::Result<Self> {
+        let mut parts = text.split(',');
+        let (prefix, dir) = parts
+            .next()
+            .and_then(|mount| mount.split_once('='))
+            .filter(|(prefix, dir)| prefix.starts_with('/') && !dir.is_empty())
+            .ok_or_else(|| anyhow!("Invalid mount `{}`, expected `/prefix=dir`", text))?;
+
+        let mut mount = Mount::new(prefix, dir);
+        mount.fallback = fallback.clone();
+
+        for option in parts {
+            match option.split_once('=') {
+                None if option == "spa" => mount.fallback = Fallback::Spa,
+                None if option == "strict" => mount.fallback = Fallback::Strict,
+                Some(("not-found", page)) => {
+                    mount.fallback = Fallback::NotFound(PathBuf::from(page))
+                }
+                Some(("index", file)) => mount.index_file = file.to_string(),
+                _ => return Err(anyhow!("Unknown mount option `{}`", option)),
+            }
+        }
+
+        Ok(mount)
+    }
+
+    /// Returns the request path relative to the prefix, `None` for the paths outside of the mount.
+    pub fn relative_path<'a>(&self, path: &'a str) -> Option<&'a str> {
+        if self.prefix == "/" {
+            return Some(path);
+        }
+
+        match path.strip_prefix(self.prefix.as_str()) {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => Some(rest),
+            _ => None,
+        }
+    }
+
+    /// Resolves the request path to a file of the mount.
+    pub fn resolve_file(&self, path: &str) -> Option<PathBuf> {
+        assets::resolve_file(&self.root_dir, &self.index_file, self.relative_path(path)?)
+    }
+}
+
+/// Finds the mount with the longest prefix matching the path.
+pub fn find_mount<'a>(mounts: &'a [Mount], path: &str) -> Option<&'a Mount> {
+    mounts
+        .iter()
+        .filter(|mount| mount.relative_path(path).is_some())
+        .max_by_key(|mount| mount.prefix.len())
+}
+
+/// Response for the requests that do not match any file
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fallback {
+    /// `index.html` with the `404` status
+    Index,
+    /// `index.html` with the `200` status for the HTML navigation requests (history API)
+    Spa,
+    /// Custom page with the `404` status, relative paths are resolved against the root directory
+    NotFound(PathBuf),
+    /// Plain `404` response
+    Strict,
+}
+
+impl Default for Fallback {
+    fn default() -> Self {
+        Fallback::Index
+    }
+}
+
+impl Fallback {
+    /// Resolves the file and the status code to respond with, `None` for a plain `404` response.
+    /// Missing assets (paths with an extension) never fall back to a page.
+    pub fn resolve(
+        &self,
+        root_dir: &Path,
+        path: &str,
+        accepts_html: bool,
+    ) -> Option<(PathBuf, StatusCode)> {
+        self.resolve_with_index(root_dir, "index.html", path, accepts_html)
+    }
+
+    /// Resolves the fallback with a custom index file.
+    pub fn resolve_with_index(
+        &self,
+        root_dir: &Path,
+        index_file: &str,
+        path: &str,
+        accepts_html: bool,
+    ) -> Option<(PathBuf, StatusCode)> {
+        let file_name = path.rsplit('/').next().unwrap_or_default();
+        if file_name.contains('.') {
+            return None;
+        }
+
+        match self {
+            Fallback::Index => Some((root_dir.join(index_file), StatusCode::NOT_FOUND)),
+            Fallback::Spa if accepts_html => Some((root_dir.join(index_file), StatusCode::OK)),
+            Fallback::Spa => None,
+            Fallback::NotFound(page) => Some((root_dir.join(page), StatusCode::NOT_FOUND)),
+            Fallback::Strict => None,
+        }
+    }
+}
+
+fn accepts_html(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.contains("text/html") || value.contains("application/xhtml+xml"))
+        .unwrap_or(false)
 }
 
 /// 404 handler
-async fn p404(data: web::Data<AppState>) -> Result<NamedFile> {
-    // let file_path = Path::new(&data.root_dir).join("404.html");
-    let file_path = Path::new(&data.root_dir).join("index.html");
-    Ok(NamedFile::open(file_path)?.set_status_code(StatusCode::NOT_FOUND))
+async fn p404(req: HttpRequest, data: web::Data<AppState>) -> Result<HttpResponse> {
+    let mount = match find_mount(&data.mounts, req.path()) {
+        Some(mount) => mount,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let path = mount.relative_path(req.path()).unwrap_or_default();
+
+    if data.list {
+        if let Some(dir) = listing::resolve_dir(&mount.root_dir, path) {
+            let has_parent = !path.trim_matches('/').is_empty();
+            return Ok(listing::respond(&req, &dir, has_parent));
+        }
+    }
+
+    match mount.fallback.resolve_with_index(
+        &mount.root_dir,
+        &mount.index_file,
+        path,
+        accepts_html(req.headers()),
+    ) {
+        Some((file_path, status)) => match &data.templates {
+            Some(templates) if templates.matches(&mount.root_dir, &file_path) => {
+                Ok(templates.respond(&file_path, status))
+            }
+            _ => NamedFile::open(file_path)?
+                .set_status_code(status)
+                .into_response(&req),
+        },
+        None => Ok(HttpResponse::NotFound().finish()),
+    }
 }
 
 #[derive(Clone)]
 pub struct ServerOptions {
     pub host: String,
-    pub port: String,
-    pub root_dir: String,
+    pub port: Port,
+    /// Served directories, the first one is opened in the browser
+    pub mounts: Vec<Mount>,
+    /// Locales of the localized build, the root is redirected to the preferred one
+    pub locales: Option<i18n::LocaleOptions>,
     pub open: bool,
+    /// Basic auth and the IP allow-list
+    pub access: access::AccessOptions,
+    /// Serve over HTTPS
+    pub ssl: Option<ssl::SslOptions>,
+    /// Reverse proxy configuration
+    pub proxy: Vec<proxy::ProxyConfig>,
+    /// Directory to save the proxied requests to
+    pub record: Option<PathBuf>,
+    /// Answer the proxied requests from the recordings
+    pub replay: Option<recording::ReplayOptions>,
+    /// Response headers, CSP and CORS
+    pub headers: headers::HeadersConfig,
+    /// Reload the pages when the files change
+    pub watch: bool,
+    /// List the directories without the index page
+    pub list: bool,
+    /// Serve the health check and the metrics endpoints
+    pub metrics: bool,
+    /// Access log file
+    pub access_log: Option<access_log::AccessLogOptions>,
+    /// Add the `X-Request-Id` header to the requests and the responses
+    pub request_id: bool,
+    /// Directory with the mock API fixtures
+    pub mocks: Option<PathBuf>,
+    /// Path prefix of the mocked requests
+    pub mocks_prefix: String,
+    /// Runtime configuration files rendered from the environment variables
+    pub templates: Option<templates::TemplateOptions>,
+    /// Simulated latency, throughput and failures
+    pub network: network::NetworkConfig,
+    /// File to write the server url to once it is listening
+    pub ready_file: Option<PathBuf>,
+}
+
+/// Seconds to wait for the in-flight requests on shutdown
+const SHUTDOWN_TIMEOUT: u64 = 5;
+
+/// Number of the following ports tried when the port is in use
+const PORT_ATTEMPTS: u16 = 10;
+
+/// Port to listen on
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Port {
+    /// The port, or one of the following ones if it is in use
+    Fixed(u16),
+    /// Any free port
+    Auto,
+}
+
+impl FromStr for Port {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "auto" => Ok(Port::Auto),
+            _ => value
+                .parse()
+                .map(Port::Fixed)
+                .map_err(|_| format!("`{}` is not a port number or `auto`", value)),
+        }
+    }
+}
+
+/// Waits for SIGINT (Ctrl+C) or SIGTERM.
+async fn wait_for_shutdown() {
+    #[cfg(unix)]
+    {
+        use rt::signal::unix::{signal, SignalKind};
+
+        if let Ok(mut terminate) = signal(SignalKind::terminate()) {
+            futures::select! {
+                _ = rt::signal::ctrl_c().fuse() => {},
+                _ = terminate.recv().fuse() => {},
+            }
+            return;
+        }
+    }
+
+    let _ = rt::signal::ctrl_c().await;
+}
+
+/// Binds the listener, falling through to the next ports when the port is in use.
+pub fn bind_listener(host: &str, port: Port) -> io::Result<TcpListener> {
+    let port = match port {
+        Port::Auto => return TcpListener::bind((host, 0)),
+        Port::Fixed(port) => port,
+    };
+
+    let mut result = TcpListener::bind((host, port));
+    for next in port.saturating_add(1)..=port.saturating_add(PORT_ATTEMPTS) {
+        match &result {
+            Err(err) if err.kind() == io::ErrorKind::AddrInUse => {
+                warn!("Port {} is in use, trying {}", next - 1, next);
+                result = TcpListener::bind((host, next));
+            }
+            _ => break,
+        }
+    }
+
+    result
 }
 
 impl ServerOptions {
-    pub fn get_addr(&self) -> String {
-        format!("{}:{}", self.host, self.port)
+    /// Returns the url of the first mount for the bound port.
+    pub fn get_url(&self, port: u16) -> String {
+        let scheme = if self.ssl.is_some() { "https" } else { "http" };
+        let path = match self.mounts.first() {
+            Some(mount) if mount.prefix != "/" => format!("{}/", mount.prefix),
+            _ => String::new(),
+        };
+        format!("{}://{}:{}{}", scheme, self.host, port, path)
+    }
+}
+
+/// State shared by the server workers
+#[derive(Clone)]
+pub struct ServerState {
+    options: ServerOptions,
+    live_reload: reload::LiveReload,
+    recorder: Option<recording::Recorder>,
+    replay: Option<recording::Replay>,
+    access: access::Access,
+    metrics: metrics::Metrics,
+    access_log: Option<access_log::AccessLog>,
+    mock_api: Option<mock::MockApi>,
+}
+
+impl ServerState {
+    /// Loads the recordings and the mocks, starts watching the files.
+    pub fn new(options: ServerOptions) -> io::Result<Self> {
+        let live_reload = reload::LiveReload::default();
+        if options.watch {
+            for mount in &options.mounts {
+                live_reload
+                    .watch(&mount.root_dir)
+                    .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
+            }
+        }
+
+        let recorder = match &options.record {
+            Some(dir) => {
+                info!("Recording the proxied requests to {}", dir.display());
+                Some(recording::Recorder::new(dir)?)
+            }
+            None => None,
+        };
+        let replay = match &options.replay {
+            Some(replay_options) => Some(
+                recording::Replay::load(replay_options)
+                    .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?,
+            ),
+            None => None,
+        };
+
+        let access = access::Access::new(&options.access)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
+
+        proxy::check_proxy(&options.proxy)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
+
+        let metrics = metrics::Metrics::default();
+        let access_log = match &options.access_log {
+            Some(log_options) => {
+                info!("Writing the access log to {}", log_options.path.display());
+                Some(access_log::AccessLog::new(log_options)?)
+            }
+            None => None,
+        };
+
+        let mock_api = match &options.mocks {
+            Some(dir) => {
+                info!("Mocking {} with {}", options.mocks_prefix, dir.display());
+                let mock_api = mock::MockApi::new(dir, &options.mocks_prefix);
+                mock_api
+                    .watch()
+                    .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
+                Some(mock_api)
+            }
+            None => None,
+        };
+
+        Ok(ServerState {
+            options,
+            live_reload,
+            recorder,
+            replay,
+            access,
+            metrics,
+            access_log,
+            mock_api,
+        })
+    }
+}
+
+/// Creates the application of a server worker.
+pub fn create_app(
+    state: &ServerState,
+) -> App<
+    impl ServiceFactory<
+        Config = (),
+        Request = ServiceRequest,
+        Response = ServiceResponse,
+        Error = Error,
+        InitError = (),
+    >,
+    Body,
+> {
+    let options = &state.options;
+    let mut mounts = options.mounts.clone();
+    // the more specific prefixes are registered first
+    mounts.sort_by_key(|mount| std::cmp::Reverse(mount.prefix.len()));
+    for mount in &mounts {
+        info!("Serving {} at {}", mount.root_dir.display(), mount.prefix);
     }
-    pub fn get_url(&self) -> String {
-        format!("http://{}:{}", self.host, self.port)
+
+    let mut proxy = proxy::Proxy::new(&options.proxy).unwrap();
+    if let Some(recorder) = &state.recorder {
+        proxy = proxy.record(recorder.clone());
     }
+    let proxy = Rc::new(proxy);
+    let replay = state.replay.clone();
+    let watch = options.watch;
+    let headers = Rc::new(headers::Headers::new(&options.headers, options.ssl.is_some()).unwrap());
+    let csp_report = headers.csp_report();
+    let static_mounts = Rc::new(mounts.clone());
+    let mock_api = state.mock_api.clone();
+    let access = state.access.clone();
+    let access_headers = headers.clone();
+    let templates = options
+        .templates
+        .as_ref()
+        .map(|template_options| templates::Templates::new(template_options).unwrap());
+    let static_templates = templates.clone();
+    let locales = options.locales.clone();
+    let locale_mounts = static_mounts.clone();
+    let metrics = options.metrics.then(|| state.metrics.clone());
+    let access_log = state.access_log.clone();
+    let request_id = options.request_id;
+
+    App::new()
+        .data(AppState {
+            mounts: mounts.clone(),
+            templates,
+            list: options.list,
+        })
+        .data(state.live_reload.clone())
+        .data(metrics.clone().unwrap_or_default())
+        .wrap_fn(
+            move |req, srv| -> LocalBoxFuture<Result<ServiceResponse, Error>> {
+                let mount = find_mount(&static_mounts, req.path());
+                let file = mount.and_then(|mount| mount.resolve_file(req.path()));
+
+                if let (Some(templates), Some(mount), Some(file)) =
+                    (&static_templates, mount, &file)
+                {
+                    let is_read = req.method() == Method::GET || req.method() == Method::HEAD;
+                    if is_read && templates.matches(&mount.root_dir, file) {
+                        let response = templates.respond(file, StatusCode::OK);
+                        return Box::pin(ok(req.into_response(response)));
+                    }
+                }
+
+                // the reload client can not be injected into the compressed pages
+                let fut = match &file {
+                    Some(file) => match assets::serve_precompressed(file, req, watch) {
+                        Ok(res) => Box::pin(ok(res)),
+                        Err(req) => srv.call(req),
+                    },
+                    None => srv.call(req),
+                };
+                Box::pin(async move {
+                    let mut res = fut.await?;
+                    assets::set_cache_headers(file.as_deref(), &mut res);
+                    Ok(res)
+                })
+            },
+        )
+        .wrap_fn(
+            move |req, srv| -> LocalBoxFuture<Result<ServiceResponse, Error>> {
+                if let Some(locales) = &locales {
+                    let is_navigation = accepts_html(req.headers())
+                        && find_mount(&locale_mounts, req.path()).is_none();
+                    if let Some(response) = locales.redirect(req.head(), is_navigation) {
+                        return Box::pin(ok(req.into_response(response)));
+                    }
+                }
+                Box::pin(srv.call(req))
+            },
+        )
+        .wrap_fn(
+            move |req, srv| -> LocalBoxFuture<Result<ServiceResponse, Error>> {
+                let fut = srv.call(req);
+                Box::pin(async move {
+                    let res = fut.await?;
+                    if watch {
+                        reload::inject_response(res).await
+                    } else {
+                        Ok(res)
+                    }
+                })
+            },
+        )
+        .wrap_fn(
+            move |req, srv| -> LocalBoxFuture<Result<ServiceResponse, Error>> {
+                match &mock_api {
+                    Some(mock_api) if mock_api.matches(req.path()) => {
+                        let mock_api = mock_api.clone();
+                        Box::pin(async move { mock_api.respond(req).await })
+                    }
+                    _ => Box::pin(srv.call(req)),
+                }
+            },
+        )
+        .wrap_fn(
+            move |req, srv| -> LocalBoxFuture<Result<ServiceResponse, Error>> {
+                let index = proxy.find(req.path());
+                match (&replay, index) {
+                    // the proxied requests never reach the backend on replay
+                    (Some(replay), _) if index.is_some() || replay.has_path(req.path()) => {
+                        let replay = replay.clone();
+                        Box::pin(async move { replay.respond(req).await })
+                    }
+                    (_, Some(index)) => {
+                        let proxy = proxy.clone();
+                        Box::pin(async move { proxy.forward(index, req).await })
+                    }
+                    _ => Box::pin(srv.call(req)),
+                }
+            },
+        )
+        // the mocked and the proxied responses get the headers too
+        .wrap_fn(
+            move |req, srv| -> LocalBoxFuture<Result<ServiceResponse, Error>> {
+                let headers = headers.clone();
+                if let Some(response) = headers.preflight(req.head()) {
+                    return Box::pin(ok(req.into_response(response)));
+                }
+
+                let fut = srv.call(req);
+                Box::pin(async move {
+                    let mut res = fut.await?;
+                    let response_headers = headers.response_headers(res.request().head());
+                    for (name, value) in response_headers {
+                        if name == header::VARY {
+                            res.headers_mut().append(name, value);
+                        } else {
+                            res.headers_mut().insert(name, value);
+                        }
+                    }
+                    Ok(res)
+                })
+            },
+        )
+        .wrap_fn(
+            move |req, srv| -> LocalBoxFuture<Result<ServiceResponse, Error>> {
+                let peer_addr = req.peer_addr().map(|addr| addr.ip());
+                let preflight = access_headers.preflight(req.head());
+                match access.check(req.head(), peer_addr, preflight) {
+                    Some(response) => Box::pin(ok(req.into_response(response))),
+                    None => Box::pin(srv.call(req)),
+                }
+            },
+        )
+        .wrap(middleware::Compress::default())
+        // after compression, the throughput limit, the metrics and the access log
+        // apply to the transferred bytes
+        .wrap(network::Simulation::new(
+            network::Network::new(&options.network).unwrap(),
+        ))
+        .wrap_fn(
+            move |mut req, srv| -> LocalBoxFuture<Result<ServiceResponse, Error>> {
+                let request_id = request_id.then(|| access_log::set_request_id(&mut req));
+                let log = access_log.as_ref().map(|log| {
+                    (
+                        log.clone(),
+                        access_log::Entry::new(&req, request_id.clone()),
+                    )
+                });
+                let counted = match &metrics {
+                    Some(metrics) if metrics::Metrics::is_counted(req.path()) => Some((
+                        metrics.clone(),
+                        req.method().clone(),
+                        req.path().to_string(),
+                    )),
+                    _ => None,
+                };
+                let fut: LocalBoxFuture<Result<ServiceResponse, Error>> = Box::pin(srv.call(req));
+                // the simulated latency and the transferred bytes are measured too
+                let fut = match counted {
+                    Some((metrics, method, path)) => {
+                        Box::pin(metrics::measure(fut, metrics, method, path))
+                    }
+                    None => fut,
+                };
+                match (log, request_id) {
+                    (None, None) => fut,
+                    (log, request_id) => Box::pin(access_log::log(fut, log, request_id)),
+                }
+            },
+        )
+        .configure(|cfg| {
+            if watch {
+                cfg.route(reload::SOCKET_PATH, web::get().to(reload::socket));
+            }
+            if options.metrics {
+                cfg.route(metrics::HEALTH_PATH, web::get().to(metrics::health));
+                cfg.route(metrics::METRICS_PATH, web::get().to(metrics::metrics));
+            }
+            if csp_report {
+                cfg.route(
+                    headers::CSP_REPORT_PATH,
+                    web::post().to(headers::csp_report),
+                );
+            }
+        })
+        .configure(|cfg| {
+            for mount in &mounts {
+                cfg.service(
+                    Files::new(&mount.prefix, &mount.root_dir)
+                        .index_file(&mount.index_file)
+                        .default_handler(web::get().to(p404)),
+                );
+            }
+        })
+        .default_service(
+            // 404 for GET request
+            web::resource("")
+                .route(web::get().to(p404))
+                // all requests that are not `GET`
+                .route(
+                    web::route()
+                        .guard(guard::Not(guard::Get()))
+                        .to(HttpResponse::MethodNotAllowed),
+                ),
+        )
 }
 
 pub fn run_server(options: ServerOptions) -> std::io::Result<()> {
     let mut sys = rt::System::new("server");
 
-    let addr = options.get_addr();
-    let url = options.get_url();
+    let listener = bind_listener(&options.host, options.port)?;
+    let url = options.get_url(listener.local_addr()?.port());
     let open = options.open;
+    let ready_file = options.ready_file.clone();
+    let acceptor = match &options.ssl {
+        Some(ssl_options) => Some(ssl::create_acceptor(ssl_options, &options.host)?),
+        None => None,
+    };
+
+    let state = ServerState::new(options)?;
 
     let srv = HttpServer::new(move || {
-        let root_dir = &options.root_dir;
-        info!("Serving {}", root_dir);
-
-        App::new()
-            .data(AppState {
-                root_dir: String::from(root_dir),
-            })
-            .wrap(middleware::Compress::default())
-            // enable logger - always register actix-web Logger middleware last
-            .wrap(middleware::Logger::default())
-            .service(Files::new("/", &root_dir).index_file("index.html"))
-            .default_service(
-                // 404 for GET request
-                web::resource("")
-                    .route(web::get().to(p404))
-                    // all requests that are not `GET`
-                    .route(
-                        web::route()
-                            .guard(guard::Not(guard::Get()))
-                            .to(HttpResponse::MethodNotAllowed),
-                    ),
-            )
+        // enable logger - always register actix-web Logger middleware last
+        create_app(&state).wrap(middleware::Logger::default())
     })
     .workers(1)
-    .bind(addr)?
+    .disable_signals()
+    .shutdown_timeout(SHUTDOWN_TIMEOUT);
+
+    let srv = match acceptor {
+        Some(acceptor) => srv.listen_openssl(listener, acceptor)?,
+        None => srv.listen(listener)?,
+    }
     .run();
 
+    info!("Listening on {}", url);
+    if let Some(path) = &ready_file {
+        fs::write(path, &url)?;
+    }
+
     if open {
         webbrowser::open(url.as_str())?;
     }
 
-    sys.block_on(srv)
+    let result = sys.block_on(async move {
+        let server = srv.clone();
+        rt::spawn(async move {
+            wait_for_shutdown().await;
+            info!("Stopping, waiting for the in-flight requests");
+            server.stop(true).await;
+        });
+        srv.await
+    });
+
+    if let Some(path) = &ready_file {
+        let _ = fs::remove_file(path);
+    }
+
+    result
 }
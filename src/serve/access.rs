@@ -0,0 +1,224 @@
+//! Access control: HTTP basic auth and the IP allow-list
+
+use actix_web::dev::RequestHead;
+use actix_web::http::header;
+use actix_web::HttpResponse;
+use anyhow::{anyhow, Result};
+use ipnet::IpNet;
+use log::{debug, warn};
+use sha1::{Digest, Sha1};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+const REALM: &str = "rung";
+
+/// Password of the user
+#[derive(Clone, Debug, PartialEq)]
+enum Password {
+    Plain(String),
+    /// `$2y$`, `$2b$` or `$2a$` hash of the htpasswd files
+    Bcrypt(String),
+}
+
+/// Access control options
+#[derive(Clone, Debug, Default)]
+pub struct AccessOptions {
+    /// `user:password` pairs
+    pub users: Vec<String>,
+    /// htpasswd file with the bcrypt passwords
+    pub htpasswd: Option<PathBuf>,
+    /// Allowed client addresses and networks, i.e. `192.168.1.0/24`
+    pub allow: Vec<String>,
+}
+
+/// Parses the allowed address or network, a single address allows only itself.
+pub fn parse_network(text: &str) -> Result<IpNet> {
+    text.parse::<IpNet>()
+        .or_else(|_| text.parse::<IpAddr>().map(IpNet::from))
+        .map_err(|_| anyhow!("Invalid address or network `{}`", text))
+}
+
+/// Parses the htpasswd file, only the bcrypt passwords are supported.
+pub fn parse_htpasswd(text: &str) -> Result<Vec<(String, String)>> {
+    let mut users = vec![];
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (user, hash) = line
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Invalid line {}, expected `user:hash`", index + 1))?;
+        if !["$2y$", "$2b$", "$2a$"]
+            .iter()
+            .any(|prefix| hash.starts_with(prefix))
+        {
+            return Err(anyhow!(
+                "Unsupported password hash of `{}`, only bcrypt (`htpasswd -B`) is supported",
+                user
+            ));
+        }
+        users.push((user.to_string(), hash.to_string()));
+    }
+    Ok(users)
+}
+
+/// Decodes the `user:password` credentials of the basic `Authorization` header value.
+pub fn basic_credentials(authorization: &str) -> Option<String> {
+    let encoded = authorization.strip_prefix("Basic ")?;
+    let decoded = base64::decode(encoded.trim()).ok()?;
+    String::from_utf8(decoded).ok()
+}
+
+/// Compares the strings in constant time.
+fn secure_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Compiled access control
+#[derive(Clone)]
+pub struct Access {
+    users: HashMap<String, Password>,
+    allow: Vec<IpNet>,
+    /// Hashes of the verified `Authorization` headers, bcrypt is too slow for every request
+    verified: Arc<Mutex<HashSet<String>>>,
+}
+
+impl Access {
+    pub fn new(options: &AccessOptions) -> Result<Self> {
+        let mut users = HashMap::new();
+
+        if let Some(path) = &options.htpasswd {
+            let text = fs::read_to_string(path)
+                .map_err(|err| anyhow!("Error reading `{}`. {}", path.display(), err))?;
+            let entries = parse_htpasswd(&text)
+                .map_err(|err| anyhow!("Error parsing `{}`. {}", path.display(), err))?;
+            for (user, hash) in entries {
+                users.insert(user, Password::Bcrypt(hash));
+            }
+        }
+
+        for user in &options.users {
+            match user.split_once(':') {
+                Some((name, password)) if !name.is_empty() => {
+                    users.insert(name.to_string(), Password::Plain(password.to_string()));
+                }
+                _ => return Err(anyhow!("Invalid user `{}`, expected `user:password`", user)),
+            }
+        }
+
+        let allow = options
+            .allow
+            .iter()
+            .map(|network| parse_network(network))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Access {
+            users,
+            allow,
+            verified: Arc::new(Mutex::new(HashSet::new())),
+        })
+    }
+
+    /// Checks whether the client address is allowed, any address is allowed without the list.
+    pub fn allows(&self, addr: IpAddr) -> bool {
+        // IPv4 clients of the dual-stack sockets
+        let addr = match addr {
+            IpAddr::V6(v6) => match v6.octets() {
+                [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, a, b, c, d] => {
+                    IpAddr::V4(Ipv4Addr::new(a, b, c, d))
+                }
+                _ => addr,
+            },
+            IpAddr::V4(_) => addr,
+        };
+        self.allow.is_empty() || self.allow.iter().any(|network| network.contains(&addr))
+    }
+
+    /// Checks the `Authorization` header value, any request is authorized without the users.
+    pub fn authorizes(&self, authorization: Option<&str>) -> bool {
+        if self.users.is_empty() {
+            return true;
+        }
+
+        let credentials = match authorization.and_then(basic_credentials) {
+            Some(credentials) => credentials,
+            None => return false,
+        };
+        let key = format!("{:x}", Sha1::digest(credentials.as_bytes()));
+        if self.verified.lock().unwrap().contains(&key) {
+            return true;
+        }
+
+        let (user, password) = match credentials.split_once(':') {
+            Some(pair) => pair,
+            None => return false,
+        };
+        let verified = match self.users.get(user) {
+            Some(Password::Plain(expected)) => secure_eq(expected, password),
+            Some(Password::Bcrypt(hash)) => bcrypt::verify(password, hash).unwrap_or(false),
+            None => false,
+        };
+
+        if verified {
+            self.verified.lock().unwrap().insert(key);
+        } else {
+            debug!("Invalid credentials of `{}`", user);
+        }
+        verified
+    }
+
+    /// Rejects the request of the disallowed address or without the valid credentials.
+    /// The CORS preflight requests are sent without the credentials, so the `preflight` response
+    /// answered locally is returned for them instead, they never reach the proxied backends.
+    pub fn check(
+        &self,
+        req: &RequestHead,
+        peer_addr: Option<IpAddr>,
+        preflight: Option<HttpResponse>,
+    ) -> Option<HttpResponse> {
+        match peer_addr {
+            Some(addr) if self.allows(addr) => {}
+            None if self.allow.is_empty() => {}
+            Some(addr) => {
+                warn!("Rejected {} {} from {}", req.method, req.uri, addr);
+                return Some(HttpResponse::Forbidden().finish());
+            }
+            None => {
+                warn!(
+                    "Rejected {} {} from an unknown address",
+                    req.method, req.uri
+                );
+                return Some(HttpResponse::Forbidden().finish());
+            }
+        }
+
+        let authorization = req
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok());
+        if self.authorizes(authorization) {
+            return None;
+        }
+        if preflight.is_some() {
+            return preflight;
+        }
+
+        Some(
+            HttpResponse::Unauthorized()
+                .header(
+                    header::WWW_AUTHENTICATE,
+                    format!("Basic realm=\"{}\", charset=\"UTF-8\"", REALM),
+                )
+                .finish(),
+        )
+    }
+}
@@ -0,0 +1,471 @@
+//! Access log file in the JSON or the combined log format, with the size and time based rotation
+
+use actix_web::dev::{Body, BodySize, MessageBody, ResponseBody, ServiceRequest, ServiceResponse};
+use actix_web::http::{header, HeaderMap, HeaderName, HeaderValue};
+use actix_web::web::Bytes;
+use actix_web::Error;
+use anyhow::{anyhow, Result};
+use futures::future::LocalBoxFuture;
+use log::warn;
+use rand::Rng;
+use serde_json::{json, Value};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::access::basic_credentials;
+
+/// Request id header, reused when the client sends one
+pub const X_REQUEST_ID: &str = "x-request-id";
+
+/// Number of the rotated files kept, unless configured
+pub const DEFAULT_KEEP: usize = 7;
+
+/// Access log line format
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LogFormat {
+    /// JSON object per line
+    Json,
+    /// Apache/nginx combined log format
+    Combined,
+}
+
+impl Default for LogFormat {
+    fn default() -> Self {
+        LogFormat::Json
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "json" => Ok(LogFormat::Json),
+            "combined" => Ok(LogFormat::Combined),
+            _ => Err(format!(
+                "Invalid log format `{}`, expected `json` or `combined`",
+                value
+            )),
+        }
+    }
+}
+
+/// Time based rotation period, in UTC
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Period {
+    Hourly,
+    Daily,
+}
+
+impl Period {
+    fn seconds(&self) -> u64 {
+        match self {
+            Period::Hourly => 3600,
+            Period::Daily => 86400,
+        }
+    }
+
+    /// Index of the period the time belongs to
+    fn index(&self, time: SystemTime) -> u64 {
+        let seconds = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        seconds / self.seconds()
+    }
+}
+
+/// Rotation of the log file, by size, by time or both
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Rotation {
+    pub max_size: Option<u64>,
+    pub period: Option<Period>,
+}
+
+/// Parses the size with the binary unit, i.e. `500KB` or `10MB`.
+pub fn parse_size(text: &str) -> Result<u64> {
+    let text = text.trim();
+    let digits = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(digits);
+    let multiplier = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1024,
+        "M" | "MB" => 1024 * 1024,
+        "G" | "GB" => 1024 * 1024 * 1024,
+        _ => 0,
+    };
+    match number.parse::<u64>() {
+        Ok(number) if number > 0 && multiplier > 0 => Ok(number * multiplier),
+        _ => Err(anyhow!("Invalid size `{}`", text)),
+    }
+}
+
+/// Parses the rotation triggers, `hourly`, `daily` or a size.
+pub fn parse_rotation(values: &[&str]) -> Result<Rotation> {
+    let mut rotation = Rotation::default();
+    for value in values {
+        match *value {
+            "hourly" => rotation.period = Some(Period::Hourly),
+            "daily" => rotation.period = Some(Period::Daily),
+            size => rotation.max_size = Some(parse_size(size)?),
+        }
+    }
+    Ok(rotation)
+}
+
+/// Access log options
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccessLogOptions {
+    pub path: PathBuf,
+    pub format: LogFormat,
+    pub rotation: Rotation,
+    /// Number of the rotated files kept, `access.log.1` is the newest one
+    pub keep: usize,
+    /// Log the request duration
+    pub timing: bool,
+}
+
+/// Logged request
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub time: SystemTime,
+    pub remote: Option<String>,
+    /// User of the basic authentication
+    pub user: Option<String>,
+    pub method: String,
+    /// Path and query
+    pub uri: String,
+    pub protocol: String,
+    pub status: u16,
+    /// Transferred bytes of the response body
+    pub bytes: u64,
+    pub referer: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    /// Time to the end of the response body
+    pub duration: Option<Duration>,
+}
+
+fn header_value(headers: &HeaderMap, name: header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from)
+}
+
+/// Formats the time as in the combined log format, i.e. `10/Oct/2000:13:55:36 +0000`.
+fn clf_time(time: SystemTime) -> String {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    // `2000-10-10T13:55:36Z`
+    let text = humantime::format_rfc3339_seconds(time).to_string();
+    let month = text[5..7].parse::<usize>().unwrap_or(1);
+    format!(
+        "{}/{}/{}:{} +0000",
+        &text[8..10],
+        MONTHS[month - 1],
+        &text[..4],
+        &text[11..19]
+    )
+}
+
+/// Quotes the field of the combined log format.
+fn quote(value: Option<&str>) -> String {
+    match value {
+        Some(value) => format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"")),
+        None => String::from("\"-\""),
+    }
+}
+
+impl Entry {
+    /// Creates the entry of the request, the status and the bytes are set once responded.
+    pub fn new(req: &ServiceRequest, request_id: Option<String>) -> Self {
+        let headers = req.headers();
+        let user = header_value(headers, header::AUTHORIZATION)
+            .and_then(|value| basic_credentials(&value))
+            .and_then(|credentials| {
+                credentials
+                    .split_once(':')
+                    .map(|(user, _)| user.to_string())
+            });
+
+        Entry {
+            time: SystemTime::now(),
+            remote: req.peer_addr().map(|addr| addr.ip().to_string()),
+            user,
+            method: req.method().to_string(),
+            uri: req
+                .uri()
+                .path_and_query()
+                .map(|uri| uri.to_string())
+                .unwrap_or_else(|| req.path().to_string()),
+            protocol: format!("{:?}", req.version()),
+            status: 0,
+            bytes: 0,
+            referer: header_value(headers, header::REFERER),
+            user_agent: header_value(headers, header::USER_AGENT),
+            request_id,
+            duration: None,
+        }
+    }
+
+    /// Formats the log line, without the line break.
+    pub fn format(&self, format: LogFormat) -> String {
+        match format {
+            LogFormat::Json => {
+                let mut line = json!({
+                    "time": humantime::format_rfc3339_millis(self.time).to_string(),
+                    "remote": self.remote,
+                    "user": self.user,
+                    "method": self.method,
+                    "uri": self.uri,
+                    "protocol": self.protocol,
+                    "status": self.status,
+                    "bytes": self.bytes,
+                    "referer": self.referer,
+                    "userAgent": self.user_agent,
+                });
+                if let Some(request_id) = &self.request_id {
+                    line["requestId"] = json!(request_id);
+                }
+                if let Some(duration) = self.duration {
+                    line["durationMs"] = Value::from(duration.as_micros() as f64 / 1000.0);
+                }
+                line.to_string()
+            }
+            LogFormat::Combined => {
+                let mut line = format!(
+                    "{} - {} [{}] {} {} {} {} {}",
+                    self.remote.as_deref().unwrap_or("-"),
+                    self.user.as_deref().unwrap_or("-"),
+                    clf_time(self.time),
+                    quote(Some(&format!(
+                        "{} {} {}",
+                        self.method, self.uri, self.protocol
+                    ))),
+                    self.status,
+                    self.bytes,
+                    quote(self.referer.as_deref()),
+                    quote(self.user_agent.as_deref()),
+                );
+                if let Some(request_id) = &self.request_id {
+                    line.push_str(&format!(" rid={}", request_id));
+                }
+                if let Some(duration) = self.duration {
+                    line.push_str(&format!(" rt={:.3}", duration.as_secs_f64()));
+                }
+                line
+            }
+        }
+    }
+
+    /// Writes the entry with the duration since the request.
+    fn finish(mut self, log: &AccessLog) {
+        if log.timing {
+            self.duration = Some(self.time.elapsed().unwrap_or_default());
+        }
+        log.write(&self);
+    }
+}
+
+/// Returns the request id sent by the client, or a new one.
+pub fn request_id(headers: &HeaderMap) -> String {
+    headers
+        .get(X_REQUEST_ID)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= 128)
+        .map(String::from)
+        .unwrap_or_else(|| format!("{:032x}", rand::thread_rng().gen::<u128>()))
+}
+
+struct Writer {
+    options: AccessLogOptions,
+    file: File,
+    size: u64,
+    /// Period index of the file, for the time based rotation
+    period: Option<u64>,
+}
+
+impl Writer {
+    fn open(options: &AccessLogOptions) -> io::Result<Self> {
+        if let Some(dir) = options
+            .path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+        {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&options.path)?;
+        let metadata = file.metadata()?;
+        // the file of the previous period is rotated on the first write
+        let modified = metadata.modified().unwrap_or_else(|_| SystemTime::now());
+        Ok(Writer {
+            options: options.clone(),
+            file,
+            size: metadata.len(),
+            period: options.rotation.period.map(|period| period.index(modified)),
+        })
+    }
+
+    fn rotated_path(path: &Path, index: usize) -> PathBuf {
+        let mut name = path.as_os_str().to_os_string();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let path = &self.options.path;
+        let keep = self.options.keep;
+        let _ = fs::remove_file(Self::rotated_path(path, keep.max(1)));
+        for index in (1..keep).rev() {
+            let from = Self::rotated_path(path, index);
+            if from.exists() {
+                fs::rename(&from, Self::rotated_path(path, index + 1))?;
+            }
+        }
+        if keep > 0 {
+            fs::rename(path, Self::rotated_path(path, 1))?;
+        } else {
+            fs::remove_file(path)?;
+        }
+
+        let options = self.options.clone();
+        *self = Writer::open(&options)?;
+        Ok(())
+    }
+
+    fn write(&mut self, line: &str, time: SystemTime) -> io::Result<()> {
+        let rotation = &self.options.rotation;
+        let size = line.len() as u64 + 1;
+        let is_full = rotation.max_size.map_or(false, |max_size| {
+            self.size > 0 && self.size + size > max_size
+        });
+        let is_expired = match (rotation.period, self.period) {
+            (Some(period), Some(index)) => period.index(time) != index,
+            _ => false,
+        };
+        if is_full || is_expired {
+            self.rotate()?;
+            if let Some(period) = self.options.rotation.period {
+                self.period = Some(period.index(time));
+            }
+        }
+
+        writeln!(self.file, "{}", line)?;
+        self.size += size;
+        Ok(())
+    }
+}
+
+/// Access log file shared by the workers
+#[derive(Clone)]
+pub struct AccessLog {
+    format: LogFormat,
+    timing: bool,
+    writer: Arc<Mutex<Writer>>,
+}
+
+impl AccessLog {
+    pub fn new(options: &AccessLogOptions) -> io::Result<Self> {
+        Ok(AccessLog {
+            format: options.format,
+            timing: options.timing,
+            writer: Arc::new(Mutex::new(Writer::open(options)?)),
+        })
+    }
+
+    /// Writes the entry, rotating the file first when needed.
+    pub fn write(&self, entry: &Entry) {
+        let line = entry.format(self.format);
+        if let Err(err) = self.writer.lock().unwrap().write(&line, entry.time) {
+            warn!("Error writing the access log. {}", err);
+        }
+    }
+}
+
+/// Response body writing the log entry once sent
+pub struct Logged<B> {
+    body: Pin<Box<B>>,
+    log: AccessLog,
+    entry: Entry,
+}
+
+impl<B: MessageBody> MessageBody for Logged<B> {
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Error>>> {
+        let this = self.get_mut();
+        let poll = this.body.as_mut().poll_next(cx);
+        if let Poll::Ready(Some(Ok(chunk))) = &poll {
+            this.entry.bytes += chunk.len() as u64;
+        }
+        poll
+    }
+}
+
+impl<B> Drop for Logged<B> {
+    fn drop(&mut self) {
+        self.entry.clone().finish(&self.log);
+    }
+}
+
+/// Sets the request id of the request, forwarded to the proxied backends too.
+pub fn set_request_id(req: &mut ServiceRequest) -> String {
+    let id = request_id(req.headers());
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        req.headers_mut()
+            .insert(HeaderName::from_static(X_REQUEST_ID), value);
+    }
+    id
+}
+
+/// Logs the response of the request and adds the request id to it.
+pub async fn log(
+    fut: LocalBoxFuture<'static, Result<ServiceResponse, Error>>,
+    log: Option<(AccessLog, Entry)>,
+    request_id: Option<String>,
+) -> Result<ServiceResponse, Error> {
+    let mut res = match fut.await {
+        Ok(res) => res,
+        Err(err) => {
+            if let Some((log, mut entry)) = log {
+                entry.status = err.as_response_error().status_code().as_u16();
+                entry.finish(&log);
+            }
+            return Err(err);
+        }
+    };
+
+    if let Some(value) = request_id.and_then(|id| HeaderValue::from_str(&id).ok()) {
+        res.headers_mut()
+            .insert(HeaderName::from_static(X_REQUEST_ID), value);
+    }
+
+    match log {
+        Some((log, mut entry)) => {
+            entry.status = res.status().as_u16();
+            Ok(res.map_body(|_, body| {
+                ResponseBody::Body(Body::from_message(Logged {
+                    body: Box::pin(body),
+                    log,
+                    entry,
+                }))
+            }))
+        }
+        None => Ok(res),
+    }
+}
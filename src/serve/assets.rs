@@ -0,0 +1,221 @@
+//! Precompressed assets and caching headers
+
+use actix_files::NamedFile;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{
+    self, ContentDisposition, ContentEncoding, DispositionParam, DispositionType, HeaderValue,
+};
+use actix_web::http::Method;
+use mime_guess::mime;
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+
+use crate::utils::is_hash;
+
+/// Cache-Control of the files with a content hash in the name
+pub const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// Cache-Control of the pages, revalidated on every request
+pub const NO_CACHE: &str = "no-cache";
+
+/// Supported precompressed siblings, in the order of preference
+const ENCODINGS: [(&str, &str, ContentEncoding); 2] = [
+    ("br", "br", ContentEncoding::Br),
+    ("gzip", "gz", ContentEncoding::Gzip),
+];
+
+/// Checks whether the file name contains a content hash,
+/// i.e. `main.3f2a1b4c5d6e7f80.js` or `chunk-5FGHKL2C.js`.
+pub fn is_hashed(file_name: &str) -> bool {
+    let mut parts: Vec<&str> = file_name.split('.').collect();
+    if parts.len() < 2 {
+        return false;
+    }
+    // the extension
+    parts.pop();
+
+    parts.iter().skip(1).any(|part| is_hash(part))
+        || parts
+            .last()
+            .and_then(|part| part.rsplit_once('-'))
+            .map_or(false, |(_, hash)| is_hash(hash))
+}
+
+/// Returns the Cache-Control value for the request path: pages and navigation requests
+/// are revalidated, hashed files are cached forever.
+pub fn cache_control(path: &str) -> Option<&'static str> {
+    let file_name = path.rsplit('/').next().unwrap_or_default();
+
+    if file_name.is_empty() || !file_name.contains('.') || file_name.ends_with(".html") {
+        Some(NO_CACHE)
+    } else if is_hashed(file_name) {
+        Some(IMMUTABLE)
+    } else {
+        None
+    }
+}
+
+fn quality(params: &str) -> f32 {
+    params
+        .split(';')
+        .filter_map(|param| param.trim().strip_prefix("q="))
+        .find_map(|value| value.trim().parse().ok())
+        .unwrap_or(1.0)
+}
+
+/// Returns the supported encodings accepted by the client, the preferred ones first.
+pub fn accepted_encodings(accept_encoding: &str) -> Vec<&'static str> {
+    let mut accepted: Vec<(&'static str, f32)> = vec![];
+
+    for (coding, _, _) in &ENCODINGS {
+        let q = accept_encoding
+            .split(',')
+            .filter_map(|item| {
+                let (name, params) = item.split_once(';').unwrap_or((item, ""));
+                let name = name.trim();
+                if name.eq_ignore_ascii_case(coding) || name == "*" {
+                    Some((name == "*", quality(params)))
+                } else {
+                    None
+                }
+            })
+            // explicit codings take precedence over the wildcard
+            .min_by_key(|(wildcard, _)| *wildcard)
+            .map(|(_, q)| q);
+
+        if let Some(q) = q.filter(|q| *q > 0.0) {
+            accepted.push((coding, q));
+        }
+    }
+
+    // stable sort keeps the preference order for equal qualities
+    accepted.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+    accepted.into_iter().map(|(coding, _)| coding).collect()
+}
+
+fn sibling_path(file: &Path, extension: &str) -> PathBuf {
+    let mut name = file.as_os_str().to_os_string();
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+/// Returns the first existing precompressed sibling of the file, i.e. `main.js.br`.
+pub fn precompressed(file: &Path, accept_encoding: &str) -> Option<(PathBuf, ContentEncoding)> {
+    accepted_encodings(accept_encoding)
+        .into_iter()
+        .find_map(|coding| {
+            let (_, extension, encoding) = ENCODINGS.iter().find(|(name, ..)| *name == coding)?;
+            let sibling = sibling_path(file, extension);
+            sibling.is_file().then(|| (sibling, *encoding))
+        })
+}
+
+/// Resolves the request path to a file in the root directory.
+pub fn resolve_file(root_dir: &Path, index_file: &str, path: &str) -> Option<PathBuf> {
+    // encoded paths are left to the static files service
+    if path.contains('%') {
+        return None;
+    }
+
+    let mut file = root_dir.to_path_buf();
+    for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+        if segment == ".." {
+            return None;
+        }
+        file.push(segment);
+    }
+
+    if file.is_dir() {
+        file.push(index_file);
+    }
+    file.is_file().then(|| file)
+}
+
+fn has_siblings(file: &Path) -> bool {
+    ENCODINGS
+        .iter()
+        .any(|(_, extension, _)| sibling_path(file, extension).is_file())
+}
+
+/// Sets the content type and disposition of the original file, as the static files service does.
+fn with_original_headers(named: NamedFile, file: &Path) -> NamedFile {
+    let content_type = mime_guess::from_path(file).first_or_octet_stream();
+    let disposition = match content_type.type_() {
+        mime::IMAGE | mime::TEXT | mime::VIDEO => DispositionType::Inline,
+        _ => DispositionType::Attachment,
+    };
+    let file_name = file
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    named
+        .set_content_type(content_type)
+        .set_content_disposition(ContentDisposition {
+            disposition,
+            parameters: vec![DispositionParam::Filename(file_name)],
+        })
+}
+
+/// Serves the precompressed sibling of the requested file when the client accepts it.
+/// Returns the request back when there is nothing to serve.
+pub fn serve_precompressed(
+    file: &Path,
+    req: ServiceRequest,
+    skip_html: bool,
+) -> Result<ServiceResponse, ServiceRequest> {
+    let is_html = file.extension().map_or(false, |ext| ext == "html");
+    if (req.method() != Method::GET && req.method() != Method::HEAD) || (skip_html && is_html) {
+        return Err(req);
+    }
+
+    let accept_encoding = req
+        .headers()
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    let (sibling, encoding) = match precompressed(file, accept_encoding) {
+        Some(found) => found,
+        None => return Err(req),
+    };
+
+    let named = match NamedFile::open(sibling) {
+        Ok(named) => with_original_headers(named, file),
+        Err(_) => return Err(req),
+    };
+
+    let (http_req, _) = req.into_parts();
+    let mut res = match named.into_response(&http_req) {
+        Ok(res) => res,
+        Err(err) => return Ok(ServiceResponse::from_err(err, http_req)),
+    };
+
+    // Compress middleware does not encode the responses with the Content-Encoding header
+    res.headers_mut().insert(
+        header::CONTENT_ENCODING,
+        HeaderValue::from_static(encoding.as_str()),
+    );
+    Ok(ServiceResponse::new(http_req, res))
+}
+
+/// Adds the caching headers to the response for the static file. The hashed urls are cached
+/// forever only when the file is served, the errors and the fallback pages are revalidated.
+pub fn set_cache_headers(file: Option<&Path>, res: &mut ServiceResponse) {
+    let value = match cache_control(res.request().path()) {
+        Some(IMMUTABLE) if file.is_none() || !res.status().is_success() => Some(NO_CACHE),
+        value => value,
+    };
+    if let Some(value) = value {
+        if !res.headers().contains_key(header::CACHE_CONTROL) {
+            res.headers_mut()
+                .insert(header::CACHE_CONTROL, HeaderValue::from_static(value));
+        }
+    }
+
+    if file.map_or(false, has_siblings) {
+        res.headers_mut()
+            .append(header::VARY, HeaderValue::from_static("Accept-Encoding"));
+    }
+}
@@ -0,0 +1,303 @@
+//! Custom response headers, Content Security Policy and CORS
+
+use actix_web::dev::RequestHead;
+use actix_web::http::{header, HeaderName, HeaderValue, Method};
+use actix_web::{web, HttpResponse};
+use anyhow::{anyhow, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use log::warn;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Endpoint the browsers post the CSP violation reports to
+pub const CSP_REPORT_PATH: &str = "/__rung/csp-report";
+
+/// Policy used by the report mode when none is configured
+const DEFAULT_CSP: &str = "default-src 'self'";
+
+/// Headers for the requests matching the glob pattern
+#[derive(Clone, Debug, Deserialize)]
+pub struct HeaderRule {
+    /// Glob pattern of the request path, i.e. `/assets/**` or `**/*.js`.
+    pub source: String,
+    pub headers: BTreeMap<String, String>,
+}
+
+/// Cross-origin resource sharing
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CorsConfig {
+    /// Allowed origins, `*` allows any origin.
+    pub origins: Vec<String>,
+    /// Allowed methods of the preflight requests, `GET, HEAD, OPTIONS` by default.
+    #[serde(default)]
+    pub methods: Vec<String>,
+    /// Allowed request headers of the preflight requests, the requested ones by default.
+    #[serde(default)]
+    pub headers: Vec<String>,
+    /// Allows cookies and credentials.
+    #[serde(default)]
+    pub credentials: bool,
+    /// Seconds the preflight response can be cached for.
+    pub max_age: Option<u64>,
+}
+
+/// Response headers configuration
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeadersConfig {
+    /// Custom headers, later rules override the earlier ones.
+    #[serde(default)]
+    pub rules: Vec<HeaderRule>,
+    /// `Content-Security-Policy` header value.
+    pub content_security_policy: Option<String>,
+    /// Sends the policy as `Content-Security-Policy-Report-Only` and logs the violations.
+    #[serde(default)]
+    pub csp_report: bool,
+    pub cors: Option<CorsConfig>,
+    /// Adds `Strict-Transport-Security` (HTTPS only), `X-Frame-Options`,
+    /// `X-Content-Type-Options` and `Referrer-Policy` headers.
+    #[serde(default)]
+    pub security_headers: bool,
+}
+
+/// Reads the headers configuration file.
+pub fn read_headers_config(path: &Path) -> Result<HeadersConfig> {
+    let reader = BufReader::new(File::open(path)?);
+    let config: HeadersConfig = serde_json::from_reader(reader)?;
+    Headers::new(&config, false)?;
+    Ok(config)
+}
+
+/// Parses the `Name: value` header.
+pub fn parse_header(text: &str) -> Result<(String, String)> {
+    match text.split_once(':') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err(anyhow!("Invalid header `{}`, expected `Name: value`", text)),
+    }
+}
+
+type HeaderList = Vec<(HeaderName, HeaderValue)>;
+
+fn to_header(name: &str, value: &str) -> Result<(HeaderName, HeaderValue)> {
+    let name = HeaderName::from_bytes(name.as_bytes())
+        .map_err(|_| anyhow!("Invalid header name `{}`", name))?;
+    let value =
+        HeaderValue::from_str(value).map_err(|_| anyhow!("Invalid header value `{}`", value))?;
+    Ok((name, value))
+}
+
+/// Compiled headers configuration
+pub struct Headers {
+    common: HeaderList,
+    rules: Vec<(GlobMatcher, HeaderList)>,
+    cors: Option<CorsConfig>,
+    csp_report: bool,
+}
+
+impl Headers {
+    pub fn new(config: &HeadersConfig, https: bool) -> Result<Self> {
+        let mut common = vec![];
+
+        if config.security_headers {
+            if https {
+                common.push(to_header(
+                    "strict-transport-security",
+                    "max-age=31536000; includeSubDomains",
+                )?);
+            }
+            common.push(to_header("x-frame-options", "DENY")?);
+            common.push(to_header("x-content-type-options", "nosniff")?);
+            common.push(to_header(
+                "referrer-policy",
+                "strict-origin-when-cross-origin",
+            )?);
+        }
+
+        match (&config.content_security_policy, config.csp_report) {
+            (policy, true) => {
+                let policy = policy.as_deref().unwrap_or(DEFAULT_CSP);
+                common.push(to_header(
+                    "content-security-policy-report-only",
+                    &format!("{}; report-uri {}", policy, CSP_REPORT_PATH),
+                )?);
+            }
+            (Some(policy), false) => common.push(to_header("content-security-policy", policy)?),
+            (None, false) => {}
+        }
+
+        let mut rules = vec![];
+        for rule in &config.rules {
+            let glob = GlobBuilder::new(&rule.source)
+                .literal_separator(true)
+                .build()?;
+            let mut headers = vec![];
+            for (name, value) in &rule.headers {
+                headers.push(to_header(name, value)?);
+            }
+            rules.push((glob.compile_matcher(), headers));
+        }
+
+        Ok(Headers {
+            common,
+            rules,
+            cors: config.cors.clone(),
+            csp_report: config.csp_report,
+        })
+    }
+
+    /// Violations are reported to the local endpoint.
+    pub fn csp_report(&self) -> bool {
+        self.csp_report
+    }
+
+    /// Returns the `Access-Control-Allow-Origin` value for the request origin.
+    pub fn allowed_origin(&self, origin: &str) -> Option<String> {
+        let cors = self.cors.as_ref()?;
+
+        if cors.origins.iter().any(|allowed| allowed == origin) {
+            Some(origin.to_string())
+        } else if cors.origins.iter().any(|allowed| allowed == "*") {
+            // the wildcard is not allowed for the requests with credentials
+            Some(if cors.credentials { origin } else { "*" }.to_string())
+        } else {
+            None
+        }
+    }
+
+    fn cors_headers(&self, req: &RequestHead) -> HeaderList {
+        let origin = match req.headers.get(header::ORIGIN) {
+            Some(origin) => origin.to_str().unwrap_or_default(),
+            None => return vec![],
+        };
+
+        let mut headers = vec![];
+        if let Some(allowed) = self.allowed_origin(origin) {
+            if let Ok(value) = HeaderValue::from_str(&allowed) {
+                headers.push((header::ACCESS_CONTROL_ALLOW_ORIGIN, value));
+            }
+            if self.cors.as_ref().map_or(false, |cors| cors.credentials) {
+                headers.push((
+                    header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                    HeaderValue::from_static("true"),
+                ));
+            }
+        }
+        if self.cors.is_some() {
+            headers.push((header::VARY, HeaderValue::from_static("Origin")));
+        }
+        headers
+    }
+
+    /// Returns the headers to add to the response, in the order they should be applied.
+    pub fn response_headers(&self, req: &RequestHead) -> HeaderList {
+        let mut headers = self.common.clone();
+
+        for (glob, rule_headers) in &self.rules {
+            if glob.is_match(req.uri.path()) {
+                headers.extend(rule_headers.iter().cloned());
+            }
+        }
+
+        headers.extend(self.cors_headers(req));
+        headers
+    }
+
+    /// Responds to the CORS preflight requests.
+    pub fn preflight(&self, req: &RequestHead) -> Option<HttpResponse> {
+        let cors = self.cors.as_ref()?;
+        if req.method != Method::OPTIONS
+            || !req
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+        {
+            return None;
+        }
+
+        // the origin is not allowed when the response has no `Access-Control-Allow-Origin` header
+        let mut response = HttpResponse::NoContent();
+        for (name, value) in self.cors_headers(req) {
+            response.set_header(name, value);
+        }
+
+        let methods = if cors.methods.is_empty() {
+            String::from("GET, HEAD, OPTIONS")
+        } else {
+            cors.methods.join(", ")
+        };
+        response.set_header(header::ACCESS_CONTROL_ALLOW_METHODS, methods);
+
+        if !cors.headers.is_empty() {
+            response.set_header(
+                header::ACCESS_CONTROL_ALLOW_HEADERS,
+                cors.headers.join(", "),
+            );
+        } else if let Some(requested) = req.headers.get(header::ACCESS_CONTROL_REQUEST_HEADERS) {
+            response.set_header(header::ACCESS_CONTROL_ALLOW_HEADERS, requested.clone());
+        }
+
+        if let Some(max_age) = cors.max_age {
+            response.set_header(header::ACCESS_CONTROL_MAX_AGE, max_age.to_string());
+        }
+
+        Some(response.finish())
+    }
+}
+
+fn field<'a>(value: &'a Value, names: &[&str]) -> &'a str {
+    names
+        .iter()
+        .find_map(|name| value.get(*name).and_then(Value::as_str))
+        .unwrap_or("?")
+}
+
+/// Describes the violations of the legacy `report-uri` (`{"csp-report": {...}}`)
+/// and the Reporting API (`[{"type": "csp-violation", "body": {...}}]`) formats.
+pub fn describe_violations(report: &Value) -> Vec<String> {
+    let violations: Vec<&Value> = match report {
+        Value::Array(reports) => reports
+            .iter()
+            .filter_map(|report| report.get("body"))
+            .collect(),
+        _ => report.get("csp-report").into_iter().collect(),
+    };
+
+    violations
+        .into_iter()
+        .map(|violation| {
+            format!(
+                "`{}` blocked `{}` on {}",
+                field(
+                    violation,
+                    &[
+                        "effective-directive",
+                        "effectiveDirective",
+                        "violated-directive"
+                    ]
+                ),
+                field(violation, &["blocked-uri", "blockedURL"]),
+                field(violation, &["document-uri", "documentURL"]),
+            )
+        })
+        .collect()
+}
+
+/// Logs the CSP violation reports
+pub async fn csp_report(body: web::Bytes) -> HttpResponse {
+    match serde_json::from_slice::<Value>(&body) {
+        Ok(report) => {
+            for violation in describe_violations(&report) {
+                warn!("CSP violation: {}", violation);
+            }
+        }
+        Err(err) => warn!("Invalid CSP report. {}", err),
+    }
+
+    HttpResponse::NoContent().finish()
+}
@@ -0,0 +1,175 @@
+//! Locale-aware serving of the localized builds
+//!
+//! The root is redirected to the locale preferred by the cookie or the `Accept-Language` header,
+//! like the production server configurations of the localized applications do.
+
+use actix_web::dev::RequestHead;
+use actix_web::http::{header, Method};
+use actix_web::HttpResponse;
+use std::cmp::Ordering;
+
+use super::assets::NO_CACHE;
+
+/// Cookie with the locale chosen by the user, unless configured
+pub const DEFAULT_COOKIE: &str = "locale";
+
+/// Locale build served under the path prefix
+#[derive(Clone, Debug, PartialEq)]
+pub struct LocaleRoute {
+    pub locale: String,
+    /// Path prefix without the trailing slash, as the mounts
+    pub prefix: String,
+}
+
+/// Localized build options
+#[derive(Clone, Debug, PartialEq)]
+pub struct LocaleOptions {
+    /// Locales, the first one is used when none is preferred
+    pub routes: Vec<LocaleRoute>,
+    /// Name of the cookie with the preferred locale
+    pub cookie: String,
+}
+
+/// Returns the language ranges of the `Accept-Language` header, the preferred ones first.
+pub fn accepted_languages(accept_language: &str) -> Vec<String> {
+    let mut languages: Vec<(String, f32)> = accept_language
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let language = parts.next()?.trim();
+            let q = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|value| value.trim().parse().ok())
+                .unwrap_or(1.0);
+            (!language.is_empty() && q > 0.0).then(|| (language.to_string(), q))
+        })
+        .collect();
+
+    // stable sort keeps the order of the equal qualities
+    languages.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+    languages
+        .into_iter()
+        .map(|(language, _)| language)
+        .collect()
+}
+
+fn primary_language(tag: &str) -> &str {
+    tag.split(['-', '_']).next().unwrap_or(tag)
+}
+
+fn cookie_value<'a>(req: &'a RequestHead, name: &str) -> Option<&'a str> {
+    req.headers
+        .get_all(header::COOKIE)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(cookie, _)| *cookie == name)
+        .map(|(_, value)| value.trim_matches('"'))
+}
+
+impl LocaleOptions {
+    /// Path the locale prefixes are under, with the trailing slash.
+    pub fn root(&self) -> &str {
+        self.routes
+            .first()
+            .and_then(|route| route.prefix.rfind('/').map(|index| &route.prefix[..=index]))
+            .unwrap_or("/")
+    }
+
+    fn find_locale(&self, tag: &str) -> Option<&LocaleRoute> {
+        let tag = tag.trim();
+        self.routes
+            .iter()
+            .find(|route| route.locale.eq_ignore_ascii_case(tag))
+            .or_else(|| {
+                // the cookie can hold the locale base href, i.e. `deutsch`
+                self.routes.iter().find(|route| {
+                    route
+                        .prefix
+                        .strip_prefix(self.root())
+                        .map_or(false, |rest| rest.eq_ignore_ascii_case(tag))
+                })
+            })
+    }
+
+    /// Returns the locale preferred by the cookie value and the `Accept-Language` header.
+    pub fn preferred(&self, cookie: Option<&str>, accept_language: Option<&str>) -> &LocaleRoute {
+        if let Some(route) = cookie.and_then(|value| self.find_locale(value)) {
+            return route;
+        }
+
+        for language in accepted_languages(accept_language.unwrap_or_default()) {
+            if language == "*" {
+                break;
+            }
+            if let Some(route) = self.find_locale(&language) {
+                return route;
+            }
+            // `de-AT` matches `de`, `de` matches `de-DE`
+            let primary = primary_language(&language);
+            if let Some(route) = self
+                .routes
+                .iter()
+                .find(|route| primary_language(&route.locale).eq_ignore_ascii_case(primary))
+            {
+                return route;
+            }
+        }
+
+        &self.routes[0]
+    }
+
+    fn is_localized(&self, path: &str) -> bool {
+        self.routes.iter().any(|route| {
+            path.strip_prefix(route.prefix.as_str())
+                .map_or(false, |rest| rest.is_empty() || rest.starts_with('/'))
+        })
+    }
+
+    /// Redirects the root, and the navigation requests outside of the locales,
+    /// to the preferred locale.
+    pub fn redirect(&self, req: &RequestHead, is_navigation: bool) -> Option<HttpResponse> {
+        if req.method != Method::GET && req.method != Method::HEAD {
+            return None;
+        }
+
+        let root = self.root();
+        // a locale served at the root gets all the requests
+        if self
+            .routes
+            .iter()
+            .any(|route| route.prefix == "/" || route.prefix == root)
+        {
+            return None;
+        }
+
+        let path = req.uri.path();
+        let is_root = path == root || path == root.trim_end_matches('/');
+        let rest = match path.strip_prefix(root) {
+            _ if is_root => "",
+            Some(rest) if is_navigation && !self.is_localized(path) => rest,
+            _ => return None,
+        };
+
+        let cookie = cookie_value(req, &self.cookie);
+        let accept_language = req
+            .headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok());
+        let route = self.preferred(cookie, accept_language);
+
+        let mut location = format!("{}/{}", route.prefix, rest);
+        if let Some(query) = req.uri.query() {
+            location.push('?');
+            location.push_str(query);
+        }
+
+        Some(
+            HttpResponse::Found()
+                .header(header::LOCATION, location)
+                .header(header::VARY, "Accept-Language, Cookie")
+                .header(header::CACHE_CONTROL, NO_CACHE)
+                .finish(),
+        )
+    }
+}
@@ -0,0 +1,316 @@
+//! Directory listings of the served directories without the index page
+
+use actix_web::http::header;
+use actix_web::{HttpRequest, HttpResponse};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use serde_json::{json, Value};
+use std::cmp::Ordering;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use super::assets::NO_CACHE;
+use crate::template::Escape;
+use crate::utils::format_size;
+
+/// Characters encoded in the links to the entries
+const SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}')
+    .add(b'/');
+
+const STYLE: &str = r#"<style>
+body { font-family: system-ui, sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; min-width: 40em; }
+th, td { padding: 0.25em 1em 0.25em 0; text-align: left; }
+th a { color: inherit; }
+td.size, th.size { text-align: right; }
+tr:hover td { background: #f3f3f3; }
+</style>"#;
+
+/// Column the entries are sorted by
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortKey {
+    Name,
+    Size,
+    Modified,
+}
+
+impl FromStr for SortKey {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "name" => Ok(SortKey::Name),
+            "size" => Ok(SortKey::Size),
+            "modified" => Ok(SortKey::Modified),
+            _ => Err(()),
+        }
+    }
+}
+
+impl SortKey {
+    fn name(&self) -> &'static str {
+        match self {
+            SortKey::Name => "name",
+            SortKey::Size => "size",
+            SortKey::Modified => "modified",
+        }
+    }
+}
+
+/// Sorting of the listing, by name in ascending order unless the query overrides it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sorting {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl Default for Sorting {
+    fn default() -> Self {
+        Sorting {
+            key: SortKey::Name,
+            descending: false,
+        }
+    }
+}
+
+impl Sorting {
+    /// Reads the `sort` (`name`, `size` or `modified`) and `order` (`asc` or `desc`) query parameters.
+    pub fn from_query(query: &str) -> Self {
+        let mut sorting = Sorting::default();
+        for (name, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+            match name {
+                "sort" => sorting.key = value.parse().unwrap_or(sorting.key),
+                "order" => sorting.descending = value == "desc",
+                _ => {}
+            }
+        }
+        sorting
+    }
+
+    /// Query of the column link, toggles the order of the current column.
+    fn query(&self, key: SortKey) -> String {
+        let descending = key == self.key && !self.descending;
+        format!(
+            "?sort={}&order={}",
+            key.name(),
+            if descending { "desc" } else { "asc" }
+        )
+    }
+}
+
+/// Directory entry
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    pub is_dir: bool,
+    /// Size in bytes, `0` for the directories
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+impl Entry {
+    fn href(&self) -> String {
+        let href = utf8_percent_encode(&self.name, SEGMENT).to_string();
+        if self.is_dir {
+            href + "/"
+        } else {
+            href
+        }
+    }
+
+    fn modified(&self) -> Option<String> {
+        self.modified
+            .map(|time| humantime::format_rfc3339_seconds(time).to_string())
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "type": if self.is_dir { "directory" } else { "file" },
+            "size": self.size,
+            "modified": self.modified(),
+        })
+    }
+}
+
+/// Resolves the request path to a directory of the root directory.
+pub fn resolve_dir(root_dir: &Path, path: &str) -> Option<PathBuf> {
+    let mut dir = root_dir.to_path_buf();
+    for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+        let segment = percent_decode_str(segment).decode_utf8().ok()?;
+        if segment == ".." || segment.contains(['/', '\\']) {
+            return None;
+        }
+        dir.push(segment.as_ref());
+    }
+    dir.is_dir().then(|| dir)
+}
+
+/// Reads the directory entries, the hidden ones are skipped.
+pub fn read_entries(dir: &Path) -> io::Result<Vec<Entry>> {
+    let mut entries = vec![];
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') {
+            continue;
+        }
+
+        // follows the symlinks, as the static files service does
+        let metadata = match fs::metadata(entry.path()) {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        entries.push(Entry {
+            name,
+            is_dir: metadata.is_dir(),
+            size: if metadata.is_dir() { 0 } else { metadata.len() },
+            modified: metadata.modified().ok(),
+        });
+    }
+    Ok(entries)
+}
+
+/// Sorts the entries, the directories go first.
+pub fn sort_entries(entries: &mut [Entry], sorting: Sorting) {
+    entries.sort_by(|a, b| {
+        let by_name = || a.name.to_lowercase().cmp(&b.name.to_lowercase());
+        let ordering = match sorting.key {
+            SortKey::Name => by_name(),
+            SortKey::Size => a.size.cmp(&b.size).then_with(by_name),
+            SortKey::Modified => a.modified.cmp(&b.modified).then_with(by_name),
+        };
+        let ordering = if sorting.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        };
+        match (a.is_dir, b.is_dir) {
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            _ => ordering,
+        }
+    });
+}
+
+/// Renders the HTML listing of the request path.
+pub fn render_html(path: &str, entries: &[Entry], sorting: Sorting, has_parent: bool) -> String {
+    let title = Escape::Html.apply(path);
+    let header = |key: SortKey, label: &str, class: &str| {
+        let arrow = match sorting {
+            Sorting { key: current, .. } if current != key => "",
+            Sorting {
+                descending: true, ..
+            } => " ▼",
+            _ => " ▲",
+        };
+        format!(
+            "<th class=\"{}\"><a href=\"{}\">{}</a>{}</th>",
+            class,
+            Escape::Html.apply(&sorting.query(key)),
+            label,
+            arrow
+        )
+    };
+
+    let mut rows = String::new();
+    if has_parent {
+        rows.push_str("<tr><td><a href=\"../\">../</a></td><td></td><td></td></tr>\n");
+    }
+    for entry in entries {
+        let name = if entry.is_dir {
+            format!("{}/", entry.name)
+        } else {
+            entry.name.clone()
+        };
+        let size = if entry.is_dir {
+            String::from("-")
+        } else {
+            format_size(entry.size)
+        };
+        let modified = entry.modified().unwrap_or_default();
+        rows.push_str(&format!(
+            "<tr><td><a href=\"{}\">{}</a></td><td class=\"size\" title=\"{} bytes\">{}</td><td><time datetime=\"{}\">{}</time></td></tr>\n",
+            Escape::Html.apply(&entry.href()),
+            Escape::Html.apply(&name),
+            entry.size,
+            size,
+            modified,
+            modified.replace('T', " ").trim_end_matches('Z'),
+        ));
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Index of {title}</title>\n{style}\n</head>\n<body>\n<h1>Index of {title}</h1>\n<table>\n<thead><tr>{name}{size}{modified}</tr></thead>\n<tbody>\n{rows}</tbody>\n</table>\n</body>\n</html>\n",
+        title = title,
+        style = STYLE,
+        name = header(SortKey::Name, "Name", "name"),
+        size = header(SortKey::Size, "Size", "size"),
+        modified = header(SortKey::Modified, "Modified (UTC)", "modified"),
+        rows = rows,
+    )
+}
+
+/// Renders the JSON listing of the request path.
+pub fn render_json(path: &str, entries: &[Entry]) -> Value {
+    json!({
+        "path": path,
+        "entries": entries.iter().map(Entry::to_json).collect::<Vec<_>>(),
+    })
+}
+
+fn accepts_json(req: &HttpRequest) -> bool {
+    req.headers()
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.contains("application/json"))
+        .unwrap_or(false)
+}
+
+/// Responds with the listing of the directory, as JSON for the `Accept: application/json` requests.
+/// The parent link is rendered unless the directory is the served root.
+pub fn respond(req: &HttpRequest, dir: &Path, has_parent: bool) -> HttpResponse {
+    let path = req.path();
+    // the entry links are relative to the directory
+    if !path.ends_with('/') {
+        let location = match req.query_string() {
+            "" => format!("{}/", path),
+            query => format!("{}/?{}", path, query),
+        };
+        return HttpResponse::Found()
+            .header(header::LOCATION, location)
+            .finish();
+    }
+
+    let mut entries = match read_entries(dir) {
+        Ok(entries) => entries,
+        Err(_) => return HttpResponse::Forbidden().finish(),
+    };
+    let sorting = Sorting::from_query(req.query_string());
+    sort_entries(&mut entries, sorting);
+
+    let display_path = percent_decode_str(path).decode_utf8_lossy().to_string();
+    let mut response = HttpResponse::Ok();
+    response
+        .header(header::CACHE_CONTROL, NO_CACHE)
+        .header(header::VARY, "Accept");
+    if accepts_json(req) {
+        response.json(render_json(&display_path, &entries))
+    } else {
+        response
+            .content_type("text/html; charset=utf-8")
+            .body(render_html(&display_path, &entries, sorting, has_parent))
+    }
+}
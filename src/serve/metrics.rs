@@ -0,0 +1,267 @@
+//! Request statistics: the health check and the Prometheus metrics endpoints
+
+use actix_web::dev::{Body, BodySize, MessageBody, ResponseBody, ServiceResponse};
+use actix_web::http::{header, Method, StatusCode};
+use actix_web::web::{Bytes, Data};
+use actix_web::{Error, HttpResponse};
+use futures::future::LocalBoxFuture;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+pub const HEALTH_PATH: &str = "/__rung/health";
+pub const METRICS_PATH: &str = "/__rung/metrics";
+
+/// Prefix of the internal endpoints, not counted
+const INTERNAL_PREFIX: &str = "/__rung/";
+
+/// Upper bounds of the latency histogram buckets, in seconds
+pub const LATENCY_BUCKETS: [f64; 12] = [
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Limit of the paths counted separately
+const MAX_PATHS: usize = 1000;
+
+#[derive(Default)]
+struct Stats {
+    /// Requests by the method and the status code
+    requests: BTreeMap<(String, u16), u64>,
+    /// Successful requests by the path
+    paths: BTreeMap<String, u64>,
+    /// Transferred bytes of the response bodies
+    bytes: u64,
+    /// Requests by the latency bucket, the last one is `+Inf`
+    buckets: [u64; LATENCY_BUCKETS.len() + 1],
+    latency_sum: f64,
+}
+
+/// Request statistics shared by the workers
+#[derive(Clone)]
+pub struct Metrics {
+    started: Instant,
+    stats: Arc<Mutex<Stats>>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics {
+            started: Instant::now(),
+            stats: Arc::new(Mutex::new(Stats::default())),
+        }
+    }
+}
+
+/// Escapes the Prometheus label value.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn write_help(text: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(text, "# HELP {} {}", name, help);
+    let _ = writeln!(text, "# TYPE {} {}", name, kind);
+}
+
+impl Metrics {
+    /// Checks whether the requests of the path are counted, the internal endpoints are not.
+    pub fn is_counted(path: &str) -> bool {
+        !path.starts_with(INTERNAL_PREFIX)
+    }
+
+    /// Counts the response of the request.
+    pub fn record(&self, method: &Method, path: &str, status: StatusCode, latency: Duration) {
+        let mut stats = self.stats.lock().unwrap();
+        *stats
+            .requests
+            .entry((method.to_string(), status.as_u16()))
+            .or_default() += 1;
+
+        // the requested files, i.e. the lazy chunks
+        let is_read = method == Method::GET || method == Method::HEAD;
+        if is_read && (status.is_success() || status == StatusCode::NOT_MODIFIED) {
+            if let Some(count) = stats.paths.get_mut(path) {
+                *count += 1;
+            } else if stats.paths.len() < MAX_PATHS {
+                stats.paths.insert(path.to_string(), 1);
+            }
+        }
+
+        let seconds = latency.as_secs_f64();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        stats.buckets[bucket] += 1;
+        stats.latency_sum += seconds;
+    }
+
+    /// Counts the transferred bytes.
+    pub fn add_bytes(&self, bytes: u64) {
+        self.stats.lock().unwrap().bytes += bytes;
+    }
+
+    /// Number of the counted requests
+    pub fn requests(&self) -> u64 {
+        self.stats.lock().unwrap().requests.values().sum()
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Renders the metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let stats = self.stats.lock().unwrap();
+        let mut text = String::new();
+
+        write_help(
+            &mut text,
+            "rung_uptime_seconds",
+            "gauge",
+            "Seconds since the start.",
+        );
+        let _ = writeln!(
+            text,
+            "rung_uptime_seconds {:.3}",
+            self.uptime().as_secs_f64()
+        );
+
+        write_help(
+            &mut text,
+            "rung_requests_total",
+            "counter",
+            "Requests by the method and the status code.",
+        );
+        for ((method, status), count) in &stats.requests {
+            let _ = writeln!(
+                text,
+                "rung_requests_total{{method=\"{}\",status=\"{}\"}} {}",
+                method, status, count
+            );
+        }
+
+        write_help(
+            &mut text,
+            "rung_response_bytes_total",
+            "counter",
+            "Transferred bytes of the response bodies.",
+        );
+        let _ = writeln!(text, "rung_response_bytes_total {}", stats.bytes);
+
+        write_help(
+            &mut text,
+            "rung_path_requests_total",
+            "counter",
+            "Successful GET and HEAD requests by the path.",
+        );
+        for (path, count) in &stats.paths {
+            let _ = writeln!(
+                text,
+                "rung_path_requests_total{{path=\"{}\"}} {}",
+                escape_label(path),
+                count
+            );
+        }
+
+        write_help(
+            &mut text,
+            "rung_request_duration_seconds",
+            "histogram",
+            "Time to the response headers.",
+        );
+        let mut cumulative = 0;
+        for (index, count) in stats.buckets.iter().enumerate() {
+            cumulative += count;
+            let bound = match LATENCY_BUCKETS.get(index) {
+                Some(bound) => bound.to_string(),
+                None => String::from("+Inf"),
+            };
+            let _ = writeln!(
+                text,
+                "rung_request_duration_seconds_bucket{{le=\"{}\"}} {}",
+                bound, cumulative
+            );
+        }
+        let _ = writeln!(
+            text,
+            "rung_request_duration_seconds_sum {:.6}",
+            stats.latency_sum
+        );
+        let _ = writeln!(text, "rung_request_duration_seconds_count {}", cumulative);
+
+        text
+    }
+}
+
+/// Response body counting the transferred bytes
+pub struct Counted<B> {
+    body: Pin<Box<B>>,
+    metrics: Metrics,
+}
+
+impl<B: MessageBody> MessageBody for Counted<B> {
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Error>>> {
+        let this = self.get_mut();
+        let poll = this.body.as_mut().poll_next(cx);
+        if let Poll::Ready(Some(Ok(chunk))) = &poll {
+            this.metrics.add_bytes(chunk.len() as u64);
+        }
+        poll
+    }
+}
+
+/// Counts the response of the request and the bytes of its body.
+pub async fn measure(
+    fut: LocalBoxFuture<'static, Result<ServiceResponse, Error>>,
+    metrics: Metrics,
+    method: Method,
+    path: String,
+) -> Result<ServiceResponse, Error> {
+    let started = Instant::now();
+    let res = match fut.await {
+        Ok(res) => res,
+        Err(err) => {
+            let status = err.as_response_error().status_code();
+            metrics.record(&method, &path, status, started.elapsed());
+            return Err(err);
+        }
+    };
+
+    metrics.record(&method, &path, res.status(), started.elapsed());
+    Ok(res.map_body(|_, body| {
+        ResponseBody::Body(Body::from_message(Counted {
+            body: Box::pin(body),
+            metrics,
+        }))
+    }))
+}
+
+/// Health check handler
+pub async fn health(metrics: Data<Metrics>) -> HttpResponse {
+    HttpResponse::Ok()
+        .header(header::CACHE_CONTROL, "no-store")
+        .json(json!({
+            "status": "ok",
+            "uptime": metrics.uptime().as_secs(),
+            "requests": metrics.requests(),
+        }))
+}
+
+/// Prometheus metrics handler
+pub async fn metrics(metrics: Data<Metrics>) -> HttpResponse {
+    HttpResponse::Ok()
+        .header(header::CACHE_CONTROL, "no-store")
+        .content_type("text/plain; version=0.0.4; charset=utf-8")
+        .body(metrics.render())
+}
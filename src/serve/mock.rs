@@ -0,0 +1,493 @@
+//! Mock REST API answering from the JSON fixtures
+//!
+//! Only the requests under the prefix are mocked, `/api` by default.
+//! `GET /api/users` is answered with `mocks/api/users.json`, `[name]` files and directories
+//! match any path segment, i.e. `mocks/api/users/[id].json`, and replace the `{{id}}` placeholders
+//! of the fixture. Items of the array fixtures can be
+//! read and changed by id (`/api/users/1`), changes are kept in memory until the server stops
+//! or the fixture file changes, separately for every value of the `[name]` segments.
+
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::{HeaderName, HeaderValue, Method, StatusCode};
+use actix_web::web::BytesMut;
+use actix_web::{Error, HttpMessage, HttpResponse};
+use futures::stream::StreamExt;
+use log::{debug, error};
+use notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+
+/// Response metadata, read from the `<name>.meta.json` file next to the fixture
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct MockMeta {
+    /// Status code of the `GET` responses, `200` by default.
+    pub status: Option<u16>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// Mock API response
+#[derive(Debug, PartialEq)]
+pub struct MockResponse {
+    pub status: StatusCode,
+    pub headers: BTreeMap<String, String>,
+    pub body: Option<Value>,
+}
+
+impl MockResponse {
+    fn new(status: StatusCode, body: Option<Value>) -> Self {
+        MockResponse {
+            status,
+            headers: BTreeMap::new(),
+            body,
+        }
+    }
+
+    fn error(status: StatusCode, message: &str) -> Self {
+        MockResponse::new(status, Some(json!({ "error": message })))
+    }
+}
+
+/// Fixture matching the request path
+#[derive(Debug, PartialEq)]
+enum Route {
+    /// The fixture itself, with the values of the `[name]` segments
+    Resource {
+        file: PathBuf,
+        params: BTreeMap<String, String>,
+    },
+    /// Item of the array fixture
+    Item {
+        file: PathBuf,
+        params: BTreeMap<String, String>,
+        id: String,
+    },
+}
+
+/// In-memory state of the fixture file with the values of the `[name]` segments
+type DataKey = (PathBuf, BTreeMap<String, String>);
+
+/// Fixture files and directories, read once and refreshed when the directory changes
+#[derive(Debug, Default)]
+struct Fixtures {
+    /// Sorted entries by the directory
+    entries: HashMap<PathBuf, Vec<PathBuf>>,
+    files: HashSet<PathBuf>,
+}
+
+impl Fixtures {
+    fn read(root_dir: &Path) -> Self {
+        let mut fixtures = Fixtures::default();
+        fixtures.read_dir(root_dir);
+        fixtures
+    }
+
+    fn read_dir(&mut self, dir: &Path) {
+        let mut entries: Vec<PathBuf> = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    path.file_name()
+                        .and_then(|name| name.to_str())
+                        .map_or(false, |name| !name.starts_with('.'))
+                })
+                .collect(),
+            Err(_) => vec![],
+        };
+        entries.sort();
+
+        for path in &entries {
+            if path.is_dir() {
+                self.read_dir(path);
+            } else if path.extension().map_or(false, |ext| ext == "json") {
+                self.files.insert(path.clone());
+            }
+        }
+        self.entries.insert(dir.to_path_buf(), entries);
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.entries.contains_key(path)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.files.contains(path)
+    }
+
+    /// Finds the parameter entry of the directory, directories or `.json` files.
+    fn find_param(&self, dir: &Path, is_dir: bool) -> Option<(PathBuf, String)> {
+        self.entries.get(dir)?.iter().find_map(|path| {
+            if is_dir != self.is_dir(path) || (!is_dir && !self.is_file(path)) {
+                return None;
+            }
+            let file_name = path.file_name()?.to_str()?;
+            let name = if is_dir {
+                file_name
+            } else {
+                file_name.strip_suffix(".json")?
+            };
+            let param = name.strip_prefix('[')?.strip_suffix(']')?.to_string();
+            Some((path.clone(), param))
+        })
+    }
+}
+
+/// Replaces the `{{name}}` placeholders of the string values with the route params.
+fn substitute_params(value: &mut Value, params: &BTreeMap<String, String>) {
+    match value {
+        Value::String(text) if text.contains("{{") => {
+            for (name, param) in params {
+                *text = text.replace(&format!("{{{{{}}}}}", name), param);
+            }
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| substitute_params(item, params)),
+        Value::Object(map) => map
+            .values_mut()
+            .for_each(|item| substitute_params(item, params)),
+        _ => {}
+    }
+}
+
+fn id_matches(item: &Value, id: &str) -> bool {
+    match item.get("id") {
+        Some(Value::String(value)) => value == id,
+        Some(Value::Number(value)) => value.to_string() == id,
+        _ => false,
+    }
+}
+
+/// Returns the next numeric id of the collection.
+fn next_id(items: &[Value]) -> Value {
+    let max = items
+        .iter()
+        .filter_map(|item| item.get("id").and_then(Value::as_u64))
+        .max()
+        .unwrap_or(0);
+    json!(max + 1)
+}
+
+/// Mock API with the in-memory state of the fixtures
+#[derive(Clone)]
+pub struct MockApi {
+    root_dir: PathBuf,
+    /// Path prefix of the mocked requests, without the trailing slash
+    prefix: String,
+    fixtures: Arc<RwLock<Fixtures>>,
+    data: Arc<Mutex<HashMap<DataKey, Value>>>,
+}
+
+impl MockApi {
+    /// Reads the fixtures of the directory, only the requests under the prefix are mocked.
+    pub fn new(root_dir: impl Into<PathBuf>, prefix: &str) -> Self {
+        let root_dir = root_dir.into();
+        // the watcher reports the absolute paths of the changed files
+        let root_dir = root_dir.canonicalize().unwrap_or(root_dir);
+        MockApi {
+            fixtures: Arc::new(RwLock::new(Fixtures::read(&root_dir))),
+            root_dir,
+            prefix: prefix.trim_end_matches('/').to_string(),
+            data: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Reads the fixtures again, i.e. after the files are added or removed.
+    pub fn refresh(&self) {
+        *self.fixtures.write().unwrap() = Fixtures::read(&self.root_dir);
+    }
+
+    /// Drops the in-memory state of the fixture file, i.e. after the file is changed.
+    pub fn evict(&self, file: &Path) {
+        self.data
+            .lock()
+            .unwrap()
+            .retain(|(data_file, _), _| data_file != file);
+    }
+
+    /// Watches the directory in a background thread and refreshes the fixtures on changes.
+    pub fn watch(&self) -> notify::Result<()> {
+        let (tx, rx) = channel();
+        let mut watcher = watcher(tx, Duration::from_millis(200))?;
+        watcher.watch(&self.root_dir, RecursiveMode::Recursive)?;
+
+        let mock_api = self.clone();
+        thread::spawn(move || {
+            // the watcher stops when dropped
+            let _watcher = watcher;
+
+            while let Ok(event) = rx.recv() {
+                match event {
+                    DebouncedEvent::Write(path) => {
+                        debug!("Reloading {}", path.display());
+                        mock_api.evict(&path);
+                    }
+                    DebouncedEvent::Create(path) | DebouncedEvent::Remove(path) => {
+                        debug!("Refreshing the mock fixtures");
+                        mock_api.evict(&path);
+                        mock_api.refresh();
+                    }
+                    DebouncedEvent::Rename(from, to) => {
+                        debug!("Refreshing the mock fixtures");
+                        mock_api.evict(&from);
+                        mock_api.evict(&to);
+                        mock_api.refresh();
+                    }
+                    DebouncedEvent::Rescan => {
+                        debug!("Refreshing the mock fixtures");
+                        mock_api.refresh();
+                    }
+                    DebouncedEvent::Error(err, _) => error!("Watch error. {}", err),
+                    _ => {}
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn resolve_resource(&self, fixtures: &Fixtures, segments: &[&str]) -> Option<Route> {
+        let (last, parents) = segments.split_last()?;
+        let mut dir = self.root_dir.clone();
+        let mut params = BTreeMap::new();
+
+        for segment in parents {
+            let exact = dir.join(segment);
+            if fixtures.is_dir(&exact) {
+                dir = exact;
+            } else {
+                let (param_dir, name) = fixtures.find_param(&dir, true)?;
+                params.insert(name, segment.to_string());
+                dir = param_dir;
+            }
+        }
+
+        let exact = dir.join(format!("{}.json", last));
+        let file = if fixtures.is_file(&exact) {
+            exact
+        } else {
+            let (file, name) = fixtures.find_param(&dir, false)?;
+            params.insert(name, last.to_string());
+            file
+        };
+
+        Some(Route::Resource { file, params })
+    }
+
+    fn resolve(&self, path: &str) -> Option<Route> {
+        let rest = path.strip_prefix(&self.prefix)?;
+        if !rest.is_empty() && !rest.starts_with('/') {
+            return None;
+        }
+
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        if segments.iter().any(|segment| segment.starts_with('.')) {
+            return None;
+        }
+
+        let fixtures = self.fixtures.read().unwrap();
+        if let Some(route) = self.resolve_resource(&fixtures, &segments) {
+            return Some(route);
+        }
+
+        // `/api/users/1` is the item of `/api/users`
+        let (id, parents) = segments.split_last()?;
+        match self.resolve_resource(&fixtures, parents)? {
+            Route::Resource { file, params } => Some(Route::Item {
+                file,
+                params,
+                id: id.to_string(),
+            }),
+            Route::Item { .. } => None,
+        }
+    }
+
+    /// Checks whether the request path matches a fixture.
+    pub fn matches(&self, path: &str) -> bool {
+        self.resolve(path).is_some()
+    }
+
+    fn meta(file: &Path) -> MockMeta {
+        let meta_file = file.with_extension("meta.json");
+        fs::read_to_string(meta_file)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    /// Answers the request, `None` when the path does not match any fixture.
+    pub fn handle(&self, method: &Method, path: &str, body: Option<Value>) -> Option<MockResponse> {
+        let route = self.resolve(path)?;
+        let mut data = self.data.lock().unwrap();
+
+        let (file, params) = match &route {
+            Route::Resource { file, params } | Route::Item { file, params, .. } => {
+                (file.clone(), params.clone())
+            }
+        };
+        // the writes to `/api/orders/1` do not change `/api/orders/2`
+        let key = (file.clone(), params);
+        if !data.contains_key(&key) {
+            let mut value = match fs::read_to_string(&file)
+                .map_err(|err| err.to_string())
+                .and_then(|text| serde_json::from_str(&text).map_err(|err| err.to_string()))
+            {
+                Ok(value) => value,
+                Err(err) => {
+                    return Some(MockResponse::error(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        &format!("Error loading `{}`. {}", file.display(), err),
+                    ))
+                }
+            };
+            substitute_params(&mut value, &key.1);
+            data.insert(key.clone(), value);
+        }
+        let value = data.get_mut(&key).unwrap();
+
+        let response = match route {
+            Route::Resource { .. } => Self::handle_resource(method, value, body),
+            Route::Item { id, .. } => Self::handle_item(method, value, &id, body),
+        };
+
+        if *method == Method::GET && response.status == StatusCode::OK {
+            let meta = Self::meta(&file);
+            return Some(MockResponse {
+                status: meta
+                    .status
+                    .and_then(|status| StatusCode::from_u16(status).ok())
+                    .unwrap_or(response.status),
+                headers: meta.headers,
+                body: response.body,
+            });
+        }
+
+        Some(response)
+    }
+
+    fn handle_resource(method: &Method, value: &mut Value, body: Option<Value>) -> MockResponse {
+        match (method, value, body) {
+            (&Method::GET, value, _) | (&Method::HEAD, value, _) => {
+                MockResponse::new(StatusCode::OK, Some(value.clone()))
+            }
+            (&Method::POST, Value::Array(items), Some(mut item)) => {
+                if let Value::Object(map) = &mut item {
+                    if !map.contains_key("id") {
+                        map.insert(String::from("id"), next_id(items));
+                    }
+                }
+                items.push(item.clone());
+                MockResponse::new(StatusCode::CREATED, Some(item))
+            }
+            (&Method::PUT, value, Some(body)) => {
+                *value = body;
+                MockResponse::new(StatusCode::OK, Some(value.clone()))
+            }
+            (&Method::PATCH, Value::Object(map), Some(Value::Object(changes))) => {
+                map.extend(changes);
+                MockResponse::new(StatusCode::OK, Some(Value::Object(map.clone())))
+            }
+            (&Method::POST, _, None) | (&Method::PUT, _, None) | (&Method::PATCH, _, None) => {
+                MockResponse::error(StatusCode::BAD_REQUEST, "JSON body expected")
+            }
+            _ => MockResponse::error(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed"),
+        }
+    }
+
+    fn handle_item(
+        method: &Method,
+        value: &mut Value,
+        id: &str,
+        body: Option<Value>,
+    ) -> MockResponse {
+        let items = match value {
+            Value::Array(items) => items,
+            _ => return MockResponse::error(StatusCode::NOT_FOUND, "Not found"),
+        };
+        let index = match items.iter().position(|item| id_matches(item, id)) {
+            Some(index) => index,
+            None => return MockResponse::error(StatusCode::NOT_FOUND, "Not found"),
+        };
+
+        match (method, body) {
+            (&Method::GET, _) | (&Method::HEAD, _) => {
+                MockResponse::new(StatusCode::OK, Some(items[index].clone()))
+            }
+            (&Method::PUT, Some(Value::Object(mut map))) => {
+                // the item keeps its id
+                if let Some(id) = items[index].get("id") {
+                    map.insert(String::from("id"), id.clone());
+                }
+                items[index] = Value::Object(map);
+                MockResponse::new(StatusCode::OK, Some(items[index].clone()))
+            }
+            (&Method::PATCH, Some(Value::Object(changes))) => {
+                let mut map = items[index].as_object().cloned().unwrap_or_else(Map::new);
+                let id = map.get("id").cloned();
+                map.extend(changes);
+                if let Some(id) = id {
+                    map.insert(String::from("id"), id);
+                }
+                items[index] = Value::Object(map);
+                MockResponse::new(StatusCode::OK, Some(items[index].clone()))
+            }
+            (&Method::DELETE, _) => {
+                items.remove(index);
+                MockResponse::new(StatusCode::NO_CONTENT, None)
+            }
+            (&Method::PUT, _) | (&Method::PATCH, _) => {
+                MockResponse::error(StatusCode::BAD_REQUEST, "JSON object expected")
+            }
+            _ => MockResponse::error(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed"),
+        }
+    }
+
+    /// Answers the request from the fixtures.
+    pub async fn respond(&self, mut req: ServiceRequest) -> Result<ServiceResponse, Error> {
+        let mut payload = req.take_payload();
+        let mut bytes = BytesMut::new();
+        while let Some(chunk) = payload.next().await {
+            bytes.extend_from_slice(&chunk?);
+        }
+
+        let body = if bytes.is_empty() {
+            None
+        } else {
+            match serde_json::from_slice(&bytes) {
+                Ok(body) => Some(body),
+                Err(err) => {
+                    let response = HttpResponse::BadRequest()
+                        .json(json!({ "error": format!("Invalid JSON. {}", err) }));
+                    return Ok(req.into_response(response));
+                }
+            }
+        };
+
+        let mock = self
+            .handle(req.method(), req.path(), body)
+            .unwrap_or_else(|| MockResponse::error(StatusCode::NOT_FOUND, "Not found"));
+
+        let mut response = HttpResponse::build(mock.status);
+        for (name, value) in &mock.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                response.set_header(name, value);
+            }
+        }
+
+        let response = match mock.body {
+            Some(body) => response.json(body),
+            None => response.finish(),
+        };
+        Ok(req.into_response(response))
+    }
+}
@@ -0,0 +1,379 @@
+//! Network conditions simulation: latency, throughput and failing requests
+
+use actix_web::dev::{
+    Body, BodySize, MessageBody, ResponseBody, Service, ServiceRequest, ServiceResponse, Transform,
+};
+use actix_web::http::StatusCode;
+use actix_web::rt::time::{delay_for, Delay};
+use actix_web::web::Bytes;
+use actix_web::{Error, HttpResponse};
+use anyhow::{anyhow, Result};
+use futures::future::{ok, LocalBoxFuture, Ready};
+use globset::{GlobBuilder, GlobMatcher};
+use log::debug;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::fs::File;
+use std::future::Future;
+use std::io::BufReader;
+use std::path::Path;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Status code of the failed requests when none is configured
+const DEFAULT_FAIL_STATUS: StatusCode = StatusCode::SERVICE_UNAVAILABLE;
+
+/// Prefix of the internal endpoints, never slowed down or failed
+const INTERNAL_PREFIX: &str = "/__rung/";
+
+/// Throttled bodies are sent in slices of this many milliseconds
+const SLICE_MILLIS: u64 = 100;
+
+/// Throughput limit, a preset or kilobytes per second
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Throttle {
+    /// 2000 ms latency, 50 KB/s
+    Slow3g,
+    /// 560 ms latency, 180 KB/s
+    Fast3g,
+    /// Kilobytes (1000 bytes) per second
+    Rate(u64),
+}
+
+impl Throttle {
+    /// Bytes per second
+    pub fn bytes_per_second(&self) -> u64 {
+        match self {
+            Throttle::Slow3g => 50_000,
+            Throttle::Fast3g => 180_000,
+            Throttle::Rate(rate) => rate * 1000,
+        }
+    }
+
+    /// Latency of the preset
+    pub fn latency(&self) -> Option<Duration> {
+        match self {
+            Throttle::Slow3g => Some(Duration::from_millis(2000)),
+            Throttle::Fast3g => Some(Duration::from_millis(560)),
+            Throttle::Rate(_) => None,
+        }
+    }
+}
+
+impl FromStr for Throttle {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "slow-3g" => Ok(Throttle::Slow3g),
+            "fast-3g" => Ok(Throttle::Fast3g),
+            _ => value
+                .parse()
+                .ok()
+                .filter(|rate| *rate > 0)
+                .map(Throttle::Rate)
+                .ok_or_else(|| {
+                    format!(
+                        "`{}` is not `slow-3g`, `fast-3g` or kilobytes per second",
+                        value
+                    )
+                }),
+        }
+    }
+}
+
+/// Network conditions of the requests matching the glob pattern
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkRule {
+    /// Glob pattern of the request path, i.e. `/api/**`.
+    pub source: String,
+    /// Milliseconds to wait before handling the request.
+    pub latency: Option<u64>,
+    /// `slow-3g`, `fast-3g` or kilobytes per second, the presets add their latency.
+    pub throttle: Option<String>,
+    /// Percentage of the requests to fail.
+    pub fail_rate: Option<f64>,
+    /// Status codes of the failed requests, picked at random, `503` by default.
+    #[serde(default)]
+    pub fail_status: Vec<u16>,
+}
+
+/// Network simulation configuration
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct NetworkConfig {
+    /// Later rules override the earlier ones.
+    #[serde(default)]
+    pub rules: Vec<NetworkRule>,
+    /// Seed of the failures, for reproducible runs.
+    pub seed: Option<u64>,
+}
+
+/// Reads the network configuration file.
+pub fn read_network_config(path: &Path) -> Result<NetworkConfig> {
+    let reader = BufReader::new(File::open(path)?);
+    let config: NetworkConfig = serde_json::from_reader(reader)?;
+    Network::new(&config)?;
+    Ok(config)
+}
+
+/// Network conditions of the request
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Conditions {
+    pub latency: Duration,
+    pub bytes_per_second: Option<u64>,
+    /// Percentage of the requests to fail
+    pub fail_rate: f64,
+    pub fail_status: Vec<StatusCode>,
+}
+
+struct CompiledRule {
+    glob: GlobMatcher,
+    latency: Option<Duration>,
+    throttle: Option<Throttle>,
+    fail_rate: Option<f64>,
+    fail_status: Vec<StatusCode>,
+}
+
+/// Compiled network configuration
+pub struct Network {
+    rules: Vec<CompiledRule>,
+    rng: RefCell<StdRng>,
+}
+
+impl Network {
+    pub fn new(config: &NetworkConfig) -> Result<Self> {
+        let mut rules = vec![];
+        for rule in &config.rules {
+            let glob = GlobBuilder::new(&rule.source)
+                .literal_separator(true)
+                .build()?;
+            let throttle = match &rule.throttle {
+                Some(throttle) => Some(throttle.parse().map_err(|err: String| anyhow!(err))?),
+                None => None,
+            };
+            if let Some(rate) = rule.fail_rate.filter(|rate| !(0.0..=100.0).contains(rate)) {
+                return Err(anyhow!("Invalid fail rate `{}`, expected 0 to 100", rate));
+            }
+            let mut fail_status = vec![];
+            for status in &rule.fail_status {
+                fail_status.push(
+                    StatusCode::from_u16(*status)
+                        .map_err(|_| anyhow!("Invalid status code `{}`", status))?,
+                );
+            }
+
+            rules.push(CompiledRule {
+                glob: glob.compile_matcher(),
+                latency: rule.latency.map(Duration::from_millis),
+                throttle,
+                fail_rate: rule.fail_rate,
+                fail_status,
+            });
+        }
+
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        Ok(Network {
+            rules,
+            rng: RefCell::new(rng),
+        })
+    }
+
+    /// Returns the conditions of the request path, `None` when no rule matches.
+    pub fn conditions(&self, path: &str) -> Option<Conditions> {
+        if path.starts_with(INTERNAL_PREFIX) {
+            return None;
+        }
+
+        let mut matched = false;
+        let mut conditions = Conditions::default();
+
+        for rule in self.rules.iter().filter(|rule| rule.glob.is_match(path)) {
+            matched = true;
+            if let Some(throttle) = rule.throttle {
+                conditions.bytes_per_second = Some(throttle.bytes_per_second());
+                if let Some(latency) = throttle.latency() {
+                    conditions.latency = latency;
+                }
+            }
+            if let Some(latency) = rule.latency {
+                conditions.latency = latency;
+            }
+            if let Some(fail_rate) = rule.fail_rate {
+                conditions.fail_rate = fail_rate;
+            }
+            if !rule.fail_status.is_empty() {
+                conditions.fail_status = rule.fail_status.clone();
+            }
+        }
+
+        matched.then(|| conditions)
+    }
+
+    /// Decides whether the request fails, returns the status code of the failure.
+    pub fn failure(&self, conditions: &Conditions) -> Option<StatusCode> {
+        if conditions.fail_rate <= 0.0 {
+            return None;
+        }
+
+        let mut rng = self.rng.borrow_mut();
+        if rng.gen_range(0.0, 100.0) >= conditions.fail_rate {
+            return None;
+        }
+
+        if conditions.fail_status.is_empty() {
+            Some(DEFAULT_FAIL_STATUS)
+        } else {
+            Some(conditions.fail_status[rng.gen_range(0, conditions.fail_status.len())])
+        }
+    }
+}
+
+/// Response body sent at the limited rate
+pub struct Throttled<B> {
+    body: Pin<Box<B>>,
+    bytes_per_second: Option<u64>,
+    /// Rest of the current chunk
+    pending: Bytes,
+    /// Slice sent once the delay elapses
+    slice: Option<(Bytes, Pin<Box<Delay>>)>,
+}
+
+impl<B: MessageBody> Throttled<B> {
+    /// Body passed through unchanged without the rate.
+    pub fn new(body: B, bytes_per_second: Option<u64>) -> Self {
+        Throttled {
+            body: Box::pin(body),
+            bytes_per_second,
+            pending: Bytes::new(),
+            slice: None,
+        }
+    }
+}
+
+impl<B: MessageBody> MessageBody for Throttled<B> {
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Error>>> {
+        let this = self.get_mut();
+        let bytes_per_second = match this.bytes_per_second {
+            Some(bytes_per_second) => bytes_per_second,
+            None => return this.body.as_mut().poll_next(cx),
+        };
+
+        loop {
+            if let Some((_, delay)) = &mut this.slice {
+                if delay.as_mut().poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                let (slice, _) = this.slice.take().unwrap();
+                return Poll::Ready(Some(Ok(slice)));
+            }
+
+            if !this.pending.is_empty() {
+                let slice_size = (bytes_per_second * SLICE_MILLIS / 1000).max(1) as usize;
+                let slice = this.pending.split_to(slice_size.min(this.pending.len()));
+                let millis = slice.len() as u64 * 1000 / bytes_per_second;
+                let delay = Box::pin(delay_for(Duration::from_millis(millis)));
+                this.slice = Some((slice, delay));
+                continue;
+            }
+
+            match this.body.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => this.pending = chunk,
+                other => return other,
+            }
+        }
+    }
+}
+
+/// Middleware simulating the network conditions of the requests
+pub struct Simulation {
+    network: Rc<Network>,
+}
+
+impl Simulation {
+    pub fn new(network: Network) -> Self {
+        Simulation {
+            network: Rc::new(network),
+        }
+    }
+}
+
+impl<S, B> Transform<S> for Simulation
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse;
+    type Error = Error;
+    type InitError = ();
+    type Transform = SimulationMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(SimulationMiddleware {
+            service: Rc::new(RefCell::new(service)),
+            network: self.network.clone(),
+        })
+    }
+}
+
+pub struct SimulationMiddleware<S> {
+    service: Rc<RefCell<S>>,
+    network: Rc<Network>,
+}
+
+impl<S, B> Service for SimulationMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<ServiceResponse, Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let conditions = self.network.conditions(req.path()).unwrap_or_default();
+        let failure = self.network.failure(&conditions);
+        let service = self.service.clone();
+
+        Box::pin(async move {
+            // the request reaches the backend or the mocks once the latency elapses
+            if conditions.latency > Duration::ZERO {
+                delay_for(conditions.latency).await;
+            }
+
+            if let Some(status) = failure {
+                debug!("Simulated {} failure of {}", status.as_u16(), req.path());
+                return Ok(req.into_response(HttpResponse::build(status).finish()));
+            }
+
+            let fut = service.borrow_mut().call(req);
+            let res = fut.await?;
+            Ok(res.map_body(|_, body| {
+                ResponseBody::Body(Body::from_message(Throttled::new(
+                    body,
+                    conditions.bytes_per_second,
+                )))
+            }))
+        })
+    }
+}
@@ -0,0 +1,507 @@
+//! Reverse proxy compatible with the Angular CLI `proxy.conf.json`
+
+use actix::io::{SinkWrite, WriteHandler};
+use actix::{Actor, ActorContext, AsyncContext, StreamHandler};
+use actix_codec::Framed;
+use actix_web::client::{Client, ClientRequest, Connector};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::error::{ErrorBadGateway, ErrorInternalServerError};
+use actix_web::http::{header, HeaderMap, HeaderName, Uri};
+use actix_web::{Error, HttpRequest, HttpResponse};
+use actix_web_actors::ws;
+use anyhow::{anyhow, Result};
+use awc::ws::{Codec, Frame, Message};
+use awc::BoxedSocket;
+use futures::stream::{SplitSink, SplitStream, StreamExt};
+use globset::{Glob, GlobBuilder, GlobMatcher};
+use log::{debug, error};
+use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use super::recording::{read_payload, RecordedRequest, RecordedResponse, Recorder, Recording};
+
+/// Maximum size of the recorded response bodies
+const RECORD_LIMIT: usize = 64 * 1024 * 1024;
+
+fn default_secure() -> bool {
+    true
+}
+
+/// Single proxy configuration entry, see the Angular CLI `proxy.conf.json` format
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyConfig {
+    /// Paths to proxy, plain path prefixes or glob patterns.
+    #[serde(default)]
+    pub context: Vec<String>,
+    /// Backend url.
+    pub target: String,
+    /// Regular expressions to rewrite the request path with, the first matching one is used.
+    #[serde(default)]
+    pub path_rewrite: Map<String, Value>,
+    /// Changes the `Host` header to the target host.
+    #[serde(default)]
+    pub change_origin: bool,
+    /// Verifies the SSL certificate of the target.
+    #[serde(default = "default_secure")]
+    pub secure: bool,
+    /// Proxies websockets.
+    #[serde(default)]
+    pub ws: bool,
+}
+
+/// Reads the proxy configuration in the object (`{ "/api": {...} }`)
+/// or array (`[{ "context": ["/api"], ... }]`) format.
+pub fn read_proxy_config(path: &Path) -> Result<Vec<ProxyConfig>> {
+    let reader = BufReader::new(File::open(path)?);
+    let value: Value = serde_json::from_reader(reader)?;
+
+    let configs = match value {
+        Value::Array(_) => serde_json::from_value(value)?,
+        Value::Object(map) => {
+            let mut configs = vec![];
+            for (context, value) in map {
+                let mut config: ProxyConfig = serde_json::from_value(value)?;
+                config.context = vec![context];
+                configs.push(config);
+            }
+            configs
+        }
+        _ => return Err(anyhow!("Invalid proxy configuration: {}", path.display())),
+    };
+
+    for config in &configs {
+        ProxyRule::new(config)?;
+    }
+
+    Ok(configs)
+}
+
+enum Context {
+    Prefix(String),
+    Glob(GlobMatcher),
+}
+
+/// Compiled proxy rule
+pub struct ProxyRule {
+    contexts: Vec<Context>,
+    target: String,
+    rewrites: Vec<(Regex, String)>,
+    change_origin: bool,
+    secure: bool,
+    ws: bool,
+}
+
+impl ProxyRule {
+    pub fn new(config: &ProxyConfig) -> Result<Self> {
+        let mut contexts = vec![];
+        for context in &config.context {
+            if context.contains('*') || context.contains('?') {
+                let glob = GlobBuilder::new(context).literal_separator(true).build()?;
+                contexts.push(Context::Glob(glob.compile_matcher()));
+            } else {
+                contexts.push(Context::Prefix(context.to_string()));
+            }
+        }
+
+        // `**` matches everything, including the paths without a leading slash segment
+        if config.context.iter().any(|context| context == "**") {
+            contexts.push(Context::Glob(Glob::new("**")?.compile_matcher()));
+        }
+
+        // validate the url early
+        config.target.parse::<Uri>()?;
+
+        let mut rewrites = vec![];
+        for (pattern, replacement) in &config.path_rewrite {
+            let replacement = replacement.as_str().unwrap_or_default().to_string();
+            rewrites.push((Regex::new(pattern)?, replacement));
+        }
+
+        Ok(ProxyRule {
+            contexts,
+            target: config.target.trim_end_matches('/').to_string(),
+            rewrites,
+            change_origin: config.change_origin,
+            secure: config.secure,
+            ws: config.ws,
+        })
+    }
+
+    /// Checks whether the request path should be proxied.
+    pub fn matches(&self, path: &str) -> bool {
+        self.contexts.iter().any(|context| match context {
+            Context::Prefix(prefix) => path.starts_with(prefix.as_str()),
+            Context::Glob(glob) => glob.is_match(path),
+        })
+    }
+
+    /// Rewrites the request path and query to the target url.
+    pub fn target_url(&self, path_and_query: &str) -> String {
+        let mut path = path_and_query.to_string();
+
+        for (regex, replacement) in &self.rewrites {
+            if regex.is_match(&path) {
+                path = regex.replace(&path, replacement.as_str()).to_string();
+                break;
+            }
+        }
+
+        if !path.starts_with('/') {
+            path.insert(0, '/');
+        }
+
+        format!("{}{}", self.target, path)
+    }
+
+    fn target_authority(&self) -> String {
+        self.target
+            .parse::<Uri>()
+            .ok()
+            .and_then(|uri| uri.authority().map(|authority| authority.to_string()))
+            .unwrap_or_default()
+    }
+
+    fn ws_url(&self, path_and_query: &str) -> String {
+        let url = self.target_url(path_and_query);
+        if let Some(rest) = url.strip_prefix("https://") {
+            format!("wss://{}", rest)
+        } else if let Some(rest) = url.strip_prefix("http://") {
+            format!("ws://{}", rest)
+        } else {
+            url
+        }
+    }
+}
+
+/// Headers that are specific to a single connection and must not be forwarded
+fn is_hop_by_hop(name: &HeaderName) -> bool {
+    [
+        header::CONNECTION,
+        header::TE,
+        header::TRAILER,
+        header::TRANSFER_ENCODING,
+        header::UPGRADE,
+        header::PROXY_AUTHORIZATION,
+        header::PROXY_AUTHENTICATE,
+    ]
+    .contains(name)
+        || name == "keep-alive"
+}
+
+/// Returns the backend response headers to send to the client.
+fn response_headers(headers: &HeaderMap) -> HeaderMap {
+    let mut forwarded = HeaderMap::new();
+    for (name, value) in headers.iter() {
+        if !is_hop_by_hop(name) && name != header::CONTENT_LENGTH {
+            forwarded.append(name.clone(), value.clone());
+        }
+    }
+    forwarded
+}
+
+fn is_websocket_upgrade(req: &HttpRequest) -> bool {
+    req.headers()
+        .get(header::UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.eq_ignore_ascii_case("websocket"))
+        .unwrap_or(false)
+}
+
+fn ssl_connector(secure: bool) -> Result<SslConnector> {
+    let mut ssl = SslConnector::builder(SslMethod::tls())?;
+    if !secure {
+        ssl.set_verify(SslVerifyMode::NONE);
+    }
+    Ok(ssl.build())
+}
+
+fn create_client(secure: bool) -> Result<Client> {
+    Ok(Client::builder()
+        .connector(Connector::new().ssl(ssl_connector(secure)?).finish())
+        .disable_redirects()
+        .disable_timeout()
+        .finish())
+}
+
+/// Checks the rules and the SSL setup before the server workers create their proxies.
+pub fn check_proxy(configs: &[ProxyConfig]) -> Result<()> {
+    for config in configs {
+        ProxyRule::new(config)?;
+    }
+    ssl_connector(true)?;
+    ssl_connector(false)?;
+    Ok(())
+}
+
+/// Reverse proxy, created for every server worker
+pub struct Proxy {
+    rules: Vec<ProxyRule>,
+    client: Client,
+    insecure_client: Client,
+    recorder: Option<Recorder>,
+}
+
+impl Proxy {
+    pub fn new(configs: &[ProxyConfig]) -> Result<Self> {
+        let rules = configs
+            .iter()
+            .map(ProxyRule::new)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Proxy {
+            rules,
+            client: create_client(true)?,
+            insecure_client: create_client(false)?,
+            recorder: None,
+        })
+    }
+
+    /// Saves the proxied requests with the recorder.
+    pub fn record(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Finds the rule matching the request path.
+    pub fn find(&self, path: &str) -> Option<usize> {
+        self.rules.iter().position(|rule| rule.matches(path))
+    }
+
+    fn client(&self, rule: &ProxyRule) -> &Client {
+        if rule.secure {
+            &self.client
+        } else {
+            &self.insecure_client
+        }
+    }
+
+    /// Forwards the request to the backend of the rule.
+    pub async fn forward(
+        &self,
+        index: usize,
+        req: ServiceRequest,
+    ) -> Result<ServiceResponse, Error> {
+        let rule = &self.rules[index];
+        let (req, payload) = req.into_parts();
+
+        let path_and_query = req
+            .uri()
+            .path_and_query()
+            .map(|value| value.as_str())
+            .unwrap_or("/");
+
+        if rule.ws && is_websocket_upgrade(&req) {
+            let response = self.forward_ws(rule, &req, payload, path_and_query).await?;
+            return Ok(ServiceResponse::new(req, response));
+        }
+
+        let url = rule.target_url(path_and_query);
+        debug!("Proxy {} {} -> {}", req.method(), req.path(), url);
+
+        let mut forwarded = self
+            .client(rule)
+            .request(req.method().clone(), url.as_str());
+        for (name, value) in req.headers().iter() {
+            if !is_hop_by_hop(name) {
+                forwarded = forwarded.header(name.clone(), value.clone());
+            }
+        }
+        if rule.change_origin {
+            forwarded = forwarded.set_header(header::HOST, rule.target_authority());
+        }
+        if let Some(addr) = req.peer_addr() {
+            forwarded = forwarded.header("x-forwarded-for", addr.ip().to_string());
+        }
+
+        if let Some(recorder) = &self.recorder {
+            let response = self
+                .forward_recorded(recorder, forwarded, &req, payload, &url)
+                .await?;
+            return Ok(ServiceResponse::new(req, response));
+        }
+
+        let backend_response = forwarded
+            .no_decompress()
+            .send_stream(payload)
+            .await
+            .map_err(|err| {
+                error!("Proxy error {}: {}", url, err);
+                ErrorBadGateway(err)
+            })?;
+
+        let mut response = HttpResponse::build(backend_response.status());
+        for (name, value) in response_headers(backend_response.headers()).iter() {
+            response.header(name.clone(), value.clone());
+        }
+
+        Ok(ServiceResponse::new(
+            req,
+            response.streaming(backend_response),
+        ))
+    }
+
+    /// Forwards the request with the whole bodies and saves them.
+    async fn forward_recorded(
+        &self,
+        recorder: &Recorder,
+        forwarded: ClientRequest,
+        req: &HttpRequest,
+        mut payload: Payload,
+        url: &str,
+    ) -> Result<HttpResponse, Error> {
+        let body = read_payload(&mut payload).await?;
+
+        let mut backend_response = forwarded
+            .no_decompress()
+            .send_body(body.clone())
+            .await
+            .map_err(|err| {
+                error!("Proxy error {}: {}", url, err);
+                ErrorBadGateway(err)
+            })?;
+        let response_body = backend_response
+            .body()
+            .limit(RECORD_LIMIT)
+            .await
+            .map_err(|err| {
+                error!("Proxy error {}: {}", url, err);
+                ErrorBadGateway(err)
+            })?;
+        let headers = response_headers(backend_response.headers());
+
+        let recording = Recording {
+            request: RecordedRequest::new(req.method(), req.path(), req.query_string(), &body),
+            response: RecordedResponse::new(backend_response.status(), &headers, &response_body),
+        };
+        match recorder.save(&recording) {
+            Ok(path) => debug!(
+                "Recorded {} {} to {}",
+                req.method(),
+                req.uri(),
+                path.display()
+            ),
+            Err(err) => error!("Error recording {} {}. {}", req.method(), req.uri(), err),
+        }
+
+        let mut response = HttpResponse::build(backend_response.status());
+        for (name, value) in headers.iter() {
+            response.header(name.clone(), value.clone());
+        }
+        Ok(response.body(response_body))
+    }
+
+    async fn forward_ws(
+        &self,
+        rule: &ProxyRule,
+        req: &HttpRequest,
+        payload: Payload,
+        path_and_query: &str,
+    ) -> Result<HttpResponse, Error> {
+        let url = rule.ws_url(path_and_query);
+        debug!("Proxy websocket {} -> {}", req.path(), url);
+
+        let mut request = self.client(rule).ws(url.as_str());
+        for (name, value) in req.headers().iter() {
+            let is_handshake = name.as_str().starts_with("sec-websocket-")
+                && name != header::SEC_WEBSOCKET_PROTOCOL;
+            if !is_hop_by_hop(name) && !is_handshake && name != header::HOST {
+                request = request.header(name.clone(), value.clone());
+            }
+        }
+        if !rule.change_origin {
+            if let Some(host) = req.headers().get(header::HOST) {
+                request = request.header(header::HOST, host.clone());
+            }
+        }
+
+        let (_, socket) = request.connect().await.map_err(|err| {
+            error!("Proxy websocket error {}: {}", url, err);
+            ErrorBadGateway(err.to_string())
+        })?;
+
+        let (sink, stream) = socket.split();
+        ws::start(
+            WebsocketProxy {
+                sink: None,
+                backend: Some((sink, stream)),
+            },
+            req,
+            payload,
+        )
+        .map_err(ErrorInternalServerError)
+    }
+}
+
+type BackendSink = SplitSink<Framed<BoxedSocket, Codec>, Message>;
+type BackendStream = SplitStream<Framed<BoxedSocket, Codec>>;
+
+/// Forwards websocket messages between the client and the backend
+struct WebsocketProxy {
+    sink: Option<SinkWrite<Message, BackendSink>>,
+    backend: Option<(BackendSink, BackendStream)>,
+}
+
+impl Actor for WebsocketProxy {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        if let Some((sink, stream)) = self.backend.take() {
+            ctx.add_stream(stream);
+            self.sink = Some(SinkWrite::new(sink, ctx));
+        }
+    }
+}
+
+/// Messages from the client
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WebsocketProxy {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        match (msg, self.sink.as_mut()) {
+            (Ok(msg), Some(sink)) => {
+                let is_close = matches!(msg, Message::Close(_));
+                let _ = sink.write(msg);
+                if is_close {
+                    ctx.stop();
+                }
+            }
+            _ => ctx.stop(),
+        }
+    }
+}
+
+/// Messages from the backend
+impl StreamHandler<Result<Frame, ws::ProtocolError>> for WebsocketProxy {
+    fn handle(&mut self, frame: Result<Frame, ws::ProtocolError>, ctx: &mut Self::Context) {
+        match frame {
+            Ok(Frame::Text(text)) => match String::from_utf8(text.to_vec()) {
+                Ok(text) => ctx.text(text),
+                Err(_) => ctx.binary(text),
+            },
+            Ok(Frame::Binary(data)) => ctx.binary(data),
+            Ok(Frame::Continuation(item)) => ctx.write_raw(Message::Continuation(item)),
+            // the backend pings are answered by the proxy, not by the client
+            Ok(Frame::Ping(data)) => {
+                if let Some(sink) = self.sink.as_mut() {
+                    let _ = sink.write(Message::Pong(data));
+                }
+            }
+            Ok(Frame::Pong(data)) => ctx.pong(&data),
+            Ok(Frame::Close(reason)) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            Err(_) => ctx.stop(),
+        }
+    }
+
+    fn finished(&mut self, ctx: &mut Self::Context) {
+        ctx.stop();
+    }
+}
+
+impl WriteHandler<ws::ProtocolError> for WebsocketProxy {}
@@ -0,0 +1,322 @@
+//! Record and replay of the proxied requests
+//!
+//! Every proxied request is saved with its response to a JSON file in the recordings directory.
+//! The replay answers the requests from the recordings, without a backend.
+
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use actix_web::web::{Bytes, BytesMut};
+use actix_web::{Error, HttpMessage, HttpResponse};
+use anyhow::{anyhow, Result};
+use futures::stream::StreamExt;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha1::{Digest, Sha1};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Maximum length of the path part of the recording file names
+const MAX_SLUG_LENGTH: usize = 60;
+
+/// Recorded request, with the hash of its body
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    #[serde(default)]
+    pub query: String,
+    /// SHA-1 of the body, `None` for the requests without a body.
+    pub body_hash: Option<String>,
+}
+
+impl RecordedRequest {
+    pub fn new(method: &Method, path: &str, query: &str, body: &[u8]) -> Self {
+        RecordedRequest {
+            method: method.to_string(),
+            path: path.to_string(),
+            query: query.to_string(),
+            body_hash: if body.is_empty() {
+                None
+            } else {
+                Some(format!("{:x}", Sha1::digest(body)))
+            },
+        }
+    }
+}
+
+/// Recorded response, text bodies are saved as is, the other ones in base64
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_base64: Option<String>,
+}
+
+impl RecordedResponse {
+    pub fn new(status: StatusCode, headers: &HeaderMap, body: &[u8]) -> Self {
+        let is_encoded = headers.contains_key("content-encoding");
+        let text = if is_encoded {
+            None
+        } else {
+            std::str::from_utf8(body).ok()
+        };
+
+        RecordedResponse {
+            status: status.as_u16(),
+            headers: headers
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect(),
+            body: text.map(String::from),
+            body_base64: match text {
+                Some(_) => None,
+                None if body.is_empty() => None,
+                None => Some(base64::encode(body)),
+            },
+        }
+    }
+
+    /// Returns the body bytes.
+    pub fn body(&self) -> Result<Bytes> {
+        match (&self.body, &self.body_base64) {
+            (Some(text), _) => Ok(Bytes::from(text.clone())),
+            (None, Some(encoded)) => Ok(Bytes::from(base64::decode(encoded)?)),
+            (None, None) => Ok(Bytes::new()),
+        }
+    }
+}
+
+/// Request and response pair
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Recording {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+impl Recording {
+    /// File name of the recording, the same request is saved to the same file.
+    pub fn file_name(&self) -> String {
+        let request = &self.request;
+        let mut slug: String = request
+            .path
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        slug = slug
+            .trim_matches('_')
+            .chars()
+            .take(MAX_SLUG_LENGTH)
+            .collect();
+
+        let key = format!(
+            "{}\n{}\n{}\n{}",
+            request.method,
+            request.path,
+            request.query,
+            request.body_hash.as_deref().unwrap_or_default()
+        );
+        let hash = format!("{:x}", Sha1::digest(key.as_bytes()));
+
+        format!("{}-{}-{}.json", request.method, slug, &hash[..10])
+    }
+}
+
+/// Request fields a recording has to match
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MatchOptions {
+    pub method: bool,
+    pub path: bool,
+    pub query: bool,
+    pub body: bool,
+}
+
+impl Default for MatchOptions {
+    fn default() -> Self {
+        MatchOptions {
+            method: true,
+            path: true,
+            query: true,
+            body: false,
+        }
+    }
+}
+
+impl FromStr for MatchOptions {
+    type Err = String;
+
+    /// Parses the comma-separated `method`, `path`, `query` and `body` fields.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut options = MatchOptions {
+            method: false,
+            path: false,
+            query: false,
+            body: false,
+        };
+
+        for field in value.split(',').map(str::trim) {
+            match field {
+                "method" => options.method = true,
+                "path" => options.path = true,
+                "query" => options.query = true,
+                "body" => options.body = true,
+                _ => {
+                    return Err(format!(
+                        "Unknown field `{}`, expected `method`, `path`, `query` or `body`",
+                        field
+                    ))
+                }
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+impl MatchOptions {
+    pub fn matches(&self, recorded: &RecordedRequest, request: &RecordedRequest) -> bool {
+        (!self.method || recorded.method == request.method)
+            && (!self.path || recorded.path == request.path)
+            && (!self.query || recorded.query == request.query)
+            && (!self.body || recorded.body_hash == request.body_hash)
+    }
+}
+
+/// Reads the whole request body.
+pub async fn read_payload(payload: &mut Payload) -> Result<Bytes, Error> {
+    let mut bytes = BytesMut::new();
+    while let Some(chunk) = payload.next().await {
+        bytes.extend_from_slice(&chunk?);
+    }
+    Ok(bytes.freeze())
+}
+
+/// Saves the proxied requests to the directory
+#[derive(Clone, Debug)]
+pub struct Recorder {
+    dir: PathBuf,
+}
+
+impl Recorder {
+    pub fn new(dir: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Recorder { dir })
+    }
+
+    /// Saves the recording, replacing the previous recording of the same request.
+    pub fn save(&self, recording: &Recording) -> Result<PathBuf> {
+        let path = self.dir.join(recording.file_name());
+        let writer = BufWriter::new(File::create(&path)?);
+        serde_json::to_writer_pretty(writer, recording)?;
+        Ok(path)
+    }
+}
+
+/// Replay options
+#[derive(Clone, Debug)]
+pub struct ReplayOptions {
+    /// Directory with the recordings
+    pub dir: PathBuf,
+    pub matching: MatchOptions,
+}
+
+/// Answers the requests from the recordings
+#[derive(Clone)]
+pub struct Replay {
+    recordings: Arc<Vec<Recording>>,
+    matching: MatchOptions,
+}
+
+impl Replay {
+    /// Reads all recordings of the directory.
+    pub fn load(options: &ReplayOptions) -> Result<Self> {
+        let mut files: Vec<PathBuf> = fs::read_dir(&options.dir)
+            .map_err(|err| anyhow!("Error reading `{}`. {}", options.dir.display(), err))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().map_or(false, |ext| ext == "json"))
+            .collect();
+        files.sort();
+
+        let mut recordings = vec![];
+        for file in files {
+            recordings.push(
+                read_recording(&file)
+                    .map_err(|err| anyhow!("Error loading `{}`. {}", file.display(), err))?,
+            );
+        }
+        info!(
+            "Replaying {} recording(s) from {}",
+            recordings.len(),
+            options.dir.display()
+        );
+
+        Ok(Replay {
+            recordings: Arc::new(recordings),
+            matching: options.matching,
+        })
+    }
+
+    /// Checks whether a recording has the request path.
+    pub fn has_path(&self, path: &str) -> bool {
+        self.recordings
+            .iter()
+            .any(|recording| recording.request.path == path)
+    }
+
+    /// Finds the recording matching the request.
+    pub fn find(&self, request: &RecordedRequest) -> Option<&Recording> {
+        self.recordings
+            .iter()
+            .find(|recording| self.matching.matches(&recording.request, request))
+    }
+
+    /// Answers the request from the recordings, with 404 status when none matches.
+    pub async fn respond(&self, mut req: ServiceRequest) -> Result<ServiceResponse, Error> {
+        let body = read_payload(&mut req.take_payload()).await?;
+        let request = RecordedRequest::new(req.method(), req.path(), req.query_string(), &body);
+
+        let recording = match self.find(&request) {
+            Some(recording) => recording,
+            None => {
+                warn!("No recording of {} {}", req.method(), req.uri());
+                let response = HttpResponse::NotFound().json(json!({ "error": "No recording" }));
+                return Ok(req.into_response(response));
+            }
+        };
+
+        let recorded = &recording.response;
+        let status = StatusCode::from_u16(recorded.status)
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        let mut response = HttpResponse::build(status);
+        for (name, value) in &recorded.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                response.header(name, value);
+            }
+        }
+        let body = recorded
+            .body()
+            .map_err(|err| actix_web::error::ErrorInternalServerError(err.to_string()))?;
+
+        Ok(req.into_response(response.body(body)))
+    }
+}
+
+fn read_recording(path: &Path) -> Result<Recording> {
+    let reader = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(reader)?)
+}
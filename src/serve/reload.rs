@@ -0,0 +1,214 @@
+//! Live reload of the served pages when the files change
+
+use actix::{Actor, ActorContext, AsyncContext, Handler, Message, Recipient, StreamHandler};
+use actix_web::dev::{Body, ResponseBody, ServiceResponse};
+use actix_web::http::{header, Method, StatusCode};
+use actix_web::web::{self, BytesMut};
+use actix_web::{Error, HttpRequest, HttpResponse};
+use actix_web_actors::ws;
+use futures::stream::StreamExt;
+use log::{debug, error, info};
+use notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
+use once_cell::sync::Lazy;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Websocket endpoint the reload client connects to
+pub const SOCKET_PATH: &str = "/__rung/livereload";
+
+/// Reload client injected into the pages, `{socket_path}` is replaced with the endpoint
+const CLIENT_TEMPLATE: &str = r#"<script>
+(function () {
+  var protocol = location.protocol === 'https:' ? 'wss://' : 'ws://';
+  var socket = new WebSocket(protocol + location.host + '{socket_path}');
+  socket.onmessage = function (event) {
+    if (event.data !== 'css') {
+      location.reload();
+      return;
+    }
+    document.querySelectorAll('link[rel="stylesheet"]').forEach(function (link) {
+      var url = new URL(link.href);
+      url.searchParams.set('_rung', Date.now());
+      link.href = url.href;
+    });
+  };
+})();
+</script>
+"#;
+
+static CLIENT_SCRIPT: Lazy<String> =
+    Lazy::new(|| CLIENT_TEMPLATE.replace("{socket_path}", SOCKET_PATH));
+
+/// Notification sent to the connected pages
+#[derive(Clone, Copy, Debug, PartialEq, Message)]
+#[rtype(result = "()")]
+pub enum Reload {
+    /// Reloads the whole page
+    Page,
+    /// Refreshes the stylesheets only
+    Css,
+}
+
+impl Reload {
+    /// Stylesheet-only changes are applied without reloading the page.
+    pub fn for_changes(paths: &[PathBuf]) -> Reload {
+        let css_only = !paths.is_empty()
+            && paths
+                .iter()
+                .all(|path| path.extension().map_or(false, |ext| ext == "css"));
+
+        if css_only {
+            Reload::Css
+        } else {
+            Reload::Page
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Reload::Page => "reload",
+            Reload::Css => "css",
+        }
+    }
+}
+
+/// Inserts the reload client before the closing `</body>` tag, or appends it.
+pub fn inject_client(html: &str) -> String {
+    match html.rfind("</body>") {
+        Some(index) => format!("{}{}{}", &html[..index], *CLIENT_SCRIPT, &html[index..]),
+        None => format!("{}{}", html, *CLIENT_SCRIPT),
+    }
+}
+
+/// Connected pages
+#[derive(Clone, Default)]
+pub struct LiveReload {
+    clients: Arc<Mutex<Vec<Recipient<Reload>>>>,
+}
+
+impl LiveReload {
+    fn add(&self, client: Recipient<Reload>) {
+        self.clients.lock().unwrap().push(client);
+    }
+
+    /// Notifies all connected pages, dropping the closed connections.
+    pub fn broadcast(&self, reload: Reload) {
+        let mut clients = self.clients.lock().unwrap();
+        clients.retain(|client| client.do_send(reload).is_ok());
+        debug!("Sent {:?} to {} page(s)", reload, clients.len());
+    }
+
+    /// Watches the directory in a background thread and notifies the pages about the changes.
+    pub fn watch(&self, root_dir: &Path) -> notify::Result<()> {
+        let (tx, rx) = channel();
+        let mut watcher = watcher(tx, Duration::from_millis(200))?;
+        watcher.watch(root_dir, RecursiveMode::Recursive)?;
+        info!("Watching {}", root_dir.display());
+
+        let live_reload = self.clone();
+        thread::spawn(move || {
+            // the watcher stops when dropped
+            let _watcher = watcher;
+
+            while let Ok(event) = rx.recv() {
+                let mut paths = vec![];
+                let mut next = Some(event);
+
+                // changes of a rebuild arrive in a burst, notify once for all of them
+                while let Some(event) = next {
+                    match event {
+                        DebouncedEvent::Create(path)
+                        | DebouncedEvent::Write(path)
+                        | DebouncedEvent::Remove(path)
+                        | DebouncedEvent::Rename(_, path) => paths.push(path),
+                        DebouncedEvent::Rescan => paths.push(PathBuf::new()),
+                        DebouncedEvent::Error(err, _) => error!("Watch error. {}", err),
+                        _ => {}
+                    }
+                    next = rx.recv_timeout(Duration::from_millis(50)).ok();
+                }
+
+                if !paths.is_empty() {
+                    live_reload.broadcast(Reload::for_changes(&paths));
+                }
+            }
+        });
+
+        Ok(())
+    }
+}
+
+struct ReloadSocket {
+    live_reload: LiveReload,
+}
+
+impl Actor for ReloadSocket {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.live_reload.add(ctx.address().recipient());
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for ReloadSocket {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        match msg {
+            Ok(ws::Message::Ping(msg)) => ctx.pong(&msg),
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            Err(_) => ctx.stop(),
+            _ => {}
+        }
+    }
+}
+
+impl Handler<Reload> for ReloadSocket {
+    type Result = ();
+
+    fn handle(&mut self, reload: Reload, ctx: &mut Self::Context) {
+        ctx.text(reload.as_str());
+    }
+}
+
+/// Websocket handler of the reload client
+pub async fn socket(
+    req: HttpRequest,
+    stream: web::Payload,
+    live_reload: web::Data<LiveReload>,
+) -> Result<HttpResponse, Error> {
+    let live_reload = live_reload.get_ref().clone();
+    ws::start(ReloadSocket { live_reload }, &req, stream)
+}
+
+/// Injects the reload client into the HTML pages.
+pub async fn inject_response(mut res: ServiceResponse) -> Result<ServiceResponse, Error> {
+    let is_html = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.starts_with("text/html"))
+        .unwrap_or(false);
+    let is_encoded = res.headers().contains_key(header::CONTENT_ENCODING);
+    let has_page = !is_encoded
+        && matches!(res.status(), StatusCode::OK | StatusCode::NOT_FOUND)
+        && res.request().method() != Method::HEAD;
+
+    if !is_html || !has_page {
+        return Ok(res);
+    }
+
+    let mut body = BytesMut::new();
+    let mut stream = res.take_body();
+    while let Some(chunk) = stream.next().await {
+        body.extend_from_slice(&chunk?);
+    }
+
+    let html = inject_client(&String::from_utf8_lossy(&body));
+    res.headers_mut().remove(header::CONTENT_LENGTH);
+    Ok(res.map_body(|_, _| ResponseBody::Body(Body::from(html))))
+}
@@ -0,0 +1,171 @@
+//! HTTPS support with self-signed certificates
+
+use log::info;
+use openssl::asn1::Asn1Time;
+use openssl::bn::{BigNum, MsbOption};
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::rsa::Rsa;
+use openssl::ssl::{SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod};
+use openssl::x509::extension::{
+    BasicConstraints, ExtendedKeyUsage, KeyUsage, SubjectAlternativeName,
+};
+use openssl::x509::{X509NameBuilder, X509};
+use std::fs;
+use std::io::{self, Write};
+use std::net::IpAddr;
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+/// Days the generated certificates are valid for
+const VALIDITY_DAYS: u32 = 365;
+
+#[derive(Clone, Debug, Default)]
+pub struct SslOptions {
+    /// Certificate chain file in PEM format, generated when not set.
+    pub cert: Option<PathBuf>,
+    /// Private key file in PEM format, generated when not set.
+    pub key: Option<PathBuf>,
+}
+
+/// Generates a self-signed certificate for `localhost` and the host.
+pub fn generate_certificate(host: &str) -> io::Result<(X509, PKey<Private>)> {
+    let key = PKey::from_rsa(Rsa::generate(2048)?)?;
+
+    let mut name = X509NameBuilder::new()?;
+    name.append_entry_by_text("CN", "localhost")?;
+    name.append_entry_by_text("O", "rung")?;
+    let name = name.build();
+
+    let mut serial = BigNum::new()?;
+    serial.rand(128, MsbOption::MAYBE_ZERO, false)?;
+
+    let serial = serial.to_asn1_integer()?;
+    let not_before = Asn1Time::days_from_now(0)?;
+    let not_after = Asn1Time::days_from_now(VALIDITY_DAYS)?;
+
+    let mut builder = X509::builder()?;
+    builder.set_version(2)?;
+    builder.set_serial_number(&serial)?;
+    builder.set_subject_name(&name)?;
+    builder.set_issuer_name(&name)?;
+    builder.set_pubkey(&key)?;
+    builder.set_not_before(&not_before)?;
+    builder.set_not_after(&not_after)?;
+
+    builder.append_extension(BasicConstraints::new().critical().build()?)?;
+    builder.append_extension(
+        KeyUsage::new()
+            .critical()
+            .digital_signature()
+            .key_encipherment()
+            .build()?,
+    )?;
+    builder.append_extension(ExtendedKeyUsage::new().server_auth().build()?)?;
+
+    let mut alt_names = SubjectAlternativeName::new();
+    alt_names.dns("localhost").ip("127.0.0.1").ip("::1");
+    if host != "localhost" {
+        match host.parse::<IpAddr>() {
+            Ok(ip) if !ip.is_unspecified() && !ip.is_loopback() => {
+                alt_names.ip(host);
+            }
+            Ok(_) => {}
+            Err(_) => {
+                alt_names.dns(host);
+            }
+        }
+    }
+    let alt_names = alt_names.build(&builder.x509v3_context(None, None))?;
+    builder.append_extension(alt_names)?;
+
+    builder.sign(&key, MessageDigest::sha256())?;
+
+    Ok((builder.build(), key))
+}
+
+fn cache_dir(host: &str) -> PathBuf {
+    let name: String = host
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    dirs_next::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("rung")
+        .join("ssl")
+        .join(name)
+}
+
+fn is_valid(cert_path: &Path) -> bool {
+    let cert = match fs::read(cert_path)
+        .ok()
+        .and_then(|pem| X509::from_pem(&pem).ok())
+    {
+        Some(cert) => cert,
+        None => return false,
+    };
+
+    match Asn1Time::days_from_now(1) {
+        Ok(tomorrow) => cert.not_after() > tomorrow,
+        Err(_) => false,
+    }
+}
+
+/// Writes the private key readable by the owner only.
+fn write_private_key(path: &Path, pem: &[u8]) -> io::Result<()> {
+    // a stale key keeps its permissions when overwritten
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+        _ => {}
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    options.open(path)?.write_all(pem)
+}
+
+/// Returns the cached self-signed certificate and key files for the host,
+/// a new certificate is generated when missing or about to expire.
+pub fn cached_certificate(host: &str) -> io::Result<(PathBuf, PathBuf)> {
+    let dir = cache_dir(host);
+    let cert_path = dir.join("cert.pem");
+    let key_path = dir.join("key.pem");
+
+    if !(is_valid(&cert_path) && key_path.exists()) {
+        info!("Generating self-signed certificate for {}", host);
+
+        let (cert, key) = generate_certificate(host)?;
+        fs::create_dir_all(&dir)?;
+        fs::write(&cert_path, cert.to_pem()?)?;
+        write_private_key(&key_path, &key.private_key_to_pem_pkcs8()?)?;
+    }
+
+    info!("Using certificate {}", cert_path.display());
+    Ok((cert_path, key_path))
+}
+
+/// Creates the TLS acceptor with the configured or the self-signed certificate.
+pub fn create_acceptor(options: &SslOptions, host: &str) -> io::Result<SslAcceptorBuilder> {
+    let (cert_path, key_path) = match (&options.cert, &options.key) {
+        (Some(cert), Some(key)) => (cert.clone(), key.clone()),
+        _ => cached_certificate(host)?,
+    };
+
+    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls())?;
+    builder.set_private_key_file(&key_path, SslFiletype::PEM)?;
+    builder.set_certificate_chain_file(&cert_path)?;
+    builder.check_private_key()?;
+
+    Ok(builder)
+}
@@ -0,0 +1,84 @@
+//! Runtime configuration, the template files are rendered from the environment variables
+//! on every request, the files on disk stay untouched.
+
+use crate::template::{self, Rendered};
+use actix_web::http::{header, StatusCode};
+use actix_web::HttpResponse;
+use anyhow::Result;
+use globset::{GlobBuilder, GlobMatcher};
+use log::{error, warn};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::assets::NO_CACHE;
+
+/// Template files and the variables to render them with
+#[derive(Clone, Debug, Default)]
+pub struct TemplateOptions {
+    /// Glob patterns of the file paths relative to the served directory, i.e. `assets/config.json`.
+    pub patterns: Vec<String>,
+    pub vars: BTreeMap<String, String>,
+}
+
+/// Compiled template options
+#[derive(Clone)]
+pub struct Templates {
+    globs: Vec<GlobMatcher>,
+    vars: Arc<BTreeMap<String, String>>,
+}
+
+impl Templates {
+    pub fn new(options: &TemplateOptions) -> Result<Self> {
+        let mut globs = vec![];
+        for pattern in &options.patterns {
+            let glob = GlobBuilder::new(pattern.trim_start_matches('/'))
+                .literal_separator(true)
+                .build()?;
+            globs.push(glob.compile_matcher());
+        }
+
+        Ok(Templates {
+            globs,
+            vars: Arc::new(options.vars.clone()),
+        })
+    }
+
+    /// Checks whether the file of the served directory is a template.
+    pub fn matches(&self, root_dir: &Path, file: &Path) -> bool {
+        let relative = match file.strip_prefix(root_dir) {
+            Ok(relative) => relative,
+            Err(_) => return false,
+        };
+        let relative: Vec<_> = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect();
+        let relative = relative.join("/");
+
+        self.globs.iter().any(|glob| glob.is_match(&relative))
+    }
+
+    /// Responds with the rendered template.
+    pub fn respond(&self, file: &Path, status: StatusCode) -> HttpResponse {
+        match template::render_file(file, &self.vars) {
+            Ok(Rendered { text, missing }) => {
+                if !missing.is_empty() {
+                    warn!(
+                        "Rendered `{}` without the variables {}",
+                        file.display(),
+                        missing.join(", ")
+                    );
+                }
+                HttpResponse::build(status)
+                    .content_type(mime_guess::from_path(file).first_or_octet_stream().as_ref())
+                    .set_header(header::CACHE_CONTROL, NO_CACHE)
+                    .body(text)
+            }
+            Err(err) => {
+                error!("Error rendering `{}`. {}", file.display(), err);
+                HttpResponse::InternalServerError().finish()
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Rendering of the `${NAME}` placeholders from the environment variables
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// `${NAME}`, `${NAME:-default}` or the escaped `$${NAME}` placeholder
+static PLACEHOLDER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\$(\$?)\{([A-Za-z_][A-Za-z0-9_]*)(?::-([^}]*))?\}").unwrap());
+
+/// Escaping of the values for the template format
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Escape {
+    /// Values are inserted as is
+    None,
+    /// Values are escaped for the JSON strings
+    Json,
+    /// Values are escaped for the HTML text and attributes
+    Html,
+}
+
+impl Escape {
+    /// Picks the escaping by the file extension.
+    pub fn for_file(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") | Some("webmanifest") => Escape::Json,
+            Some("html") | Some("htm") => Escape::Html,
+            _ => Escape::None,
+        }
+    }
+
+    /// Escapes the value for the format.
+    pub fn apply(&self, value: &str) -> String {
+        match self {
+            Escape::None => value.to_string(),
+            Escape::Json => {
+                let quoted = serde_json::to_string(value).unwrap_or_default();
+                quoted[1..quoted.len() - 1].to_string()
+            }
+            Escape::Html => value
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+                .replace('\'', "&#39;"),
+        }
+    }
+}
+
+/// Rendered template
+#[derive(Debug, PartialEq)]
+pub struct Rendered {
+    pub text: String,
+    /// Variables without a value and a default, rendered empty
+    pub missing: Vec<String>,
+}
+
+/// Parses the `.env` file, `NAME=value` lines with optional `export` and quotes.
+pub fn parse_env(text: &str) -> Result<BTreeMap<String, String>> {
+    let mut vars = BTreeMap::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+
+        let (name, value) = line
+            .split_once('=')
+            .filter(|(name, _)| is_name(name.trim()))
+            .ok_or_else(|| anyhow!("Invalid line {}, expected `NAME=value`", index + 1))?;
+
+        let value = value.trim();
+        let value = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) if value.len() > 1 && value.ends_with(quote) => {
+                let inner = &value[1..value.len() - 1];
+                if quote == '"' {
+                    inner.replace("\\n", "\n").replace("\\\"", "\"")
+                } else {
+                    inner.to_string()
+                }
+            }
+            // comments after the unquoted values
+            _ => value
+                .split(" #")
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+        };
+
+        vars.insert(name.trim().to_string(), value);
+    }
+
+    Ok(vars)
+}
+
+fn is_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Reads the variables of the env files and the process environment,
+/// the environment takes precedence over the files, the later files over the earlier ones.
+pub fn load_vars(env_files: &[&Path]) -> Result<BTreeMap<String, String>> {
+    let mut vars = BTreeMap::new();
+    for path in env_files {
+        let text = fs::read_to_string(path)
+            .map_err(|err| anyhow!("Error reading `{}`. {}", path.display(), err))?;
+        let file_vars = parse_env(&text)
+            .map_err(|err| anyhow!("Error parsing `{}`. {}", path.display(), err))?;
+        vars.extend(file_vars);
+    }
+    vars.extend(std::env::vars());
+    Ok(vars)
+}
+
+/// Replaces the `${NAME}` and `${NAME:-default}` placeholders with the variables.
+/// `$${NAME}` is kept as the literal `${NAME}`.
+pub fn render(template: &str, vars: &BTreeMap<String, String>, escape: Escape) -> Rendered {
+    let mut missing = vec![];
+
+    let text = PLACEHOLDER
+        .replace_all(template, |captures: &Captures| {
+            let name = &captures[2];
+            if !captures[1].is_empty() {
+                return captures[0][1..].to_string();
+            }
+
+            match (vars.get(name), captures.get(3)) {
+                (Some(value), _) => escape.apply(value),
+                (None, Some(default)) => escape.apply(default.as_str()),
+                (None, None) => {
+                    if !missing.iter().any(|missing| missing == name) {
+                        missing.push(name.to_string());
+                    }
+                    String::new()
+                }
+            }
+        })
+        .to_string();
+
+    Rendered { text, missing }
+}
+
+/// Renders the template file.
+pub fn render_file(path: &Path, vars: &BTreeMap<String, String>) -> Result<Rendered> {
+    let template = fs::read_to_string(path)?;
+    Ok(render(&template, vars, Escape::for_file(path)))
+}
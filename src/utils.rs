@@ -38,6 +38,57 @@ pub fn compare<T: Ord>(a: &[T], b: &[T]) -> Ordering {
     a.len().cmp(&b.len())
 }
 
+/// Formats the size in bytes with the binary units, i.e. `1.5 KB`.
+pub fn format_size(size: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Removes the content hash from the file name, i.e. `main.3f2a1b4c5d6e7f80.js` becomes `main.js`.
+pub fn strip_hash(file_name: &str) -> String {
+    let mut parts: Vec<&str> = file_name.split('.').collect();
+    if parts.len() < 2 {
+        return file_name.to_string();
+    }
+    let extension = parts.pop().unwrap_or_default();
+
+    let mut stripped: Vec<&str> = vec![];
+    for (index, part) in parts.iter().enumerate() {
+        if index > 0 && is_hash(part) {
+            continue;
+        }
+        match part.rsplit_once('-') {
+            Some((name, hash)) if index == parts.len() - 1 && is_hash(hash) => stripped.push(name),
+            _ => stripped.push(part),
+        }
+    }
+    stripped.push(extension);
+    stripped.join(".")
+}
+
+/// Checks whether the file name part is a content hash: 16 or 20 hex characters of webpack,
+/// or 8 base32 characters of esbuild.
+pub fn is_hash(text: &str) -> bool {
+    let hex = matches!(text.len(), 16 | 20) && text.chars().all(|c| c.is_ascii_hexdigit());
+    let base32 = text.len() == 8
+        && text.chars().any(|c| c.is_ascii_digit())
+        && text.chars().any(|c| c.is_ascii_uppercase())
+        && text
+            .chars()
+            .all(|c| c.is_ascii_digit() || c.is_ascii_uppercase());
+    hex || base32
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
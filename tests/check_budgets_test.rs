@@ -0,0 +1,160 @@
+use rung::angular::Budget;
+use rung::budgets::{
+    check_budgets, format_delta, initial_files, parse_size, read_build_files, BuildStats, SizeKind,
+    Status,
+};
+use rung::utils::strip_hash;
+use serde_json::json;
+use std::fs;
+use tempfile::tempdir;
+
+mod common;
+
+fn budgets(value: serde_json::Value) -> Vec<Budget> {
+    serde_json::from_value(value).unwrap()
+}
+
+#[test]
+fn parses_budget_sizes() {
+    common::setup();
+
+    assert_eq!(500.0, parse_size("500", None, true).unwrap());
+    assert_eq!(512.0, parse_size("0.5kb", None, true).unwrap());
+    assert_eq!(1536.0 * 1024.0, parse_size("1.5MB", None, true).unwrap());
+    // relative to the baseline
+    assert_eq!(1100.0, parse_size("10%", Some("1000b"), true).unwrap());
+    assert_eq!(900.0, parse_size("10%", Some("1000b"), false).unwrap());
+    assert_eq!(2048.0, parse_size("1kb", Some("1kb"), true).unwrap());
+    assert!(parse_size("10%", None, true).is_err());
+    assert!(parse_size("1tb", None, true).is_err());
+}
+
+#[test]
+fn resolves_initial_files() {
+    common::setup();
+
+    let index = r#"<html><head>
+<link rel="stylesheet" href="/app/styles.5c3b8e4f9a0d1e2f.css?v=1">
+<link rel="modulepreload" href="chunk-AB12CD34.js">
+<link rel="icon" href="favicon.ico">
+</head><body>
+<script src="https://cdn.example.com/analytics.js"></script>
+<script src="polyfills-es5.3f2a1b4c5d6e7f80.js" nomodule defer></script>
+<script src="main.3f2a1b4c5d6e7f80.js" type="module"></script>
+</body></html>"#;
+
+    assert_eq!(
+        vec![
+            "styles.5c3b8e4f9a0d1e2f.css",
+            "chunk-AB12CD34.js",
+            "main.3f2a1b4c5d6e7f80.js"
+        ],
+        initial_files(index, "/app/", None)
+    );
+    assert_eq!(
+        vec!["main.js"],
+        initial_files(
+            r#"<script src="/static/main.js" type="module"></script>"#,
+            "/",
+            Some("/static/")
+        )
+    );
+}
+
+#[test]
+fn strips_hashes() {
+    common::setup();
+
+    assert_eq!("main.js", strip_hash("main.3f2a1b4c5d6e7f80.js"));
+    assert_eq!("chunk.js", strip_hash("chunk-5FGHKL2C.js"));
+    assert_eq!(
+        "polyfills-es2015.js",
+        strip_hash("polyfills-es2015.3f2a1b4c5d6e7f80.js")
+    );
+    assert_eq!("jquery-3.5.1.min.js", strip_hash("jquery-3.5.1.min.js"));
+}
+
+#[test]
+fn checks_budgets() {
+    common::setup();
+
+    let dist = tempdir().unwrap();
+    fs::create_dir(dist.path().join("assets")).unwrap();
+    fs::write(
+        dist.path().join("main.3f2a1b4c5d6e7f80.js"),
+        "a".repeat(3000),
+    )
+    .unwrap();
+    fs::write(dist.path().join("chunk-AB12CD34.js"), "b".repeat(1000)).unwrap();
+    fs::write(
+        dist.path().join("styles.5c3b8e4f9a0d1e2f.css"),
+        "c".repeat(500),
+    )
+    .unwrap();
+    fs::write(
+        dist.path().join("main.3f2a1b4c5d6e7f80.js.map"),
+        "d".repeat(9000),
+    )
+    .unwrap();
+    fs::write(dist.path().join("assets/logo.svg"), "<svg/>").unwrap();
+
+    let stats = BuildStats {
+        files: read_build_files(dist.path()).unwrap(),
+        initial: vec![
+            String::from("main.3f2a1b4c5d6e7f80.js"),
+            String::from("styles.5c3b8e4f9a0d1e2f.css"),
+        ],
+        component_styles: vec![],
+    };
+    assert_eq!(3, stats.files.len());
+
+    let rows = check_budgets(
+        &budgets(json!([
+            { "type": "initial", "maximumWarning": "3kb", "maximumError": "4kb" },
+            { "type": "bundle", "name": "main", "maximumError": "2kb" },
+            { "type": "allScript", "maximumError": "4kb" },
+            { "type": "all", "minimumWarning": "5kb" },
+            { "type": "anyScript", "maximumWarning": "2kb" },
+            { "type": "any", "maximumError": "1mb" },
+        ])),
+        &stats,
+        SizeKind::Raw,
+    )
+    .unwrap();
+
+    let summary: Vec<_> = rows
+        .iter()
+        .map(|row| (row.label.as_str(), row.sizes.raw))
+        .collect();
+    assert_eq!(
+        vec![
+            ("initial", 3500),
+            ("bundle main", 3000),
+            ("allScript", 4000),
+            ("all", 4500),
+            ("anyScript main.js", 3000),
+            ("any main.js", 3000),
+        ],
+        summary
+    );
+
+    assert!(matches!(rows[0].status, Status::Warning(_)));
+    assert_eq!(
+        Status::Error(String::from("exceeded maximum budget of 2.0 KB by 952 B")),
+        rows[1].status
+    );
+    assert_eq!(Status::Ok, rows[2].status);
+    assert_eq!(
+        Status::Warning(String::from(
+            "failed to meet minimum budget of 5.0 KB by 620 B"
+        )),
+        rows[3].status
+    );
+    assert!(matches!(rows[4].status, Status::Warning(_)));
+    assert_eq!(Status::Ok, rows[5].status);
+    assert!(rows[0].sizes.gzip < rows[0].sizes.raw);
+
+    assert_eq!("+1.0 KB", format_delta(2048, 1024));
+    assert_eq!("-24 B", format_delta(1000, 1024));
+    assert_eq!("0 B", format_delta(1024, 1024));
+}
@@ -0,0 +1,101 @@
+use rung::deps::{check_package, fix_package, min_version, satisfies, Issue};
+use rung::format::{format_text_with, FormatOptions};
+use serde_json::json;
+
+mod common;
+
+#[test]
+fn matches_npm_ranges() {
+    common::setup();
+
+    assert_eq!(Some(true), satisfies("12.1.2", "^12.0.0"));
+    assert_eq!(Some(false), satisfies("13.0.0", "^12.0.0"));
+    assert_eq!(Some(true), satisfies("0.2.5", "^0.2.1"));
+    assert_eq!(Some(false), satisfies("0.3.0", "^0.2.1"));
+    assert_eq!(Some(true), satisfies("1.2.9", "~1.2.3"));
+    assert_eq!(Some(false), satisfies("1.3.0", "~1.2.3"));
+    assert_eq!(Some(true), satisfies("11.2.0", "^10.0.0 || ^11.0.0"));
+    assert_eq!(Some(true), satisfies("2.5.0", ">= 2.0.0 <3"));
+    assert_eq!(Some(true), satisfies("2.3.9", "1.0.0 - 2.3"));
+    assert_eq!(Some(false), satisfies("2.4.0", "1.0.0 - 2.3"));
+    assert_eq!(Some(true), satisfies("4.1.0", "4.x"));
+    assert_eq!(None, satisfies("1.0.0", "file:../lib"));
+
+    assert_eq!(Some(String::from("12.1.2")), min_version("~12.1.2"));
+}
+
+#[test]
+fn reports_package_issues() {
+    common::setup();
+
+    let root = json!({
+        "dependencies": { "@angular/core": "~12.1.2", "rxjs": "~6.6.0" },
+        "devDependencies": { "typescript": "~4.3.5" }
+    });
+
+    let package = json!({
+        "peerDependencies": {
+            "@angular/core": "^11.0.0",
+            "rxjs": "^6.5.0",
+            "ngx-toastr": "^14.0.0"
+        },
+        "dependencies": { "tslib": "^2.2.0", "rxjs": "^6.5.0" }
+    });
+
+    let issues = check_package(&root, &package);
+
+    assert_eq!(
+        vec![
+            Issue::Conflict {
+                section: String::from("peerDependencies"),
+                name: String::from("@angular/core"),
+                range: String::from("^11.0.0"),
+                expected: String::from("~12.1.2"),
+            },
+            Issue::MissingPeer {
+                name: String::from("ngx-toastr"),
+                range: String::from("^14.0.0"),
+            },
+            Issue::Duplicate {
+                name: String::from("rxjs"),
+                sections: vec![
+                    String::from("dependencies"),
+                    String::from("peerDependencies")
+                ],
+            },
+        ],
+        issues
+    );
+}
+
+#[test]
+fn fixes_conflicting_ranges() {
+    common::setup();
+
+    let root = json!({ "dependencies": { "@angular/core": "~12.1.2" } });
+    let text = r#"{
+  "version": 1.0,
+  "peerDependencies": { "@angular/core": "^11.0.0" },
+  "devDependencies": { "@angular/core": "^12.0.0" }
+}"#;
+    let mut package = serde_json::from_str(text).unwrap();
+
+    let changes = fix_package(&root, &mut package);
+    assert_eq!(
+        vec![(
+            String::from("/peerDependencies/@angular~1core"),
+            String::from("~12.1.2")
+        )],
+        changes
+    );
+    assert_eq!("~12.1.2", package["peerDependencies"]["@angular/core"]);
+    assert_eq!("^12.0.0", package["devDependencies"]["@angular/core"]);
+    assert!(check_package(&root, &package).is_empty());
+
+    // the fixed package is written in the original key order with the literals as written
+    let fixed = format_text_with(text, &changes, &FormatOptions::default()).unwrap();
+    assert_eq!(
+        "{\n  \"version\": 1.0,\n  \"peerDependencies\": {\n    \"@angular/core\": \"~12.1.2\"\n  },\n  \"devDependencies\": {\n    \"@angular/core\": \"^12.0.0\"\n  }\n}\n",
+        fixed
+    );
+}
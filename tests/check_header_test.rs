@@ -12,7 +12,7 @@ fn verifies_file() -> Result<()> {
 
     let file = NamedTempFile::new()?;
 
-    assert_eq!(true, verify_files(&vec![file.path()]));
+    assert!(verify_files(&[file.path()]));
 
     file.close()?;
 
@@ -25,10 +25,7 @@ fn fails_verifying_when_one_file_missing() -> Result<()> {
 
     let file = NamedTempFile::new()?;
 
-    assert_eq!(
-        false,
-        verify_files(&vec![file.path(), Path::new("missing.txt")])
-    );
+    assert!(!verify_files(&[file.path(), Path::new("missing.txt")]));
 
     file.close()?;
 
@@ -39,7 +36,7 @@ fn fails_verifying_when_one_file_missing() -> Result<()> {
 fn fails_to_verify_missing_file() {
     common::setup();
 
-    assert_eq!(false, verify_files(&vec![Path::new("missing.txt")]));
+    assert!(!verify_files(&[Path::new("missing.txt")]));
 }
 
 #[test]
@@ -49,7 +46,7 @@ fn verifies_multiple_files() -> Result<()> {
     let file1 = NamedTempFile::new()?;
     let file2 = NamedTempFile::new()?;
 
-    assert_eq!(true, verify_files(&vec![file1.path(), file2.path()]));
+    assert!(verify_files(&[file1.path(), file2.path()]));
 
     file1.close()?;
     file2.close()?;
@@ -67,10 +64,7 @@ fn passes_single_file_template() -> Result<()> {
     let mut template = NamedTempFile::new()?;
     writeln!(template, "hello")?;
 
-    assert_eq!(
-        true,
-        check_headers(&vec![file.path()], &vec![template.path()])
-    );
+    assert!(check_headers(&[file.path()], &[template.path()]));
 
     Ok(())
 }
@@ -88,10 +82,10 @@ fn passes_multiple_file_single_template() -> Result<()> {
     let mut template = NamedTempFile::new()?;
     writeln!(template, "hello")?;
 
-    assert_eq!(
-        true,
-        check_headers(&vec![file1.path(), file2.path()], &vec![template.path()])
-    );
+    assert!(check_headers(
+        &[file1.path(), file2.path()],
+        &[template.path()]
+    ));
 
     Ok(())
 }
@@ -109,10 +103,10 @@ fn fails_multiple_files_single_template() -> Result<()> {
     let mut template = NamedTempFile::new()?;
     writeln!(template, "something else")?;
 
-    assert_eq!(
-        false,
-        check_headers(&vec![file1.path(), file2.path()], &vec![template.path()])
-    );
+    assert!(!check_headers(
+        &[file1.path(), file2.path()],
+        &[template.path()]
+    ));
 
     Ok(())
 }
@@ -127,10 +121,7 @@ fn fails_single_file_template() -> Result<()> {
     let mut template = NamedTempFile::new()?;
     writeln!(template, "assets")?;
 
-    assert_eq!(
-        false,
-        check_headers(&vec![file.path()], &vec![template.path()])
-    );
+    assert!(!check_headers(&[file.path()], &[template.path()]));
 
     Ok(())
 }
@@ -148,13 +139,10 @@ fn passes_single_file_multiple_templates() -> Result<()> {
     let mut template2 = NamedTempFile::new()?;
     writeln!(template2, "hello")?;
 
-    assert_eq!(
-        true,
-        check_headers(
-            &vec![file.path()],
-            &vec![template1.path(), template2.path()]
-        )
-    );
+    assert!(check_headers(
+        &[file.path()],
+        &[template1.path(), template2.path()]
+    ));
 
     Ok(())
 }
@@ -172,13 +160,10 @@ fn fails_single_file_multiple_templates() -> Result<()> {
     let mut template2 = NamedTempFile::new()?;
     writeln!(template2, "template2")?;
 
-    assert_eq!(
-        false,
-        check_headers(
-            &vec![file.path()],
-            &vec![template1.path(), template2.path()]
-        )
-    );
+    assert!(!check_headers(
+        &[file.path()],
+        &[template1.path(), template2.path()]
+    ));
 
     Ok(())
 }
@@ -199,13 +184,10 @@ fn passes_multiple_files_multiple_templates() -> Result<()> {
     let mut template2 = NamedTempFile::new()?;
     writeln!(template2, "hey")?;
 
-    assert_eq!(
-        true,
-        check_headers(
-            &vec![file1.path(), file2.path()],
-            &vec![template1.path(), template2.path()]
-        )
-    );
+    assert!(check_headers(
+        &[file1.path(), file2.path()],
+        &[template1.path(), template2.path()]
+    ));
 
     Ok(())
 }
@@ -226,13 +208,10 @@ fn fails_multiple_files_multiple_templates() -> Result<()> {
     let mut template2 = NamedTempFile::new()?;
     writeln!(template2, "else")?;
 
-    assert_eq!(
-        false,
-        check_headers(
-            &vec![file1.path(), file2.path()],
-            &vec![template1.path(), template2.path()]
-        )
-    );
+    assert!(!check_headers(
+        &[file1.path(), file2.path()],
+        &[template1.path(), template2.path()]
+    ));
 
     Ok(())
 }
@@ -0,0 +1,135 @@
+use anyhow::Result;
+use rung::i18n::{check_translations, read_translation_file, Issue};
+use std::io::Write;
+use tempfile::Builder;
+
+mod common;
+
+fn write_file(suffix: &str, content: &str) -> Result<tempfile::NamedTempFile> {
+    let mut file = Builder::new().suffix(suffix).tempfile()?;
+    write!(file, "{}", content)?;
+    Ok(file)
+}
+
+const SOURCE_XLF: &str = r#"<?xml version="1.0" encoding="UTF-8" ?>
+<xliff version="1.2" xmlns="urn:oasis:names:tc:xliff:document:1.2">
+  <file source-language="en-US" datatype="plaintext" original="ng2.template">
+    <body>
+      <trans-unit id="greeting" datatype="html">
+        <source>Hello <x id="INTERPOLATION" equiv-text="{{ name }}"/>!</source>
+      </trans-unit>
+      <trans-unit id="minutes" datatype="html">
+        <source>{VAR_PLURAL, plural, =0 {just now} other {<x id="INTERPOLATION"/> minutes ago}}</source>
+      </trans-unit>
+      <trans-unit id="title" datatype="html">
+        <source>Title</source>
+      </trans-unit>
+    </body>
+  </file>
+</xliff>"#;
+
+#[test]
+fn passes_consistent_xliff_translations() -> Result<()> {
+    common::setup();
+
+    let translation = r#"<?xml version="1.0" encoding="UTF-8" ?>
+<xliff version="1.2" xmlns="urn:oasis:names:tc:xliff:document:1.2">
+  <file source-language="en-US" target-language="de" datatype="plaintext">
+    <body>
+      <trans-unit id="greeting"><target>Hallo <x id="INTERPOLATION"/>!</target></trans-unit>
+      <trans-unit id="minutes"><target>{VAR_PLURAL, plural, =0 {gerade} one {vor einer Minute} other {vor <x id="INTERPOLATION"/> Minuten}}</target></trans-unit>
+      <trans-unit id="title"><target>Titel</target></trans-unit>
+    </body>
+  </file>
+</xliff>"#;
+
+    let source = read_translation_file(write_file(".xlf", SOURCE_XLF)?.path())?;
+    let target = read_translation_file(write_file(".xlf", translation)?.path())?;
+
+    assert_eq!(Vec::<Issue>::new(), check_translations(&source, &target));
+
+    Ok(())
+}
+
+#[test]
+fn reports_xliff2_issues() -> Result<()> {
+    common::setup();
+
+    let translation = r#"<?xml version="1.0" encoding="UTF-8" ?>
+<xliff version="2.0" xmlns="urn:oasis:names:tc:xliff:document:2.0" srcLang="en-US" trgLang="de">
+  <file id="ngi18n" original="ng.template">
+    <unit id="greeting">
+      <segment><source>Hello <ph id="0" equiv="INTERPOLATION"/>!</source><target>Hallo!</target></segment>
+    </unit>
+    <unit id="title">
+      <segment><source>Title</source><target> </target></segment>
+    </unit>
+    <unit id="obsolete">
+      <segment><source>Old</source><target>Alt</target></segment>
+    </unit>
+  </file>
+</xliff>"#;
+
+    let source = read_translation_file(write_file(".xlf", SOURCE_XLF)?.path())?;
+    let target = read_translation_file(write_file(".xlf", translation)?.path())?;
+
+    let issues = check_translations(&source, &target);
+
+    assert_eq!(4, issues.len());
+    assert!(matches!(&issues[0], Issue::PlaceholderMismatch { id, .. } if id == "greeting"));
+    assert_eq!(Issue::Missing(String::from("minutes")), issues[1]);
+    assert_eq!(Issue::EmptyTarget(String::from("title")), issues[2]);
+    assert_eq!(Issue::Extra(String::from("obsolete")), issues[3]);
+
+    Ok(())
+}
+
+#[test]
+fn checks_xmb_with_xtb() -> Result<()> {
+    common::setup();
+
+    let source = r#"<?xml version="1.0" encoding="UTF-8" ?>
+<messagebundle>
+  <msg id="123">Hello <ph name="INTERPOLATION"><ex>{{ name }}</ex>{{ name }}</ph></msg>
+</messagebundle>"#;
+
+    let translation = r#"<?xml version="1.0" encoding="UTF-8" ?>
+<translationbundle lang="de">
+  <translation id="123">Hallo <ph name="INTERPOLATION_1"/></translation>
+</translationbundle>"#;
+
+    let source = read_translation_file(write_file(".xmb", source)?.path())?;
+    let target = read_translation_file(write_file(".xtb", translation)?.path())?;
+
+    let issues = check_translations(&source, &target);
+
+    assert_eq!(1, issues.len());
+    assert!(matches!(&issues[0], Issue::PlaceholderMismatch { id, .. } if id == "123"));
+
+    Ok(())
+}
+
+#[test]
+fn checks_json_translations() -> Result<()> {
+    common::setup();
+
+    let source = r#"{ "locale": "en-US", "translations": {
+        "greeting": "Hello {$INTERPOLATION}!",
+        "count": "{VAR_SELECT, select, male {he} other {they}}"
+    } }"#;
+
+    let translation = r#"{ "locale": "de", "translations": {
+        "greeting": "Hallo {$INTERPOLATION}!",
+        "count": "{VAR_PLURAL, plural, other {sie}}"
+    } }"#;
+
+    let source = read_translation_file(write_file(".json", source)?.path())?;
+    let target = read_translation_file(write_file(".json", translation)?.path())?;
+
+    let issues = check_translations(&source, &target);
+
+    assert_eq!(1, issues.len());
+    assert!(matches!(&issues[0], Issue::PlaceholderMismatch { id, .. } if id == "count"));
+
+    Ok(())
+}
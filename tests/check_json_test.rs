@@ -56,10 +56,7 @@ fn passes_validation() -> Result<()> {
     let mut schema_file = NamedTempFile::new()?;
     writeln!(schema_file, "{}", schema_text)?;
 
-    assert_eq!(
-        true,
-        validate_with_schema(json_file.path(), schema_file.path())?
-    );
+    assert!(validate_with_schema(json_file.path(), schema_file.path())?);
 
     Ok(())
 }
@@ -94,10 +91,7 @@ fn fails_validation() -> Result<()> {
     let mut schema_file = NamedTempFile::new()?;
     writeln!(schema_file, "{}", schema_text)?;
 
-    assert_eq!(
-        false,
-        validate_with_schema(json_file.path(), schema_file.path())?
-    );
+    assert!(!validate_with_schema(json_file.path(), schema_file.path())?);
 
     Ok(())
 }
@@ -0,0 +1,177 @@
+use rung::pwa::{check_pwa, file_hash, png_size, Issue};
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+mod common;
+
+fn write_png(path: &Path, width: u32, height: u32) {
+    let mut bytes = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+    bytes.extend_from_slice(&width.to_be_bytes());
+    bytes.extend_from_slice(&height.to_be_bytes());
+    bytes.extend_from_slice(&[8, 6, 0, 0, 0]);
+    fs::write(path, bytes).unwrap();
+}
+
+/// Writes the build output served at `/app/` and returns its `ngsw.json`
+fn write_build(dist: &Path) -> serde_json::Value {
+    fs::create_dir_all(dist.join("assets/icons")).unwrap();
+    fs::write(dist.join("index.html"), "<app-root></app-root>").unwrap();
+    fs::write(dist.join("main.js"), "console.log('v2')").unwrap();
+    write_png(&dist.join("assets/icons/icon-192x192.png"), 192, 192);
+    write_png(&dist.join("assets/icons/icon-512x512.png"), 512, 512);
+
+    let hash = |name: &str| file_hash(&dist.join(name)).unwrap();
+    fs::write(
+        dist.join("manifest.webmanifest"),
+        json!({
+            "name": "app",
+            "display": "standalone",
+            "start_url": "./",
+            "icons": [
+                { "src": "assets/icons/icon-192x192.png", "sizes": "192x192", "type": "image/png" },
+                { "src": "/app/assets/icons/icon-512x512.png", "sizes": "512x512", "type": "image/png" }
+            ]
+        })
+        .to_string(),
+    )
+    .unwrap();
+
+    json!({
+        "configVersion": 1,
+        "timestamp": 1610000000000u64,
+        "index": "/app/index.html",
+        "assetGroups": [
+            { "name": "app", "installMode": "prefetch", "urls": ["/app/index.html", "/app/main.js"] },
+            { "name": "assets", "installMode": "lazy", "urls": ["/app/assets/icons/icon-192x192.png"] }
+        ],
+        "hashTable": {
+            "/app/index.html": hash("index.html"),
+            "/app/main.js": hash("main.js"),
+            "/app/assets/icons/icon-192x192.png": hash("assets/icons/icon-192x192.png")
+        },
+        "navigationUrls": [
+            { "positive": true, "regex": "^\\/.*$" },
+            { "positive": false, "regex": "^\\/(?:.+\\/)?[^/]*\\.[^/]*$" },
+            { "positive": false, "regex": "^\\/(?:.+\\/)?[^/]*__[^/]*$" }
+        ]
+    })
+}
+
+#[test]
+fn verifies_valid_build() -> anyhow::Result<()> {
+    common::setup();
+
+    let dir = tempdir()?;
+    let ngsw = write_build(dir.path());
+    fs::write(dir.path().join("ngsw.json"), ngsw.to_string())?;
+
+    assert_eq!(Vec::<Issue>::new(), check_pwa(dir.path())?);
+    assert_eq!(
+        Some((512, 512)),
+        png_size(&dir.path().join("assets/icons/icon-512x512.png"))
+    );
+    assert_eq!(None, png_size(&dir.path().join("main.js")));
+
+    Ok(())
+}
+
+#[test]
+fn reports_service_worker_issues() -> anyhow::Result<()> {
+    common::setup();
+
+    let dir = tempdir()?;
+    let mut ngsw = write_build(dir.path());
+    ngsw["hashTable"]["/app/old.js"] = json!("0123456789abcdef0123456789abcdef01234567");
+    ngsw["hashTable"]["/app/assets/extra.txt"] = json!("da39a3ee5e6b4b0d3255bfef95601890afd80709");
+    ngsw["assetGroups"][1]["urls"] = json!([
+        "/app/old.js",
+        "/app/assets/extra.txt",
+        "/app/manifest.webmanifest"
+    ]);
+    ngsw["navigationUrls"][0]["regex"] = json!("^\\/app\\/admin(.*$");
+    fs::write(dir.path().join("ngsw.json"), ngsw.to_string())?;
+    fs::write(dir.path().join("assets/extra.txt"), "")?;
+    // modified after the build
+    fs::write(dir.path().join("main.js"), "console.log('v3')")?;
+
+    let issues = check_pwa(dir.path())?;
+    let main_hash = file_hash(&dir.path().join("main.js"))?;
+    assert_eq!(
+        vec![
+            Issue::HashMismatch {
+                url: String::from("/app/main.js"),
+                expected: ngsw["hashTable"]["/app/main.js"]
+                    .as_str()
+                    .unwrap()
+                    .to_string(),
+                actual: main_hash,
+            },
+            Issue::Stale {
+                url: String::from("/app/old.js")
+            },
+            Issue::Unhashed {
+                url: String::from("/app/manifest.webmanifest")
+            },
+            Issue::Unreferenced {
+                url: String::from("/app/assets/icons/icon-192x192.png")
+            },
+            Issue::InvalidNavigationUrl {
+                regex: String::from("^\\/app\\/admin(.*$")
+            },
+        ],
+        issues
+    );
+
+    Ok(())
+}
+
+#[test]
+fn reports_manifest_issues() -> anyhow::Result<()> {
+    common::setup();
+
+    let dir = tempdir()?;
+    let mut ngsw = write_build(dir.path());
+    ngsw["navigationUrls"] = json!([
+        { "positive": true, "regex": "^\\/app\\/.*$" },
+        { "positive": false, "regex": "^\\/app\\/home$" }
+    ]);
+    fs::write(dir.path().join("ngsw.json"), ngsw.to_string())?;
+    fs::write(
+        dir.path().join("manifest.webmanifest"),
+        json!({
+            "display": "browser",
+            "start_url": "home?source=pwa",
+            "icons": [
+                { "src": "assets/icons/icon-192x192.png", "sizes": "144x144" },
+                { "src": "assets/icons/missing.png", "sizes": "512x512" }
+            ]
+        })
+        .to_string(),
+    )?;
+
+    let issues: Vec<_> = check_pwa(dir.path())?
+        .iter()
+        .map(|issue| issue.to_string())
+        .collect();
+    assert_eq!(
+        vec![
+            "manifest: `name` or `short_name` is required",
+            "manifest: `display` is `browser`, the application can not be installed",
+            "navigation to `/app/home` is not handled by the service worker",
+            "icon `assets/icons/icon-192x192.png` is 192x192, but declared as `144x144`",
+            "icon `assets/icons/missing.png` does not exist",
+            "no 512x512 icon",
+        ],
+        issues
+    );
+
+    fs::remove_file(dir.path().join("manifest.webmanifest"))?;
+    assert_eq!(vec![Issue::MissingManifest], check_pwa(dir.path())?);
+
+    fs::remove_file(dir.path().join("ngsw.json"))?;
+    assert!(check_pwa(dir.path()).is_err());
+
+    Ok(())
+}
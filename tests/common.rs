@@ -1,5 +1,3 @@
-use env_logger;
-
 pub fn setup() {
     // some setup code, like creating required files/directories, starting
     // servers, etc.
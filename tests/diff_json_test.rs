@@ -0,0 +1,89 @@
+use anyhow::Result;
+use rung::diff::{diff, to_patch, Change};
+use serde_json::{json, Value};
+
+mod common;
+
+#[test]
+fn ignores_key_order_and_number_formatting() -> Result<()> {
+    common::setup();
+
+    let left: Value = serde_json::from_str(r#"{"a": 1.0, "b": {"x": [1, 2]}}"#)?;
+    let right: Value = serde_json::from_str(r#"{"b": {"x": [1, 2]}, "a": 1}"#)?;
+
+    assert!(diff(&left, &right).is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn compares_large_integers_exactly() -> Result<()> {
+    common::setup();
+
+    // both round to the same f64
+    let left: Value =
+        serde_json::from_str(r#"{"id": 9007199254740993, "big": 18446744073709551615}"#)?;
+    let right: Value =
+        serde_json::from_str(r#"{"id": 9007199254740992, "big": 18446744073709551614}"#)?;
+
+    let changes = diff(&left, &right);
+    assert_eq!(2, changes.len());
+    assert!(diff(&left, &left).is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn reports_changes_with_pointers() {
+    common::setup();
+
+    let left = json!({ "name": "app", "a/b": 1, "old": true });
+    let right = json!({ "name": "app2", "a/b": 2, "new": null });
+
+    let changes = diff(&left, &right);
+
+    assert_eq!(
+        vec![
+            Change::Changed {
+                pointer: String::from("/name"),
+                old: json!("app"),
+                new: json!("app2")
+            },
+            Change::Changed {
+                pointer: String::from("/a~1b"),
+                old: json!(1),
+                new: json!(2)
+            },
+            Change::Removed {
+                pointer: String::from("/old"),
+                value: json!(true)
+            },
+            Change::Added {
+                pointer: String::from("/new"),
+                value: Value::Null
+            },
+        ],
+        changes
+    );
+    assert_eq!("~ /name: \"app\" -> \"app2\"", changes[0].to_string());
+}
+
+#[test]
+fn creates_json_patch() {
+    common::setup();
+
+    let left = json!({ "items": [1, 2, 3, 4], "x": 1 });
+    let right = json!({ "items": [0, 2] });
+
+    let patch = to_patch(&diff(&left, &right));
+
+    assert_eq!(
+        json!([
+            { "op": "replace", "path": "/items/0", "value": 0 },
+            { "op": "remove", "path": "/items/3" },
+            { "op": "remove", "path": "/items/2" },
+            { "op": "remove", "path": "/x" }
+        ]),
+        patch
+    );
+}
@@ -0,0 +1,116 @@
+use anyhow::Result;
+use rung::format::{check_files, format_files, format_text, format_value, FormatOptions, Indent};
+use serde_json::Value;
+use std::fs::read_to_string;
+use std::io::Write;
+use tempfile::NamedTempFile;
+
+mod common;
+
+#[test]
+fn preserves_key_order_and_number_literals() -> Result<()> {
+    common::setup();
+
+    let text = format_text(
+        r#"{"b":1.50,"a":[1e3,2,12345678901234567890123],"c":"\u00e9"}"#,
+        &FormatOptions::default(),
+    )?;
+
+    assert_eq!(
+        "{\n  \"b\": 1.50,\n  \"a\": [\n    1e3,\n    2,\n    12345678901234567890123\n  ],\n  \"c\": \"é\"\n}\n",
+        text
+    );
+
+    Ok(())
+}
+
+#[test]
+fn rejects_invalid_json() {
+    common::setup();
+
+    let options = FormatOptions::default();
+    for text in &[
+        "{\"a\":01}",
+        "{\"a\":1,}",
+        "[1 2]",
+        "{\"a\" 1}",
+        "[1] 2",
+        "[\"a]",
+    ] {
+        assert!(format_text(text, &options).is_err(), "{}", text);
+    }
+
+    // nested deeper than the limit
+    let nested = format!("{}{}", "[".repeat(129), "]".repeat(129));
+    assert!(format_text(&nested, &options).is_err());
+    let nested = format!("{}{}", "[".repeat(128), "]".repeat(128));
+    assert!(format_text(&nested, &options).is_ok());
+}
+
+#[test]
+fn skips_byte_order_mark() -> Result<()> {
+    common::setup();
+
+    let text = format_text("\u{feff}{\"a\":1}", &FormatOptions::default())?;
+    assert_eq!("{\n  \"a\": 1\n}\n", text);
+
+    Ok(())
+}
+
+#[test]
+fn sorts_keys_globally() -> Result<()> {
+    common::setup();
+
+    let value: Value = serde_json::from_str(r#"{"b":{"d":1,"c":2},"a":{}}"#)?;
+    let options = FormatOptions {
+        indent: Indent::Tab,
+        sort_keys: true,
+        final_newline: false,
+        ..FormatOptions::default()
+    };
+
+    let text = format_value(&value, &options);
+
+    assert_eq!(
+        "{\n\t\"a\": {},\n\t\"b\": {\n\t\t\"c\": 2,\n\t\t\"d\": 1\n\t}\n}",
+        text
+    );
+
+    Ok(())
+}
+
+#[test]
+fn sorts_keys_by_pointer() -> Result<()> {
+    common::setup();
+
+    let value: Value = serde_json::from_str(r#"{"z":{"b":{"y":1,"x":2},"a":0},"y":0}"#)?;
+    let options = FormatOptions {
+        indent: Indent::Spaces(0),
+        sort_pointers: vec![String::from("/z")],
+        final_newline: false,
+        ..FormatOptions::default()
+    };
+
+    let text = format_value(&value, &options).replace('\n', "");
+
+    assert_eq!(r#"{"z": {"a": 0,"b": {"y": 1,"x": 2}},"y": 0}"#, text);
+
+    Ok(())
+}
+
+#[test]
+fn checks_and_formats_files() -> Result<()> {
+    common::setup();
+
+    let mut file = NamedTempFile::new()?;
+    write!(file, "{{ \"name\":\"app\" }}")?;
+
+    let options = FormatOptions::default();
+
+    assert!(!check_files(&[file.path()], &options));
+    assert!(format_files(&[file.path()], &options));
+    assert!(check_files(&[file.path()], &options));
+    assert_eq!("{\n  \"name\": \"app\"\n}\n", read_to_string(file.path())?);
+
+    Ok(())
+}
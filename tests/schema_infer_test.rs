@@ -0,0 +1,87 @@
+use anyhow::Result;
+use rung::json::validate_with_schema;
+use rung::schema::{infer_schema, InferOptions, SCHEMA_DRAFT_07};
+use serde_json::json;
+use std::io::Write;
+use tempfile::NamedTempFile;
+
+mod common;
+
+#[test]
+fn infers_types_and_required_keys() {
+    common::setup();
+
+    let samples = vec![
+        json!({ "name": "app1", "port": 4200, "ratio": 0.5, "tags": ["a"] }),
+        json!({ "name": "app2", "port": 4201, "ratio": 1, "debug": true }),
+    ];
+
+    let schema = infer_schema(&samples, &InferOptions::default());
+
+    assert_eq!(SCHEMA_DRAFT_07, schema["$schema"]);
+    assert_eq!("object", schema["type"]);
+    assert_eq!(json!(["name", "port", "ratio"]), schema["required"]);
+    assert_eq!("string", schema["properties"]["name"]["type"]);
+    assert_eq!("integer", schema["properties"]["port"]["type"]);
+    assert_eq!("number", schema["properties"]["ratio"]["type"]);
+    assert_eq!("boolean", schema["properties"]["debug"]["type"]);
+    assert_eq!("string", schema["properties"]["tags"]["items"]["type"]);
+}
+
+#[test]
+fn infers_enums_for_repeated_strings() {
+    common::setup();
+
+    let samples = vec![
+        json!({ "type": "application" }),
+        json!({ "type": "library" }),
+        json!({ "type": "application" }),
+    ];
+
+    let schema = infer_schema(&samples, &InferOptions::default());
+    assert_eq!(
+        json!(["application", "library"]),
+        schema["properties"]["type"]["enum"]
+    );
+
+    let schema = infer_schema(&samples, &InferOptions { enum_threshold: 0 });
+    assert!(schema["properties"]["type"].get("enum").is_none());
+}
+
+#[test]
+fn infers_mixed_types() {
+    common::setup();
+
+    let samples = vec![json!([1, "two", null])];
+    let schema = infer_schema(&samples, &InferOptions::default());
+
+    assert_eq!("array", schema["type"]);
+    assert_eq!(
+        json!(["null", "integer", "string"]),
+        schema["items"]["type"]
+    );
+}
+
+#[test]
+fn validates_samples_with_inferred_schema() -> Result<()> {
+    common::setup();
+
+    let sample = json!({
+        "productId": 1,
+        "productName": "An ice sculpture",
+        "tags": ["cold", "ice"],
+        "dimensions": { "length": 7.0, "width": 12.0 }
+    });
+
+    let schema = infer_schema(std::slice::from_ref(&sample), &InferOptions::default());
+
+    let mut json_file = NamedTempFile::new()?;
+    writeln!(json_file, "{}", sample)?;
+
+    let mut schema_file = NamedTempFile::new()?;
+    writeln!(schema_file, "{}", schema)?;
+
+    assert!(validate_with_schema(json_file.path(), schema_file.path())?);
+
+    Ok(())
+}
@@ -0,0 +1,175 @@
+use actix_web::http::{HeaderMap, HeaderName, HeaderValue};
+use rung::serve::access_log::{
+    parse_rotation, parse_size, request_id, AccessLog, AccessLogOptions, Entry, LogFormat, Period,
+    Rotation,
+};
+use std::fs;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tempfile::tempdir;
+
+mod common;
+
+fn entry(seconds: u64) -> Entry {
+    Entry {
+        time: UNIX_EPOCH + Duration::from_secs(seconds),
+        remote: Some(String::from("192.168.1.20")),
+        user: Some(String::from("ann")),
+        method: String::from("GET"),
+        uri: String::from("/main.js?v=1"),
+        protocol: String::from("HTTP/1.1"),
+        status: 200,
+        bytes: 5120,
+        referer: Some(String::from("http://localhost:8080/")),
+        user_agent: Some(String::from("Mozilla/5.0 \"test\"")),
+        request_id: None,
+        duration: None,
+    }
+}
+
+#[test]
+fn parses_rotation() {
+    common::setup();
+
+    assert_eq!(512, parse_size("512").unwrap());
+    assert_eq!(500 * 1024, parse_size("500KB").unwrap());
+    assert_eq!(10 * 1024 * 1024, parse_size("10mb").unwrap());
+    assert_eq!(1024 * 1024 * 1024, parse_size("1G").unwrap());
+    assert!(parse_size("0MB").is_err());
+    assert!(parse_size("10XB").is_err());
+    assert!(parse_size("MB").is_err());
+
+    assert_eq!(
+        Rotation {
+            max_size: Some(10 * 1024 * 1024),
+            period: Some(Period::Daily),
+        },
+        parse_rotation(&["daily", "10MB"]).unwrap()
+    );
+    assert_eq!(Ok(LogFormat::Combined), "combined".parse());
+    assert!("apache".parse::<LogFormat>().is_err());
+}
+
+#[test]
+fn formats_entries() {
+    common::setup();
+
+    // 2021-01-15T09:30:00Z
+    let mut entry = entry(1_610_703_000);
+    assert_eq!(
+        r#"192.168.1.20 - ann [15/Jan/2021:09:30:00 +0000] "GET /main.js?v=1 HTTP/1.1" 200 5120 "http://localhost:8080/" "Mozilla/5.0 \"test\"""#,
+        entry.format(LogFormat::Combined)
+    );
+    assert_eq!(
+        serde_json::json!({
+            "time": "2021-01-15T09:30:00.000Z",
+            "remote": "192.168.1.20",
+            "user": "ann",
+            "method": "GET",
+            "uri": "/main.js?v=1",
+            "protocol": "HTTP/1.1",
+            "status": 200,
+            "bytes": 5120,
+            "referer": "http://localhost:8080/",
+            "userAgent": "Mozilla/5.0 \"test\""
+        }),
+        serde_json::from_str::<serde_json::Value>(&entry.format(LogFormat::Json)).unwrap()
+    );
+
+    entry.request_id = Some(String::from("abc-1"));
+    entry.duration = Some(Duration::from_micros(12_345));
+    entry.remote = None;
+    entry.user = None;
+    entry.referer = None;
+    assert!(entry
+        .format(LogFormat::Combined)
+        .starts_with("- - - [15/Jan/2021:09:30:00 +0000]"));
+    assert!(entry
+        .format(LogFormat::Combined)
+        .ends_with(r#" "-" "Mozilla/5.0 \"test\"" rid=abc-1 rt=0.012"#));
+    let json: serde_json::Value = serde_json::from_str(&entry.format(LogFormat::Json)).unwrap();
+    assert_eq!("abc-1", json["requestId"]);
+    assert_eq!(12.345, json["durationMs"]);
+}
+
+#[test]
+fn reuses_request_ids() {
+    common::setup();
+
+    let mut headers = HeaderMap::new();
+    let generated = request_id(&headers);
+    assert_eq!(32, generated.len());
+    assert_ne!(generated, request_id(&headers));
+
+    headers.insert(
+        HeaderName::from_static("x-request-id"),
+        HeaderValue::from_static("trace-42"),
+    );
+    assert_eq!("trace-42", request_id(&headers));
+}
+
+#[test]
+fn rotates_by_size() -> anyhow::Result<()> {
+    common::setup();
+
+    let dir = tempdir()?;
+    let path = dir.path().join("logs/access.log");
+    let log = AccessLog::new(&AccessLogOptions {
+        path: path.clone(),
+        format: LogFormat::Combined,
+        rotation: Rotation {
+            max_size: Some(300),
+            period: None,
+        },
+        keep: 2,
+        timing: false,
+    })?;
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    for index in 0..7 {
+        let mut entry = entry(now);
+        entry.status = 200 + index;
+        log.write(&entry);
+    }
+
+    let read = |suffix: &str| fs::read_to_string(format!("{}{}", path.display(), suffix));
+    // two lines fit into a file
+    assert_eq!(2, read(".1")?.lines().count());
+    assert!(read("")?.contains("\" 206 "));
+    assert!(read(".1")?.contains("\" 205 "));
+    assert!(read(".2")?.contains("\" 203 "));
+    assert!(read(".3").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn rotates_by_time() -> anyhow::Result<()> {
+    common::setup();
+
+    let dir = tempdir()?;
+    let path = dir.path().join("access.log");
+    let log = AccessLog::new(&AccessLogOptions {
+        path: path.clone(),
+        format: LogFormat::Json,
+        rotation: Rotation {
+            max_size: None,
+            period: Some(Period::Hourly),
+        },
+        keep: 7,
+        timing: false,
+    })?;
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let hour = now / 3600 * 3600;
+    log.write(&entry(hour + 10));
+    log.write(&entry(hour + 3599));
+    log.write(&entry(hour + 3600));
+    log.write(&entry(hour + 7300));
+
+    let read = |suffix: &str| fs::read_to_string(format!("{}{}", path.display(), suffix));
+    assert_eq!(1, read("")?.lines().count());
+    assert_eq!(1, read(".1")?.lines().count());
+    assert_eq!(2, read(".2")?.lines().count());
+
+    Ok(())
+}
@@ -0,0 +1,155 @@
+use actix_web::http::{header, Method, StatusCode};
+use actix_web::test::TestRequest;
+use actix_web::HttpResponse;
+use rung::serve::access::{parse_htpasswd, parse_network, Access, AccessOptions};
+use std::fs;
+use std::net::IpAddr;
+use tempfile::tempdir;
+
+mod common;
+
+fn basic(credentials: &str) -> String {
+    format!("Basic {}", base64::encode(credentials))
+}
+
+#[test]
+fn parses_networks_and_htpasswd() {
+    common::setup();
+
+    assert_eq!(
+        "10.0.0.0/8",
+        parse_network("10.0.0.0/8").unwrap().to_string()
+    );
+    assert_eq!(
+        "127.0.0.1/32",
+        parse_network("127.0.0.1").unwrap().to_string()
+    );
+    assert_eq!("::1/128", parse_network("::1").unwrap().to_string());
+    assert!(parse_network("10.0.0/8").is_err());
+
+    let users = parse_htpasswd("# users\nann:$2y$05$abc\n\nbob:$2b$05$def\n").unwrap();
+    assert_eq!(
+        vec![
+            (String::from("ann"), String::from("$2y$05$abc")),
+            (String::from("bob"), String::from("$2b$05$def")),
+        ],
+        users
+    );
+    assert!(parse_htpasswd("ann:{SHA}W6ph5Mm5Pz8GgiULbPgzG37mj9g=").is_err());
+    assert!(parse_htpasswd("ann:$apr1$salt$hash").is_err());
+    assert!(parse_htpasswd("ann").is_err());
+}
+
+#[test]
+fn allows_listed_addresses() {
+    common::setup();
+
+    let access = Access::new(&AccessOptions {
+        allow: vec![String::from("192.168.1.0/24"), String::from("127.0.0.1")],
+        ..AccessOptions::default()
+    })
+    .unwrap();
+
+    let ip = |text: &str| text.parse::<IpAddr>().unwrap();
+    assert!(access.allows(ip("192.168.1.20")));
+    assert!(access.allows(ip("127.0.0.1")));
+    assert!(access.allows(ip("::ffff:192.168.1.20")));
+    assert!(!access.allows(ip("192.168.2.20")));
+    assert!(!access.allows(ip("::1")));
+
+    let access = Access::new(&AccessOptions::default()).unwrap();
+    assert!(access.allows(ip("8.8.8.8")));
+    assert!(access.authorizes(None));
+}
+
+#[test]
+fn authorizes_users() -> anyhow::Result<()> {
+    common::setup();
+
+    let dir = tempdir()?;
+    let htpasswd = dir.path().join(".htpasswd");
+    fs::write(&htpasswd, format!("bob:{}\n", bcrypt::hash("hunter2", 4)?))?;
+
+    let access = Access::new(&AccessOptions {
+        users: vec![String::from("ann:s3cr:et")],
+        htpasswd: Some(htpasswd),
+        allow: vec![],
+    })?;
+
+    assert!(access.authorizes(Some(&basic("ann:s3cr:et"))));
+    assert!(access.authorizes(Some(&basic("bob:hunter2"))));
+    // the verified credentials are cached
+    assert!(access.authorizes(Some(&basic("bob:hunter2"))));
+    assert!(!access.authorizes(Some(&basic("ann:s3cr"))));
+    assert!(!access.authorizes(Some(&basic("bob:hunter"))));
+    assert!(!access.authorizes(Some(&basic("eve:hunter2"))));
+    assert!(!access.authorizes(Some("Bearer token")));
+    assert!(!access.authorizes(None));
+
+    assert!(Access::new(&AccessOptions {
+        users: vec![String::from("ann")],
+        ..AccessOptions::default()
+    })
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn rejects_requests() {
+    common::setup();
+
+    let access = Access::new(&AccessOptions {
+        users: vec![String::from("ann:secret")],
+        htpasswd: None,
+        allow: vec![String::from("10.0.0.0/8")],
+    })
+    .unwrap();
+    let addr = Some("10.1.2.3".parse().unwrap());
+
+    let req = TestRequest::with_uri("/").to_srv_request();
+    let response = access.check(req.head(), addr, None).unwrap();
+    assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+    assert_eq!(
+        "Basic realm=\"rung\", charset=\"UTF-8\"",
+        response.headers().get(header::WWW_AUTHENTICATE).unwrap()
+    );
+
+    let req = TestRequest::with_uri("/")
+        .header(header::AUTHORIZATION, basic("ann:secret"))
+        .to_srv_request();
+    assert!(access.check(req.head(), addr, None).is_none());
+
+    let response = access
+        .check(req.head(), Some("192.168.1.1".parse().unwrap()), None)
+        .unwrap();
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+
+    // the unknown address is not in the allow-list
+    let response = access.check(req.head(), None, None).unwrap();
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+}
+
+#[test]
+fn answers_preflight_requests_locally() {
+    common::setup();
+
+    let access = Access::new(&AccessOptions {
+        users: vec![String::from("ann:secret")],
+        ..AccessOptions::default()
+    })
+    .unwrap();
+    let req = TestRequest::with_uri("/api")
+        .method(Method::OPTIONS)
+        .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+        .to_srv_request();
+
+    // without CORS the preflight request is not passed to the proxy or the mocks
+    let response = access.check(req.head(), None, None).unwrap();
+    assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+
+    // the CORS preflight requests are sent without the credentials
+    let preflight = HttpResponse::NoContent().finish();
+    let response = access.check(req.head(), None, Some(preflight)).unwrap();
+    assert_eq!(StatusCode::NO_CONTENT, response.status());
+}
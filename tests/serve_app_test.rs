@@ -0,0 +1,343 @@
+use actix_web::dev::ServiceResponse;
+use actix_web::http::{header, HeaderName, Method, StatusCode};
+use actix_web::test::{self, call_service, init_service, read_body, TestRequest};
+use actix_web::{rt, web, App, HttpRequest, HttpResponse};
+use rung::serve::access::AccessOptions;
+use rung::serve::assets::{IMMUTABLE, NO_CACHE};
+use rung::serve::headers::{CorsConfig, HeaderRule, HeadersConfig};
+use rung::serve::{create_app, Fallback, Mount, Port, ServerOptions, ServerState};
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+mod common;
+
+fn options(root_dir: &Path, fallback: Fallback) -> ServerOptions {
+    let mut mount = Mount::new("/", root_dir);
+    mount.fallback = fallback;
+
+    ServerOptions {
+        host: String::from("127.0.0.1"),
+        port: Port::Auto,
+        mounts: vec![mount],
+        locales: None,
+        open: false,
+        access: Default::default(),
+        ssl: None,
+        proxy: vec![],
+        record: None,
+        replay: None,
+        headers: Default::default(),
+        watch: false,
+        list: false,
+        metrics: false,
+        access_log: None,
+        request_id: false,
+        mocks: None,
+        mocks_prefix: String::from("/api"),
+        templates: None,
+        network: Default::default(),
+        ready_file: None,
+    }
+}
+
+fn header_value(res: &ServiceResponse, name: HeaderName) -> &str {
+    res.headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+}
+
+#[test]
+fn falls_back_to_index_for_spa_routes() -> anyhow::Result<()> {
+    common::setup();
+
+    let dir = tempdir()?;
+    fs::write(dir.path().join("index.html"), "<app-root></app-root>")?;
+    fs::write(dir.path().join("main.js"), "main")?;
+    let state = ServerState::new(options(dir.path(), Fallback::Spa))?;
+
+    rt::System::new("test").block_on(async move {
+        let mut app = init_service(create_app(&state)).await;
+
+        let req = TestRequest::with_uri("/products/1")
+            .header(header::ACCEPT, "text/html")
+            .to_request();
+        let res = call_service(&mut app, req).await;
+        assert_eq!(StatusCode::OK, res.status());
+        assert_eq!("<app-root></app-root>", read_body(res).await);
+
+        let req = TestRequest::with_uri("/main.js").to_request();
+        let res = call_service(&mut app, req).await;
+        assert_eq!(StatusCode::OK, res.status());
+        assert_eq!("main", read_body(res).await);
+
+        // missing assets are not answered with the page
+        let req = TestRequest::with_uri("/missing.js").to_request();
+        let res = call_service(&mut app, req).await;
+        assert_eq!(StatusCode::NOT_FOUND, res.status());
+    });
+
+    Ok(())
+}
+
+#[test]
+fn answers_unknown_routes_by_fallback() -> anyhow::Result<()> {
+    common::setup();
+
+    let dir = tempdir()?;
+    fs::write(dir.path().join("index.html"), "<app-root></app-root>")?;
+    let index = ServerState::new(options(dir.path(), Fallback::Index))?;
+    let strict = ServerState::new(options(dir.path(), Fallback::Strict))?;
+
+    rt::System::new("test").block_on(async move {
+        let mut app = init_service(create_app(&index)).await;
+        let req = TestRequest::with_uri("/products/1")
+            .header(header::ACCEPT, "text/html")
+            .to_request();
+        let res = call_service(&mut app, req).await;
+        assert_eq!(StatusCode::NOT_FOUND, res.status());
+        assert_eq!("<app-root></app-root>", read_body(res).await);
+
+        let mut app = init_service(create_app(&strict)).await;
+        let req = TestRequest::with_uri("/products/1")
+            .header(header::ACCEPT, "text/html")
+            .to_request();
+        let res = call_service(&mut app, req).await;
+        assert_eq!(StatusCode::NOT_FOUND, res.status());
+        assert!(read_body(res).await.is_empty());
+    });
+
+    Ok(())
+}
+
+#[test]
+fn serves_precompressed_files_with_cache_headers() -> anyhow::Result<()> {
+    common::setup();
+
+    let dir = tempdir()?;
+    fs::write(dir.path().join("index.html"), "<app-root></app-root>")?;
+    fs::write(dir.path().join("main.3f2a1b4c5d6e7f80.js"), "main")?;
+    fs::write(dir.path().join("main.3f2a1b4c5d6e7f80.js.br"), "brotli")?;
+    fs::write(dir.path().join("main.3f2a1b4c5d6e7f80.js.gz"), "gzip")?;
+    let state = ServerState::new(options(dir.path(), Fallback::Spa))?;
+
+    rt::System::new("test").block_on(async move {
+        let mut app = init_service(create_app(&state)).await;
+
+        let request = |accept_encoding: &str| {
+            TestRequest::with_uri("/main.3f2a1b4c5d6e7f80.js")
+                .header(header::ACCEPT_ENCODING, accept_encoding)
+                .to_request()
+        };
+
+        let res = call_service(&mut app, request("gzip, deflate, br")).await;
+        assert_eq!(StatusCode::OK, res.status());
+        assert_eq!("br", header_value(&res, header::CONTENT_ENCODING));
+        assert_eq!(IMMUTABLE, header_value(&res, header::CACHE_CONTROL));
+        assert_eq!("brotli", read_body(res).await);
+
+        let res = call_service(&mut app, request("gzip")).await;
+        assert_eq!("gzip", header_value(&res, header::CONTENT_ENCODING));
+        assert_eq!("gzip", read_body(res).await);
+
+        let res = call_service(&mut app, request("identity")).await;
+        assert!(res.headers().get(header::CONTENT_ENCODING).is_none());
+        assert_eq!(IMMUTABLE, header_value(&res, header::CACHE_CONTROL));
+        assert_eq!("main", read_body(res).await);
+
+        // the page is revalidated, a missing hashed file is never cached
+        let req = TestRequest::with_uri("/").to_request();
+        let res = call_service(&mut app, req).await;
+        assert_eq!(NO_CACHE, header_value(&res, header::CACHE_CONTROL));
+
+        let req = TestRequest::with_uri("/main.0123456789abcdef.js").to_request();
+        let res = call_service(&mut app, req).await;
+        assert_eq!(StatusCode::NOT_FOUND, res.status());
+        assert_eq!(NO_CACHE, header_value(&res, header::CACHE_CONTROL));
+    });
+
+    Ok(())
+}
+
+#[test]
+fn adds_headers_to_mocked_responses() -> anyhow::Result<()> {
+    common::setup();
+
+    let dir = tempdir()?;
+    fs::write(dir.path().join("index.html"), "<app-root></app-root>")?;
+    let mocks_dir = tempdir()?;
+    fs::create_dir_all(mocks_dir.path().join("api"))?;
+    fs::write(mocks_dir.path().join("api/users.json"), r#"[{ "id": 1 }]"#)?;
+
+    let mut options = options(dir.path(), Fallback::Spa);
+    options.mocks = Some(mocks_dir.path().to_path_buf());
+    options.headers = HeadersConfig {
+        rules: vec![HeaderRule {
+            source: String::from("/api/**"),
+            headers: vec![(String::from("X-Api"), String::from("mock"))]
+                .into_iter()
+                .collect(),
+        }],
+        cors: Some(CorsConfig {
+            origins: vec![String::from("http://localhost:4200")],
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let state = ServerState::new(options)?;
+
+    rt::System::new("test").block_on(async move {
+        let mut app = init_service(create_app(&state)).await;
+
+        let req = TestRequest::with_uri("/api/users")
+            .header(header::ORIGIN, "http://localhost:4200")
+            .to_request();
+        let res = call_service(&mut app, req).await;
+        assert_eq!(StatusCode::OK, res.status());
+        assert_eq!("mock", header_value(&res, HeaderName::from_static("x-api")));
+        assert_eq!(
+            "http://localhost:4200",
+            header_value(&res, header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        );
+
+        // the preflight requests are not answered by the mocks
+        let req = TestRequest::with_uri("/api/users")
+            .method(Method::OPTIONS)
+            .header(header::ORIGIN, "http://localhost:4200")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .to_request();
+        let res = call_service(&mut app, req).await;
+        assert_eq!(StatusCode::NO_CONTENT, res.status());
+        assert_eq!(
+            "GET, HEAD, OPTIONS",
+            header_value(&res, header::ACCESS_CONTROL_ALLOW_METHODS)
+        );
+    });
+
+    Ok(())
+}
+
+async fn users(req: HttpRequest) -> HttpResponse {
+    let query = req.query_string().to_string();
+    HttpResponse::Ok().header("X-Backend", "users").body(query)
+}
+
+#[test]
+fn forwards_proxied_requests() -> anyhow::Result<()> {
+    common::setup();
+
+    let dir = tempdir()?;
+    fs::write(dir.path().join("index.html"), "<app-root></app-root>")?;
+
+    rt::System::new("test").block_on(async move {
+        let backend = test::start(|| App::new().route("/users", web::get().to(users)));
+
+        let mut options = options(dir.path(), Fallback::Spa);
+        options.proxy = vec![serde_json::from_value(json!({
+            "context": ["/api"],
+            "target": format!("http://{}", backend.addr()),
+            "pathRewrite": { "^/api": "" },
+        }))
+        .unwrap()];
+        options.headers.rules = vec![HeaderRule {
+            source: String::from("/api/**"),
+            headers: vec![(String::from("X-Api"), String::from("proxy"))]
+                .into_iter()
+                .collect(),
+        }];
+        let state = ServerState::new(options).unwrap();
+        let mut app = init_service(create_app(&state)).await;
+
+        let req = TestRequest::with_uri("/api/users?page=2").to_request();
+        let res = call_service(&mut app, req).await;
+        assert_eq!(StatusCode::OK, res.status());
+        assert_eq!(
+            "users",
+            header_value(&res, HeaderName::from_static("x-backend"))
+        );
+        assert_eq!(
+            "proxy",
+            header_value(&res, HeaderName::from_static("x-api"))
+        );
+        assert_eq!("page=2", read_body(res).await);
+
+        // the other requests are not proxied
+        let req = TestRequest::with_uri("/").to_request();
+        let res = call_service(&mut app, req).await;
+        assert_eq!("<app-root></app-root>", read_body(res).await);
+    });
+
+    Ok(())
+}
+
+#[test]
+fn checks_access_before_answering() -> anyhow::Result<()> {
+    common::setup();
+
+    let dir = tempdir()?;
+    fs::write(dir.path().join("index.html"), "<app-root></app-root>")?;
+    let mocks_dir = tempdir()?;
+    fs::create_dir_all(mocks_dir.path().join("api"))?;
+    fs::write(mocks_dir.path().join("api/users.json"), r#"[{ "id": 1 }]"#)?;
+
+    let mut options = options(dir.path(), Fallback::Spa);
+    options.mocks = Some(mocks_dir.path().to_path_buf());
+    options.access = AccessOptions {
+        users: vec![String::from("ann:secret")],
+        allow: vec![String::from("127.0.0.1")],
+        ..Default::default()
+    };
+    options.headers.cors = Some(CorsConfig {
+        origins: vec![String::from("*")],
+        ..Default::default()
+    });
+    let state = ServerState::new(options)?;
+
+    rt::System::new("test").block_on(async move {
+        let mut app = init_service(create_app(&state)).await;
+        let request = |peer_addr: &str| {
+            TestRequest::with_uri("/api/users").peer_addr(peer_addr.parse().unwrap())
+        };
+
+        let req = request("127.0.0.1:4200").to_request();
+        let res = call_service(&mut app, req).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, res.status());
+
+        let req = request("127.0.0.1:4200")
+            .header(header::AUTHORIZATION, "Basic YW5uOnNlY3JldA==")
+            .to_request();
+        let res = call_service(&mut app, req).await;
+        assert_eq!(StatusCode::OK, res.status());
+        assert_eq!(r#"[{"id":1}]"#, read_body(res).await);
+
+        // the allow-list is checked before the credentials
+        let req = request("10.0.0.1:4200")
+            .header(header::AUTHORIZATION, "Basic YW5uOnNlY3JldA==")
+            .to_request();
+        let res = call_service(&mut app, req).await;
+        assert_eq!(StatusCode::FORBIDDEN, res.status());
+
+        let req = TestRequest::with_uri("/api/users").to_request();
+        let res = call_service(&mut app, req).await;
+        assert_eq!(StatusCode::FORBIDDEN, res.status());
+
+        // the preflight requests without the credentials get the preflight response
+        let preflight = |peer_addr: &str| {
+            request(peer_addr)
+                .method(Method::OPTIONS)
+                .header(header::ORIGIN, "http://localhost:4200")
+                .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+                .to_request()
+        };
+        let res = call_service(&mut app, preflight("127.0.0.1:4200")).await;
+        assert_eq!(StatusCode::NO_CONTENT, res.status());
+        assert_eq!("*", header_value(&res, header::ACCESS_CONTROL_ALLOW_ORIGIN));
+
+        let res = call_service(&mut app, preflight("10.0.0.1:4200")).await;
+        assert_eq!(StatusCode::FORBIDDEN, res.status());
+    });
+
+    Ok(())
+}
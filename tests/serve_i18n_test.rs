@@ -0,0 +1,116 @@
+use actix_web::http::{header, StatusCode};
+use actix_web::test::TestRequest;
+use rung::serve::i18n::{accepted_languages, LocaleOptions, LocaleRoute};
+
+mod common;
+
+fn locales(root: &str) -> LocaleOptions {
+    LocaleOptions {
+        routes: vec![
+            LocaleRoute {
+                locale: String::from("en-US"),
+                prefix: format!("{}en-US", root),
+            },
+            LocaleRoute {
+                locale: String::from("de"),
+                prefix: format!("{}deutsch", root),
+            },
+            LocaleRoute {
+                locale: String::from("fr-CA"),
+                prefix: format!("{}fr-CA", root),
+            },
+        ],
+        cookie: String::from("locale"),
+    }
+}
+
+#[test]
+fn prefers_cookie_and_accept_language() {
+    common::setup();
+
+    assert_eq!(
+        vec!["de-AT", "de", "en"],
+        accepted_languages("en;q=0.5, de-AT, de;q=0.9, fr;q=0")
+    );
+
+    let locales = locales("/");
+    assert_eq!("/", locales.root());
+    assert_eq!("en-US", locales.preferred(None, None).locale);
+    assert_eq!("de", locales.preferred(None, Some("de-AT,en;q=0.8")).locale);
+    assert_eq!("fr-CA", locales.preferred(None, Some("fr")).locale);
+    assert_eq!("en-US", locales.preferred(None, Some("ja, *;q=0.1")).locale);
+    // the cookie holds the locale or its base href
+    assert_eq!("de", locales.preferred(Some("deutsch"), Some("fr")).locale);
+    assert_eq!("fr-CA", locales.preferred(Some("FR-ca"), None).locale);
+    assert_eq!("fr-CA", locales.preferred(Some("es"), Some("fr")).locale);
+}
+
+#[test]
+fn finds_locales_with_mixed_prefix_depth() {
+    common::setup();
+
+    // the source locale is served at the root of the application
+    let locales = LocaleOptions {
+        routes: vec![
+            LocaleRoute {
+                locale: String::from("de"),
+                prefix: String::from("/shop/de"),
+            },
+            LocaleRoute {
+                locale: String::from("en-US"),
+                prefix: String::from("/shop"),
+            },
+        ],
+        cookie: String::from("locale"),
+    };
+
+    assert_eq!("/shop/", locales.root());
+    assert_eq!("de", locales.preferred(Some("DE"), None).locale);
+    assert_eq!("de", locales.preferred(Some("unknown"), Some("de")).locale);
+    assert_eq!("de", locales.preferred(Some("unknown"), None).locale);
+}
+
+#[test]
+fn redirects_to_preferred_locale() {
+    common::setup();
+
+    let locales = locales("/shop/");
+    assert_eq!("/shop/", locales.root());
+
+    let location = |path: &str, accept_language: &str, is_navigation: bool| {
+        let req = TestRequest::with_uri(path)
+            .header(header::ACCEPT_LANGUAGE, accept_language)
+            .header(header::COOKIE, "theme=dark")
+            .to_srv_request();
+        locales.redirect(req.head(), is_navigation).map(|response| {
+            assert_eq!(StatusCode::FOUND, response.status());
+            response
+                .headers()
+                .get(header::LOCATION)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string()
+        })
+    };
+
+    assert_eq!(
+        Some(String::from("/shop/deutsch/")),
+        location("/shop/", "de", false)
+    );
+    assert_eq!(
+        Some(String::from("/shop/en-US/")),
+        location("/shop", "ja", false)
+    );
+    assert_eq!(
+        Some(String::from("/shop/fr-CA/products/1?page=2")),
+        location("/shop/products/1?page=2", "fr", true)
+    );
+    // the assets and the locales are not redirected
+    assert_eq!(None, location("/shop/products/1", "fr", false));
+    assert_eq!(None, location("/shop/deutsch/products/1", "fr", true));
+    assert_eq!(None, location("/other", "fr", true));
+
+    let req = TestRequest::post().uri("/shop/").to_srv_request();
+    assert!(locales.redirect(req.head(), true).is_none());
+}
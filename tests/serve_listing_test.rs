@@ -0,0 +1,126 @@
+use rung::serve::listing::{
+    read_entries, render_html, render_json, resolve_dir, sort_entries, Entry, SortKey, Sorting,
+};
+use rung::utils::format_size;
+use std::fs;
+use std::time::{Duration, SystemTime};
+use tempfile::tempdir;
+
+mod common;
+
+fn entry(name: &str, is_dir: bool, size: u64, modified: u64) -> Entry {
+    Entry {
+        name: name.to_string(),
+        is_dir,
+        size,
+        modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(modified)),
+    }
+}
+
+fn names(entries: &[Entry]) -> Vec<&str> {
+    entries.iter().map(|entry| entry.name.as_str()).collect()
+}
+
+#[test]
+fn reads_directories() -> anyhow::Result<()> {
+    common::setup();
+
+    let dir = tempdir()?;
+    fs::create_dir_all(dir.path().join("lcov report"))?;
+    fs::write(dir.path().join("lcov.info"), "TN:")?;
+    fs::write(dir.path().join(".DS_Store"), "")?;
+
+    assert_eq!(
+        Some(dir.path().join("lcov report")),
+        resolve_dir(dir.path(), "/lcov%20report/")
+    );
+    assert_eq!(Some(dir.path().to_path_buf()), resolve_dir(dir.path(), "/"));
+    assert_eq!(None, resolve_dir(dir.path(), "/lcov.info"));
+    assert_eq!(None, resolve_dir(dir.path(), "/lcov%20report/.."));
+    assert_eq!(None, resolve_dir(dir.path(), "/..%2F.."));
+
+    let mut entries = read_entries(dir.path())?;
+    sort_entries(&mut entries, Sorting::default());
+    assert_eq!(vec!["lcov report", "lcov.info"], names(&entries));
+    assert!(entries[0].is_dir);
+    assert_eq!(3, entries[1].size);
+
+    Ok(())
+}
+
+#[test]
+fn sorts_entries() {
+    common::setup();
+
+    let mut entries = vec![
+        entry("b.js", false, 10, 3),
+        entry("assets", true, 0, 1),
+        entry("A.css", false, 300, 2),
+        entry("c.js", false, 10, 1),
+    ];
+
+    sort_entries(&mut entries, Sorting::default());
+    assert_eq!(vec!["assets", "A.css", "b.js", "c.js"], names(&entries));
+
+    let sorting = Sorting::from_query("sort=size&order=desc");
+    assert_eq!(
+        Sorting {
+            key: SortKey::Size,
+            descending: true
+        },
+        sorting
+    );
+    sort_entries(&mut entries, sorting);
+    assert_eq!(vec!["assets", "A.css", "c.js", "b.js"], names(&entries));
+
+    sort_entries(&mut entries, Sorting::from_query("sort=modified"));
+    assert_eq!(vec!["assets", "c.js", "A.css", "b.js"], names(&entries));
+
+    assert_eq!(
+        Sorting::default(),
+        Sorting::from_query("sort=type&order=up")
+    );
+}
+
+#[test]
+fn renders_listings() {
+    common::setup();
+
+    let entries = vec![
+        entry("lcov report", true, 0, 0),
+        entry("<main>.js", false, 1536, 86400),
+    ];
+
+    let html = render_html(
+        "/coverage/",
+        &entries,
+        Sorting::from_query("sort=size"),
+        true,
+    );
+    assert!(html.contains("<title>Index of /coverage/</title>"));
+    assert!(html.contains(r#"<a href="../">../</a>"#));
+    assert!(html.contains(r#"<a href="lcov%20report/">lcov report/</a>"#));
+    assert!(html.contains(r#"<a href="%3Cmain%3E.js">&lt;main&gt;.js</a>"#));
+    assert!(html.contains(">1.5 KB<"));
+    assert!(html.contains(r#"<time datetime="1970-01-02T00:00:00Z">1970-01-02 00:00:00</time>"#));
+    // the current column toggles the order
+    assert!(html.contains(r#"<a href="?sort=size&amp;order=desc">Size</a> ▲"#));
+    assert!(html.contains(r#"<a href="?sort=name&amp;order=asc">Name</a></th>"#));
+
+    let html = render_html("/", &entries, Sorting::default(), false);
+    assert!(!html.contains(r#"href="../""#));
+
+    assert_eq!(
+        serde_json::json!({
+            "path": "/coverage/",
+            "entries": [
+                { "name": "lcov report", "type": "directory", "size": 0, "modified": "1970-01-01T00:00:00Z" },
+                { "name": "<main>.js", "type": "file", "size": 1536, "modified": "1970-01-02T00:00:00Z" }
+            ]
+        }),
+        render_json("/coverage/", &entries)
+    );
+
+    assert_eq!("512 B", format_size(512));
+    assert_eq!("2.0 MB", format_size(2 * 1024 * 1024));
+}
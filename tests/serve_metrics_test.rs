@@ -0,0 +1,49 @@
+use actix_web::http::{Method, StatusCode};
+use rung::serve::metrics::Metrics;
+use std::time::Duration;
+
+mod common;
+
+#[test]
+fn counts_requests() {
+    common::setup();
+
+    let metrics = Metrics::default();
+    let ms = Duration::from_millis;
+    metrics.record(&Method::GET, "/main.js", StatusCode::OK, ms(3));
+    metrics.record(&Method::GET, "/main.js", StatusCode::NOT_MODIFIED, ms(1));
+    metrics.record(&Method::GET, "/chunk-\"a\".js", StatusCode::OK, ms(40));
+    metrics.record(&Method::GET, "/missing.js", StatusCode::NOT_FOUND, ms(2));
+    metrics.record(&Method::POST, "/api/users", StatusCode::CREATED, ms(12_000));
+    metrics.add_bytes(1024);
+    metrics.add_bytes(512);
+
+    assert_eq!(5, metrics.requests());
+    assert!(Metrics::is_counted("/main.js"));
+    assert!(!Metrics::is_counted("/__rung/health"));
+
+    let text = metrics.render();
+    for line in &[
+        "# TYPE rung_requests_total counter",
+        "rung_requests_total{method=\"GET\",status=\"200\"} 2",
+        "rung_requests_total{method=\"GET\",status=\"304\"} 1",
+        "rung_requests_total{method=\"GET\",status=\"404\"} 1",
+        "rung_requests_total{method=\"POST\",status=\"201\"} 1",
+        "rung_response_bytes_total 1536",
+        "rung_path_requests_total{path=\"/main.js\"} 2",
+        "rung_path_requests_total{path=\"/chunk-\\\"a\\\".js\"} 1",
+        "# TYPE rung_request_duration_seconds histogram",
+        "rung_request_duration_seconds_bucket{le=\"0.001\"} 1",
+        "rung_request_duration_seconds_bucket{le=\"0.005\"} 3",
+        "rung_request_duration_seconds_bucket{le=\"0.05\"} 4",
+        "rung_request_duration_seconds_bucket{le=\"10\"} 4",
+        "rung_request_duration_seconds_bucket{le=\"+Inf\"} 5",
+        "rung_request_duration_seconds_sum 12.046000",
+        "rung_request_duration_seconds_count 5",
+    ] {
+        assert!(text.lines().any(|text_line| text_line == *line), "{}", line);
+    }
+    // only the successful reads are counted by the path
+    assert!(!text.contains("/missing.js"));
+    assert!(!text.contains("/api/users"));
+}
@@ -0,0 +1,154 @@
+use actix_web::http::{Method, StatusCode};
+use rung::serve::mock::MockApi;
+use serde_json::json;
+use std::fs;
+use tempfile::tempdir;
+
+mod common;
+
+#[test]
+fn answers_from_fixtures() -> anyhow::Result<()> {
+    common::setup();
+
+    let dir = tempdir()?;
+    fs::create_dir_all(dir.path().join("api/orders"))?;
+    fs::write(
+        dir.path().join("api/users.json"),
+        r#"[{ "id": 1, "name": "Ann" }]"#,
+    )?;
+    fs::write(
+        dir.path().join("api/users.meta.json"),
+        r#"{ "status": 203, "headers": { "X-Total-Count": "1" } }"#,
+    )?;
+    fs::write(
+        dir.path().join("api/orders/[id].json"),
+        r#"{ "id": "{{id}}", "total": 10 }"#,
+    )?;
+
+    let api = MockApi::new(dir.path(), "/api");
+
+    let response = api.handle(&Method::GET, "/api/users", None).unwrap();
+    assert_eq!(StatusCode::NON_AUTHORITATIVE_INFORMATION, response.status);
+    assert_eq!(
+        Some(&String::from("1")),
+        response.headers.get("X-Total-Count")
+    );
+    assert_eq!(Some(json!([{ "id": 1, "name": "Ann" }])), response.body);
+
+    let response = api.handle(&Method::GET, "/api/orders/42", None).unwrap();
+    assert_eq!(Some(json!({ "id": "42", "total": 10 })), response.body);
+
+    assert!(api.handle(&Method::GET, "/api/products", None).is_none());
+    assert!(!api.matches("/index.html"));
+
+    Ok(())
+}
+
+#[test]
+fn changes_collections_in_memory() -> anyhow::Result<()> {
+    common::setup();
+
+    let dir = tempdir()?;
+    let fixture = dir.path().join("users.json");
+    fs::write(
+        &fixture,
+        r#"[{ "id": 1, "name": "Ann" }, { "id": 2, "name": "Bob" }]"#,
+    )?;
+
+    let api = MockApi::new(dir.path(), "/");
+
+    let created = api
+        .handle(&Method::POST, "/users", Some(json!({ "name": "Cid" })))
+        .unwrap();
+    assert_eq!(StatusCode::CREATED, created.status);
+    assert_eq!(Some(json!({ "name": "Cid", "id": 3 })), created.body);
+
+    let updated = api
+        .handle(&Method::PUT, "/users/1", Some(json!({ "name": "Anna" })))
+        .unwrap();
+    assert_eq!(Some(json!({ "name": "Anna", "id": 1 })), updated.body);
+
+    let deleted = api.handle(&Method::DELETE, "/users/2", None).unwrap();
+    assert_eq!(StatusCode::NO_CONTENT, deleted.status);
+
+    let missing = api.handle(&Method::GET, "/users/2", None).unwrap();
+    assert_eq!(StatusCode::NOT_FOUND, missing.status);
+
+    let users = api.handle(&Method::GET, "/users", None).unwrap();
+    assert_eq!(
+        Some(json!([{ "name": "Anna", "id": 1 }, { "name": "Cid", "id": 3 }])),
+        users.body
+    );
+
+    // fixtures on disk are not changed
+    assert!(fs::read_to_string(&fixture)?.contains("Bob"));
+
+    Ok(())
+}
+
+#[test]
+fn mocks_only_the_prefix() -> anyhow::Result<()> {
+    common::setup();
+
+    let dir = tempdir()?;
+    fs::write(dir.path().join("[page].json"), r#"{ "page": "{{page}}" }"#)?;
+    fs::create_dir(dir.path().join("api"))?;
+    fs::write(
+        dir.path().join("api/[name].json"),
+        r#"{ "name": "{{name}}" }"#,
+    )?;
+
+    let api = MockApi::new(dir.path(), "/api/");
+    assert!(api.matches("/api/users"));
+    assert!(!api.matches("/main.js"));
+    assert!(!api.matches("/apis"));
+    assert!(!api.matches("/assets/logo.png"));
+
+    // the fixtures are read once, until refreshed
+    fs::write(dir.path().join("api/orders.json"), r#"[]"#)?;
+    let response = api.handle(&Method::GET, "/api/orders", None).unwrap();
+    assert_eq!(Some(json!({ "name": "orders" })), response.body);
+
+    api.refresh();
+    let response = api.handle(&Method::GET, "/api/orders", None).unwrap();
+    assert_eq!(Some(json!([])), response.body);
+
+    Ok(())
+}
+
+#[test]
+fn keeps_changes_by_param() -> anyhow::Result<()> {
+    common::setup();
+
+    let dir = tempdir()?;
+    fs::create_dir_all(dir.path().join("api/orders"))?;
+    fs::write(
+        dir.path().join("api/orders/[id].json"),
+        r#"{ "id": "{{id}}", "total": 10 }"#,
+    )?;
+
+    let api = MockApi::new(dir.path(), "/api");
+
+    let response = api
+        .handle(
+            &Method::PUT,
+            "/api/orders/1",
+            Some(json!({ "id": "1", "total": 20 })),
+        )
+        .unwrap();
+    assert_eq!(StatusCode::OK, response.status);
+
+    let response = api.handle(&Method::GET, "/api/orders/1", None).unwrap();
+    assert_eq!(Some(json!({ "id": "1", "total": 20 })), response.body);
+    let response = api.handle(&Method::GET, "/api/orders/2", None).unwrap();
+    assert_eq!(Some(json!({ "id": "2", "total": 10 })), response.body);
+
+    // the changed fixture file replaces the in-memory state
+    let fixture = dir.path().canonicalize()?.join("api/orders/[id].json");
+    fs::write(&fixture, r#"{ "id": "{{id}}", "total": 30 }"#)?;
+    api.evict(&fixture);
+    let response = api.handle(&Method::GET, "/api/orders/1", None).unwrap();
+    assert_eq!(Some(json!({ "id": "1", "total": 30 })), response.body);
+
+    Ok(())
+}
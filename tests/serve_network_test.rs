@@ -0,0 +1,131 @@
+use actix_web::http::StatusCode;
+use actix_web::test::{call_service, init_service, read_body, TestRequest};
+use actix_web::{rt, web, App, HttpResponse};
+use rung::serve::network::{
+    read_network_config, Network, NetworkConfig, NetworkRule, Simulation, Throttle,
+};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tempfile::NamedTempFile;
+
+mod common;
+
+#[test]
+fn merges_matching_rules() -> anyhow::Result<()> {
+    common::setup();
+
+    let mut file = NamedTempFile::new()?;
+    write!(
+        file,
+        r#"{{
+            "rules": [
+                {{ "source": "**", "throttle": "slow-3g" }},
+                {{ "source": "/api/**", "latency": 100, "failRate": 10, "failStatus": [500] }}
+            ]
+        }}"#
+    )?;
+
+    let config = read_network_config(file.path())?;
+    let network = Network::new(&config)?;
+
+    let page = network.conditions("/index.html").unwrap();
+    assert_eq!(Duration::from_millis(2000), page.latency);
+    assert_eq!(Some(50_000), page.bytes_per_second);
+    assert_eq!(0.0, page.fail_rate);
+
+    let api = network.conditions("/api/users").unwrap();
+    assert_eq!(Duration::from_millis(100), api.latency);
+    assert_eq!(Some(50_000), api.bytes_per_second);
+    assert_eq!(vec![StatusCode::INTERNAL_SERVER_ERROR], api.fail_status);
+
+    assert_eq!(None, network.conditions("/__rung/livereload"));
+
+    assert_eq!(Ok(Throttle::Rate(64)), "64".parse());
+    assert!("0".parse::<Throttle>().is_err());
+    assert!("edge".parse::<Throttle>().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn fails_percentage_of_requests() -> anyhow::Result<()> {
+    common::setup();
+
+    let config = NetworkConfig {
+        rules: vec![NetworkRule {
+            source: String::from("**"),
+            fail_rate: Some(25.0),
+            fail_status: vec![500, 503],
+            ..Default::default()
+        }],
+        seed: Some(42),
+    };
+    let network = Network::new(&config)?;
+    let conditions = network.conditions("/api/users").unwrap();
+
+    let failures: Vec<StatusCode> = (0..1000)
+        .filter_map(|_| network.failure(&conditions))
+        .collect();
+    assert!((150..350).contains(&failures.len()));
+    assert!(failures.contains(&StatusCode::INTERNAL_SERVER_ERROR));
+    assert!(failures.contains(&StatusCode::SERVICE_UNAVAILABLE));
+
+    // the same seed fails the same requests
+    let again = Network::new(&config)?;
+    let repeated: Vec<StatusCode> = (0..1000)
+        .filter_map(|_| again.failure(&conditions))
+        .collect();
+    assert_eq!(failures, repeated);
+
+    let invalid = NetworkConfig {
+        rules: vec![NetworkRule {
+            source: String::from("**"),
+            fail_rate: Some(120.0),
+            ..Default::default()
+        }],
+        seed: None,
+    };
+    assert!(Network::new(&invalid).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn handles_requests_after_latency() -> anyhow::Result<()> {
+    common::setup();
+
+    let config = NetworkConfig {
+        rules: vec![NetworkRule {
+            source: String::from("/api/**"),
+            latency: Some(200),
+            ..Default::default()
+        }],
+        seed: None,
+    };
+    let network = Network::new(&config)?;
+    let handled = Arc::new(Mutex::new(None));
+    let handled_at = handled.clone();
+
+    rt::System::new("test").block_on(async move {
+        let mut app = init_service(App::new().wrap(Simulation::new(network)).route(
+            "/api/users",
+            web::get().to(move || {
+                *handled_at.lock().unwrap() = Some(Instant::now());
+                HttpResponse::Ok().body("[]")
+            }),
+        ))
+        .await;
+
+        let start = Instant::now();
+        let res = call_service(&mut app, TestRequest::with_uri("/api/users").to_request()).await;
+        assert_eq!(StatusCode::OK, res.status());
+        assert_eq!("[]", read_body(res).await);
+
+        // the handler runs once the latency elapses
+        let handled = handled.lock().unwrap().unwrap();
+        assert!(handled.duration_since(start) >= Duration::from_millis(200));
+    });
+
+    Ok(())
+}
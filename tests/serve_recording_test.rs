@@ -0,0 +1,89 @@
+use actix_web::http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use rung::serve::recording::{
+    MatchOptions, RecordedRequest, RecordedResponse, Recorder, Recording, Replay, ReplayOptions,
+};
+use tempfile::tempdir;
+
+mod common;
+
+fn recording(method: Method, query: &str, body: &[u8], response: &[u8]) -> Recording {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        HeaderName::from_static("content-type"),
+        HeaderValue::from_static("application/json"),
+    );
+
+    Recording {
+        request: RecordedRequest::new(&method, "/api/users", query, body),
+        response: RecordedResponse::new(StatusCode::OK, &headers, response),
+    }
+}
+
+#[test]
+fn saves_and_replays_recordings() -> anyhow::Result<()> {
+    common::setup();
+
+    let dir = tempdir()?;
+    let recorder = Recorder::new(dir.path().join("recordings"))?;
+
+    let list = recording(Method::GET, "page=1", b"", br#"[{"id":1}]"#);
+    let created = recording(Method::POST, "", br#"{"name":"Ann"}"#, &[0xff, 0x00]);
+    recorder.save(&list)?;
+    recorder.save(&created)?;
+    // the same request replaces the recording
+    let file = recorder.save(&list)?;
+    assert!(file.ends_with(list.file_name()));
+    assert_eq!(2, std::fs::read_dir(dir.path().join("recordings"))?.count());
+
+    assert_eq!(Some(String::from(r#"[{"id":1}]"#)), list.response.body);
+    assert_eq!(None, created.response.body);
+    assert_eq!(&[0xff, 0x00], &created.response.body()?[..]);
+
+    let replay = Replay::load(&ReplayOptions {
+        dir: dir.path().join("recordings"),
+        matching: MatchOptions::default(),
+    })?;
+    assert!(replay.has_path("/api/users"));
+    assert!(!replay.has_path("/api/orders"));
+
+    let request = RecordedRequest::new(&Method::GET, "/api/users", "page=1", b"");
+    assert_eq!(Some(&list), replay.find(&request));
+    let request = RecordedRequest::new(&Method::GET, "/api/users", "page=2", b"");
+    assert_eq!(None, replay.find(&request));
+
+    let request = RecordedRequest::new(&Method::POST, "/api/users", "", br#"{"name":"Bob"}"#);
+    assert_eq!(Some(&created), replay.find(&request));
+
+    Ok(())
+}
+
+#[test]
+fn matches_configured_fields() {
+    common::setup();
+
+    assert_eq!(
+        Ok(MatchOptions {
+            method: true,
+            path: true,
+            query: false,
+            body: true
+        }),
+        "method, path,body".parse()
+    );
+    assert!("method,headers".parse::<MatchOptions>().is_err());
+
+    let recorded = RecordedRequest::new(&Method::POST, "/api/search", "q=1", b"{}");
+    let request = RecordedRequest::new(&Method::POST, "/api/search", "q=2", b"{}");
+    assert!(!MatchOptions::default().matches(&recorded, &request));
+    assert!("method,path,body"
+        .parse::<MatchOptions>()
+        .unwrap()
+        .matches(&recorded, &request));
+
+    let request = RecordedRequest::new(&Method::POST, "/api/search", "q=1", b"{\"a\":1}");
+    assert!(MatchOptions::default().matches(&recorded, &request));
+    assert!(!"path,body"
+        .parse::<MatchOptions>()
+        .unwrap()
+        .matches(&recorded, &request));
+}
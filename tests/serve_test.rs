@@ -0,0 +1,543 @@
+use actix_web::http::{header, StatusCode};
+use actix_web::test::TestRequest;
+use actix_web::HttpResponse;
+use rung::angular::{read_config, BuildOutput};
+use rung::serve::assets::{
+    accepted_encodings, cache_control, is_hashed, precompressed, set_cache_headers, IMMUTABLE,
+    NO_CACHE,
+};
+use rung::serve::headers::{describe_violations, parse_header, read_headers_config, Headers};
+use rung::serve::proxy::{check_proxy, read_proxy_config, ProxyRule};
+use rung::serve::reload::{inject_client, Reload};
+use rung::serve::ssl::generate_certificate;
+use rung::serve::{bind_listener, find_mount, Fallback, Mount, Port};
+use serde_json::json;
+use std::io::Write;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use tempfile::{tempdir, NamedTempFile};
+
+mod common;
+
+#[test]
+fn resolves_fallback_pages() {
+    common::setup();
+
+    let root = Path::new("dist");
+    let index = root.join("index.html");
+
+    assert_eq!(
+        Some((index.clone(), StatusCode::NOT_FOUND)),
+        Fallback::Index.resolve(root, "/deep/link", false)
+    );
+    assert_eq!(
+        Some((index, StatusCode::OK)),
+        Fallback::Spa.resolve(root, "/deep/link", true)
+    );
+    assert_eq!(None, Fallback::Spa.resolve(root, "/api/users", false));
+    assert_eq!(
+        Some((root.join("404.html"), StatusCode::NOT_FOUND)),
+        Fallback::NotFound(PathBuf::from("404.html")).resolve(root, "/missing", true)
+    );
+    assert_eq!(None, Fallback::Strict.resolve(root, "/deep/link", true));
+}
+
+#[test]
+fn does_not_fall_back_for_missing_assets() {
+    common::setup();
+
+    let root = Path::new("dist");
+
+    assert_eq!(
+        None,
+        Fallback::Index.resolve(root, "/main.3f2a1b4c5d6e7f80.js", true)
+    );
+    assert_eq!(None, Fallback::Spa.resolve(root, "/styles.css", true));
+    assert_eq!(
+        None,
+        Fallback::NotFound(PathBuf::from("404.html")).resolve(root, "/app.js", true)
+    );
+}
+
+#[test]
+fn generates_self_signed_certificate() -> anyhow::Result<()> {
+    common::setup();
+
+    let (cert, key) = generate_certificate("dev.local")?;
+
+    let dns_names: Vec<_> = cert
+        .subject_alt_names()
+        .unwrap()
+        .iter()
+        .filter_map(|name| name.dnsname().map(String::from))
+        .collect();
+
+    assert_eq!(vec!["localhost", "dev.local"], dns_names);
+    assert!(cert.verify(&key)?);
+
+    Ok(())
+}
+
+#[test]
+fn reads_proxy_config() -> anyhow::Result<()> {
+    common::setup();
+
+    let mut file = NamedTempFile::new()?;
+    write!(
+        file,
+        r#"{{
+            "/api": {{
+                "target": "http://localhost:3000/",
+                "pathRewrite": {{ "^/api": "" }},
+                "changeOrigin": true
+            }},
+            "/assets/**/*.json": {{ "target": "https://localhost:3001", "secure": false, "ws": true }}
+        }}"#
+    )?;
+
+    let configs = read_proxy_config(file.path())?;
+
+    assert_eq!(2, configs.len());
+    assert_eq!(vec!["/api"], configs[0].context);
+    assert!(configs[0].change_origin);
+    assert!(configs[0].secure);
+    assert!(!configs[1].secure);
+    assert!(configs[1].ws);
+
+    let api = ProxyRule::new(&configs[0])?;
+    assert!(api.matches("/api/users"));
+    assert!(!api.matches("/index.html"));
+    assert_eq!(
+        "http://localhost:3000/users?page=2",
+        api.target_url("/api/users?page=2")
+    );
+    assert_eq!("http://localhost:3000/", api.target_url("/api"));
+
+    let assets = ProxyRule::new(&configs[1])?;
+    assert!(assets.matches("/assets/i18n/de.json"));
+    assert!(!assets.matches("/assets/logo.png"));
+    assert_eq!(
+        "https://localhost:3001/assets/i18n/de.json",
+        assets.target_url("/assets/i18n/de.json")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn reads_proxy_config_array() -> anyhow::Result<()> {
+    common::setup();
+
+    let mut file = NamedTempFile::new()?;
+    write!(
+        file,
+        r#"[{{ "context": ["/auth", "/api"], "target": "http://localhost:3000" }}]"#
+    )?;
+
+    let configs = read_proxy_config(file.path())?;
+    let rule = ProxyRule::new(&configs[0])?;
+
+    assert!(rule.matches("/auth/login"));
+    assert!(rule.matches("/api"));
+    assert!(!rule.matches("/"));
+
+    Ok(())
+}
+
+#[test]
+fn keeps_proxy_path_rewrite_order() -> anyhow::Result<()> {
+    common::setup();
+
+    let mut file = NamedTempFile::new()?;
+    write!(
+        file,
+        r#"{{ "/api": {{ "target": "http://localhost:3000", "pathRewrite": {{ "^/api/legacy": "/old", "^/api": "" }} }} }}"#
+    )?;
+
+    let configs = read_proxy_config(file.path())?;
+    let rule = ProxyRule::new(&configs[0])?;
+
+    // the first matching rewrite is used, in the order of the file
+    assert_eq!(
+        "http://localhost:3000/old/users",
+        rule.target_url("/api/legacy/users")
+    );
+    assert_eq!("http://localhost:3000/users", rule.target_url("/api/users"));
+
+    Ok(())
+}
+
+#[test]
+fn checks_proxy_config() -> anyhow::Result<()> {
+    common::setup();
+
+    let config = serde_json::from_value(json!({
+        "context": ["/api"],
+        "target": "http://localhost:3000",
+        "pathRewrite": { "^/api(": "" },
+    }))?;
+    assert!(check_proxy(&[config]).is_err());
+    assert!(check_proxy(&[]).is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn refreshes_stylesheets_only_for_css_changes() {
+    common::setup();
+
+    let css = vec![
+        PathBuf::from("dist/styles.css"),
+        PathBuf::from("dist/theme.css"),
+    ];
+    let mixed = vec![
+        PathBuf::from("dist/styles.css"),
+        PathBuf::from("dist/main.js"),
+    ];
+
+    assert_eq!(Reload::Css, Reload::for_changes(&css));
+    assert_eq!(Reload::Page, Reload::for_changes(&mixed));
+    assert_eq!(Reload::Page, Reload::for_changes(&[]));
+}
+
+#[test]
+fn injects_reload_client() {
+    common::setup();
+
+    let html = inject_client("<html><body><app-root></app-root></body></html>");
+    assert!(html.starts_with("<html><body><app-root></app-root><script>"));
+    assert!(html.ends_with("</script>\n</body></html>"));
+    assert!(html.contains("/__rung/livereload"));
+
+    assert!(inject_client("<p>partial</p>").starts_with("<p>partial</p><script>"));
+}
+
+#[test]
+fn reads_headers_config() -> anyhow::Result<()> {
+    common::setup();
+
+    let mut file = NamedTempFile::new()?;
+    write!(
+        file,
+        r#"{{
+            "rules": [{{ "source": "**/*.js", "headers": {{ "Cache-Control": "max-age=60" }} }}],
+            "contentSecurityPolicy": "default-src 'self'",
+            "cors": {{ "origins": ["http://localhost:4200"], "credentials": true }},
+            "securityHeaders": true
+        }}"#
+    )?;
+
+    let config = read_headers_config(file.path())?;
+    assert_eq!("**/*.js", config.rules[0].source);
+    assert_eq!(
+        Some("default-src 'self'"),
+        config.content_security_policy.as_deref()
+    );
+
+    let headers = Headers::new(&config, false)?;
+    assert_eq!(
+        Some(String::from("http://localhost:4200")),
+        headers.allowed_origin("http://localhost:4200")
+    );
+    assert_eq!(None, headers.allowed_origin("http://example.com"));
+
+    Ok(())
+}
+
+#[test]
+fn rejects_invalid_headers() -> anyhow::Result<()> {
+    common::setup();
+
+    assert_eq!(
+        (String::from("X-Frame-Options"), String::from("DENY")),
+        parse_header("X-Frame-Options: DENY")?
+    );
+    assert!(parse_header("X-Frame-Options").is_err());
+
+    let mut file = NamedTempFile::new()?;
+    write!(
+        file,
+        r#"{{ "rules": [{{ "source": "/**", "headers": {{ "Bad Name": "x" }} }}] }}"#
+    )?;
+    assert!(read_headers_config(file.path()).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn describes_csp_violations() {
+    common::setup();
+
+    let legacy = json!({
+        "csp-report": {
+            "document-uri": "http://localhost:8080/",
+            "violated-directive": "script-src-elem",
+            "blocked-uri": "https://cdn.example.com/lib.js"
+        }
+    });
+    let reporting_api = json!([{
+        "type": "csp-violation",
+        "body": {
+            "documentURL": "http://localhost:8080/",
+            "effectiveDirective": "style-src-elem",
+            "blockedURL": "inline"
+        }
+    }]);
+
+    assert_eq!(
+        vec![
+            "`script-src-elem` blocked `https://cdn.example.com/lib.js` on http://localhost:8080/"
+        ],
+        describe_violations(&legacy)
+    );
+    assert_eq!(
+        vec!["`style-src-elem` blocked `inline` on http://localhost:8080/"],
+        describe_violations(&reporting_api)
+    );
+}
+
+#[test]
+fn detects_hashed_file_names() {
+    common::setup();
+
+    assert!(is_hashed("main.3f2a1b4c5d6e7f80.js"));
+    assert!(is_hashed("styles.5c3b8e4f9a0d1e2f.css"));
+    assert!(is_hashed("chunk-5FGHKL2C.js"));
+    assert!(!is_hashed("main.js"));
+    assert!(!is_hashed("jquery-3.5.1.min.js"));
+    assert!(!is_hashed("favicon.ico"));
+    assert!(!is_hashed("app.decade.js"));
+    assert!(!is_hashed("report.20201019.json"));
+    assert!(!is_hashed("cafe.png"));
+
+    assert_eq!(Some(NO_CACHE), cache_control("/"));
+    assert_eq!(Some(NO_CACHE), cache_control("/index.html"));
+    assert_eq!(Some(NO_CACHE), cache_control("/deep/link"));
+    assert_eq!(Some(IMMUTABLE), cache_control("/main.3f2a1b4c5d6e7f80.js"));
+    assert_eq!(None, cache_control("/assets/logo.png"));
+}
+
+#[test]
+fn does_not_cache_missing_hashed_files() -> anyhow::Result<()> {
+    common::setup();
+
+    let dir = tempdir()?;
+    let file = dir.path().join("main.3f2a1b4c5d6e7f80.js");
+    std::fs::write(&file, "console.log(1)")?;
+
+    let cache_control = |file: Option<&Path>, res: HttpResponse| {
+        let mut res = TestRequest::with_uri("/main.3f2a1b4c5d6e7f80.js")
+            .to_srv_request()
+            .into_response(res);
+        set_cache_headers(file, &mut res);
+        res.headers().get(header::CACHE_CONTROL).cloned().unwrap()
+    };
+
+    assert_eq!(
+        IMMUTABLE,
+        cache_control(Some(&file), HttpResponse::Ok().finish())
+    );
+    assert_eq!(
+        NO_CACHE,
+        cache_control(None, HttpResponse::NotFound().finish())
+    );
+    // the fallback page served during a rebuild
+    assert_eq!(NO_CACHE, cache_control(None, HttpResponse::Ok().finish()));
+    assert_eq!(
+        NO_CACHE,
+        cache_control(Some(&file), HttpResponse::InternalServerError().finish())
+    );
+    Ok(())
+}
+
+#[test]
+fn negotiates_precompressed_files() -> anyhow::Result<()> {
+    common::setup();
+
+    assert_eq!(vec!["br", "gzip"], accepted_encodings("gzip, deflate, br"));
+    assert_eq!(vec!["gzip", "br"], accepted_encodings("br;q=0.5, gzip"));
+    assert_eq!(vec!["gzip"], accepted_encodings("*, br;q=0"));
+    assert!(accepted_encodings("identity").is_empty());
+
+    let dir = tempdir()?;
+    let file = dir.path().join("main.js");
+    std::fs::write(&file, "console.log(1)")?;
+    std::fs::write(dir.path().join("main.js.gz"), "")?;
+
+    assert_eq!(
+        Some(dir.path().join("main.js.gz")),
+        precompressed(&file, "gzip, br").map(|(path, _)| path)
+    );
+    assert_eq!(None, precompressed(&file, "br"));
+
+    Ok(())
+}
+
+#[test]
+fn finds_mounts_by_prefix() {
+    common::setup();
+
+    let mounts = vec![
+        Mount::new("/", "dist/shell"),
+        Mount::new("/admin/", "dist/admin"),
+    ];
+
+    assert_eq!("/admin", mounts[1].prefix);
+    assert_eq!(Some("/users"), mounts[1].relative_path("/admin/users"));
+    assert_eq!(Some(""), mounts[1].relative_path("/admin"));
+    assert_eq!(None, mounts[1].relative_path("/administrator"));
+
+    assert_eq!(Some(&mounts[1]), find_mount(&mounts, "/admin/main.js"));
+    assert_eq!(Some(&mounts[0]), find_mount(&mounts, "/administrator"));
+}
+
+#[test]
+fn resolves_project_build_outputs() -> anyhow::Result<()> {
+    common::setup();
+
+    let mut file = NamedTempFile::new()?;
+    write!(
+        file,
+        r#"{{
+            "version": 1,
+            "projects": {{
+                "app": {{
+                    "projectType": "application",
+                    "i18n": {{
+                        "sourceLocale": "en-US",
+                        "locales": {{ "de": {{ "translation": "src/locale/messages.de.xlf", "baseHref": "/deutsch/" }} }}
+                    }},
+                    "architect": {{
+                        "build": {{
+                            "options": {{ "outputPath": "dist/app", "baseHref": "/shop/" }},
+                            "configurations": {{
+                                "production": {{ "deployUrl": "static/" }},
+                                "localized": {{ "outputPath": {{ "base": "dist/localized" }}, "localize": true }}
+                            }},
+                            "defaultConfiguration": "production"
+                        }}
+                    }}
+                }}
+            }}
+        }}"#
+    )?;
+
+    let config = read_config(file.path().to_path_buf())?;
+    let project = &config.projects.as_ref().unwrap()["app"];
+
+    assert_eq!(
+        vec![BuildOutput {
+            dir: PathBuf::from("dist/app"),
+            base_href: String::from("/shop/"),
+            deploy_url: Some(String::from("static/")),
+            locale: None,
+        }],
+        project.build_outputs(None)?
+    );
+
+    let outputs = project.build_outputs(Some("localized"))?;
+    assert_eq!(2, outputs.len());
+    assert_eq!(
+        PathBuf::from("dist/localized/browser/en-US"),
+        outputs[0].dir
+    );
+    assert_eq!("/shop/en-US/", outputs[0].base_href);
+    assert_eq!(PathBuf::from("dist/localized/browser/de"), outputs[1].dir);
+    assert_eq!("/shop/deutsch/", outputs[1].base_href);
+
+    Ok(())
+}
+
+#[test]
+fn resolves_application_builder_outputs() -> anyhow::Result<()> {
+    common::setup();
+
+    let mut file = NamedTempFile::new()?;
+    write!(
+        file,
+        r#"{{
+            "version": 1,
+            "projects": {{
+                "app": {{
+                    "projectType": "application",
+                    "architect": {{
+                        "build": {{
+                            "builder": "@angular-devkit/build-angular:application",
+                            "options": {{ "outputPath": "dist/app" }}
+                        }}
+                    }}
+                }},
+                "legacy": {{
+                    "projectType": "application",
+                    "architect": {{
+                        "build": {{
+                            "builder": "@angular-devkit/build-angular:browser",
+                            "options": {{ "outputPath": "dist/legacy" }}
+                        }}
+                    }}
+                }}
+            }}
+        }}"#
+    )?;
+
+    let config = read_config(file.path().to_path_buf())?;
+    let projects = config.projects.as_ref().unwrap();
+
+    assert_eq!(
+        PathBuf::from("dist/app/browser"),
+        projects["app"].build_outputs(None)?[0].dir
+    );
+    assert_eq!(
+        PathBuf::from("dist/legacy"),
+        projects["legacy"].build_outputs(None)?[0].dir
+    );
+
+    Ok(())
+}
+
+#[test]
+fn parses_mounts() -> anyhow::Result<()> {
+    common::setup();
+
+    let mount = Mount::parse("/shop/=dist/shop,spa,index=main.html", &Fallback::Strict)?;
+    assert_eq!("/shop", mount.prefix);
+    assert_eq!(PathBuf::from("dist/shop"), mount.root_dir);
+    assert_eq!("main.html", mount.index_file);
+    assert_eq!(Fallback::Spa, mount.fallback);
+
+    let mount = Mount::parse("/=dist/shell", &Fallback::Strict)?;
+    assert_eq!("/", mount.prefix);
+    assert_eq!("index.html", mount.index_file);
+    assert_eq!(Fallback::Strict, mount.fallback);
+
+    let mount = Mount::parse("/admin=dist/admin,not-found=404.html", &Fallback::Index)?;
+    assert_eq!(
+        Fallback::NotFound(PathBuf::from("404.html")),
+        mount.fallback
+    );
+
+    assert!(Mount::parse("admin=dist/admin", &Fallback::Index).is_err());
+    assert!(Mount::parse("/admin=dist/admin,cache", &Fallback::Index).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn falls_through_to_free_port() -> anyhow::Result<()> {
+    common::setup();
+
+    assert_eq!(Ok(Port::Auto), "auto".parse());
+    assert_eq!(Ok(Port::Fixed(8080)), "8080".parse());
+    assert!("http".parse::<Port>().is_err());
+
+    let taken = TcpListener::bind("127.0.0.1:0")?;
+    let port = taken.local_addr()?.port();
+
+    let listener = bind_listener("127.0.0.1", Port::Fixed(port))?;
+    assert_ne!(port, listener.local_addr()?.port());
+
+    let listener = bind_listener("127.0.0.1", Port::Auto)?;
+    assert_ne!(0, listener.local_addr()?.port());
+
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use rung::serve::templates::{TemplateOptions, Templates};
+use rung::template::{parse_env, render, Escape};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+mod common;
+
+#[test]
+fn parses_env_files() -> anyhow::Result<()> {
+    common::setup();
+
+    let vars = parse_env(
+        r#"
+# the backend
+API_URL=https://api.example.com
+export APP_NAME="Demo \"app\""
+TITLE='Hello ${NAME}'
+EMPTY=
+FLAG=true # enabled
+"#,
+    )?;
+
+    assert_eq!("https://api.example.com", vars["API_URL"]);
+    assert_eq!("Demo \"app\"", vars["APP_NAME"]);
+    assert_eq!("Hello ${NAME}", vars["TITLE"]);
+    assert_eq!("", vars["EMPTY"]);
+    assert_eq!("true", vars["FLAG"]);
+
+    assert!(parse_env("API URL=x").is_err());
+    assert!(parse_env("just text").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn renders_placeholders() {
+    common::setup();
+
+    let mut vars = BTreeMap::new();
+    vars.insert(String::from("API_URL"), String::from("https://api/\"v1\""));
+    vars.insert(String::from("TITLE"), String::from("<Shop & Co>"));
+
+    let rendered = render(
+        r#"{ "apiUrl": "${API_URL}", "debug": ${DEBUG:-false}, "key": "${KEY}", "raw": "$${API_URL}" }"#,
+        &vars,
+        Escape::Json,
+    );
+    assert_eq!(
+        r#"{ "apiUrl": "https://api/\"v1\"", "debug": false, "key": "", "raw": "${API_URL}" }"#,
+        rendered.text
+    );
+    assert_eq!(vec![String::from("KEY")], rendered.missing);
+
+    let rendered = render("<title>${TITLE}</title>", &vars, Escape::Html);
+    assert_eq!("<title>&lt;Shop &amp; Co&gt;</title>", rendered.text);
+    assert!(rendered.missing.is_empty());
+
+    assert_eq!(
+        Escape::Json,
+        Escape::for_file(Path::new("assets/config.json"))
+    );
+    assert_eq!(Escape::Html, Escape::for_file(Path::new("index.html")));
+    assert_eq!(Escape::None, Escape::for_file(Path::new("env.js")));
+}
+
+#[test]
+fn matches_template_files() -> anyhow::Result<()> {
+    common::setup();
+
+    let templates = Templates::new(&TemplateOptions {
+        patterns: vec![
+            String::from("assets/config.json"),
+            String::from("**/index.html"),
+        ],
+        vars: BTreeMap::new(),
+    })?;
+
+    let root = Path::new("dist/app");
+    assert!(templates.matches(root, &root.join("assets/config.json")));
+    assert!(templates.matches(root, &root.join("index.html")));
+    assert!(templates.matches(root, &root.join("de/index.html")));
+    assert!(!templates.matches(root, &root.join("assets/i18n/config.json")));
+    assert!(!templates.matches(root, Path::new("other/assets/config.json")));
+
+    Ok(())
+}